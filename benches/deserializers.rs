@@ -0,0 +1,78 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use rzd_trains::{parse_schedule_reply, parse_train_reply, parse_trip_reply};
+
+thread_local! {
+    // Allocations of the current thread, so the threads of the harness are not counted.
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Allocator counting the new blocks, the grown ones are not new allocations.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Returns the number of the allocations made by the call on the current thread.
+fn allocations<T>(f: impl FnOnce() -> T) -> u64 {
+    let before = ALLOCATIONS.with(Cell::get);
+    drop(black_box(f()));
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const TRAIN_SCHEDULE: &str = include_str!("../tests/fixtures/train_schedule.json");
+const TRAIN_SCHEDULE_ELECTRIC: &str =
+    include_str!("../tests/fixtures/train_schedule_electric.json");
+const TRAIN_INFO: &str = include_str!("../tests/fixtures/train_info.json");
+const TRIP_INFO: &str = include_str!("../tests/fixtures/trip_info.json");
+
+fn deserializers(c: &mut Criterion) {
+    eprintln!(
+        "allocations: train_schedule {}, train_schedule_electric {}, train_info {}, trip_info {}",
+        allocations(|| parse_schedule_reply(TRAIN_SCHEDULE).unwrap()),
+        allocations(|| parse_schedule_reply(TRAIN_SCHEDULE_ELECTRIC).unwrap()),
+        allocations(|| parse_train_reply(TRAIN_INFO).unwrap()),
+        allocations(|| parse_trip_reply(TRIP_INFO).unwrap()),
+    );
+
+    let mut group = c.benchmark_group("deserializers");
+
+    group.throughput(Throughput::Bytes(TRAIN_SCHEDULE.len() as u64));
+    group.bench_function("train_schedule", |b| {
+        b.iter(|| parse_schedule_reply(black_box(TRAIN_SCHEDULE)).unwrap())
+    });
+
+    group.throughput(Throughput::Bytes(TRAIN_SCHEDULE_ELECTRIC.len() as u64));
+    group.bench_function("train_schedule_electric", |b| {
+        b.iter(|| parse_schedule_reply(black_box(TRAIN_SCHEDULE_ELECTRIC)).unwrap())
+    });
+
+    group.throughput(Throughput::Bytes(TRAIN_INFO.len() as u64));
+    group.bench_function("train_info", |b| {
+        b.iter(|| parse_train_reply(black_box(TRAIN_INFO)).unwrap())
+    });
+
+    group.throughput(Throughput::Bytes(TRIP_INFO.len() as u64));
+    group.bench_function("trip_info", |b| {
+        b.iter(|| parse_trip_reply(black_box(TRIP_INFO)).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, deserializers);
+criterion_main!(benches);
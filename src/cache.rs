@@ -0,0 +1,199 @@
+//! Cache of the replies revalidated with conditional requests.
+
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Statistics of the validation of the cached replies.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    revalidated: u64,
+    stored: u64,
+    unsupported: u64,
+}
+
+impl CacheStats {
+    /// Returns the number of the replies the server confirmed not modified,
+    /// the cached body was used for them.
+    #[inline]
+    pub fn revalidated(&self) -> u64 {
+        self.revalidated
+    }
+
+    /// Returns the number of the full replies stored with `ETag` or `Last-Modified`.
+    #[inline]
+    pub fn stored(&self) -> u64 {
+        self.stored
+    }
+
+    /// Returns the number of the replies without validators, they are not cached.
+    #[inline]
+    pub fn unsupported(&self) -> u64 {
+        self.unsupported
+    }
+
+    /// Returns the number of the requests passed through the cache.
+    pub fn requests(&self) -> u64 {
+        self.revalidated + self.stored + self.unsupported
+    }
+}
+
+// Cached reply with its validators.
+#[derive(Debug, Clone)]
+struct Entry {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    body: String,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    stats: CacheStats,
+}
+
+/// Cache of the replies keyed by the request URL.
+///
+/// A reply is stored only if the server sends `ETag` or `Last-Modified` with it,
+/// then the request is repeated with `If-None-Match` or `If-Modified-Since`
+/// and the cached body is used when the server answers `304 Not Modified`.
+/// The replies of the endpoints without validators are always got in full.
+///
+/// Only the single request searches are cached, e.g. the station codes,
+/// as the replies fetched by a request id are never the same.
+///
+/// ```rust,no_run
+/// use rzd_trains::{ReplyCache, RequestOptions, RzdClient, StationCodeSearch, StationList};
+/// use std::sync::Arc;
+///
+/// let cache = Arc::new(ReplyCache::new());
+/// let options = RequestOptions::new().with_cache(cache.clone());
+///
+/// let q = StationCodeSearch::new("москва").unwrap();
+/// let stations = RzdClient::<StationList>::get_with(&q, options).unwrap();
+/// println!("{:?}", cache.stats());
+/// ```
+#[derive(Debug, Default)]
+pub struct ReplyCache {
+    state: Mutex<State>,
+}
+
+impl ReplyCache {
+    pub fn new() -> Self {
+        ReplyCache::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the statistics of the validation.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Returns the number of the cached replies.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns true if no reply is cached.
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Removes the cached replies, the statistics are kept.
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    // Adds the validators of the cached reply to the headers of the request.
+    pub(crate) fn add_validators(&self, url: &str, headers: &mut HeaderMap) {
+        let state = self.lock();
+        let entry = match state.entries.get(url) {
+            Some(e) => e,
+            None => return,
+        };
+
+        if let Some(ref etag) = entry.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(ref last_modified) = entry.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+
+    // Returns the cached body when the server answered that it is not modified.
+    pub(crate) fn revalidate(&self, url: &str) -> Option<String> {
+        let mut state = self.lock();
+        let body = state.entries.get(url).map(|e| e.body.clone())?;
+        state.stats.revalidated += 1;
+        Some(body)
+    }
+
+    // Stores the reply if the server sent the validators with it.
+    pub(crate) fn store(&self, url: &str, headers: &HeaderMap, body: &str) {
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+        let mut state = self.lock();
+
+        if etag.is_none() && last_modified.is_none() {
+            state.entries.remove(url);
+            state.stats.unsupported += 1;
+            return;
+        }
+
+        state.entries.insert(
+            url.to_string(),
+            Entry {
+                etag,
+                last_modified,
+                body: body.to_string(),
+            },
+        );
+        state.stats.stored += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplyCache;
+    use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+
+    #[test]
+    fn reply_cache_test() {
+        let cache = ReplyCache::new();
+        let url = "https://pass.rzd.ru/suggester?stationNamePart=%D0%9C%D0%9E%D0%A1";
+
+        let mut headers = HeaderMap::new();
+        cache.add_validators(url, &mut headers);
+        assert!(headers.is_empty());
+        assert_eq!(cache.revalidate(url), None);
+
+        cache.store(url, &HeaderMap::new(), "[]");
+        assert!(cache.is_empty());
+
+        let mut reply = HeaderMap::new();
+        reply.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        cache.store(url, &reply, "[1]");
+        assert_eq!(cache.len(), 1);
+
+        cache.add_validators(url, &mut headers);
+        assert_eq!(headers.get(IF_NONE_MATCH).unwrap(), "\"v1\"");
+        assert!(headers.get(IF_MODIFIED_SINCE).is_none());
+        assert_eq!(cache.revalidate(url).as_deref(), Some("[1]"));
+
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.revalidated(), stats.stored(), stats.unsupported()),
+            (1, 1, 1)
+        );
+        assert_eq!(stats.requests(), 3);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), stats);
+    }
+}
@@ -1,18 +1,94 @@
-use reqwest::header::{HeaderMap, HeaderValue, COOKIE, REFERER, USER_AGENT};
-use reqwest::{blocking::Response, cookie::Cookie};
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, LOCATION, REFERER, SET_COOKIE,
+    USER_AGENT,
+};
+use reqwest::{blocking::Response, cookie::Cookie, redirect::Policy, Proxy, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
-use std::thread;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, fmt::Display};
+use url::form_urlencoded::{self, Serializer};
+use url::Url;
 
-use crate::error::Error;
-use crate::Result;
+use crate::cache::ReplyCache;
+use crate::clock::{Clock, Sleeper, SystemClock};
+use crate::error::{Error, HttpFailure, RzdErrorSet};
+use crate::{Lang, Result, RzdStationCode, TrainDate};
 
 const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
 
 const RZD_REFERER: &str = "rzd.ru";
 
+const RZD_TIMETABLE_URL: &str = "https://pass.rzd.ru/timetable/public/ru";
+
+const RZD_TIMETABLE_URL_EN: &str = "https://pass.rzd.ru/timetable/public/en";
+
+const RZD_SUGGESTER_URL: &str = "https://pass.rzd.ru/suggester";
+
+const RZD_BOOKING_URL: &str = "https://ticket.rzd.ru/searchresults/v/1";
+
+// Pause before each try to get the data by a request id.
+const POLL_DELAY: Duration = Duration::from_millis(1500);
+
+// Number of tries to get the data by one request id.
+const POLL_TRIES: u32 = 3;
+
+// The longest `Retry-After` delay the client waits for before repeating a request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+// The default maximum number of characters of the logged bodies.
+const PAYLOAD_LIMIT: usize = 4096;
+
+// Number of the new request ids the server may issue while the data is polled.
+const MAX_REISSUED_IDS: u32 = 3;
+
+// The longest pause between the repeated requests by default.
+const MAX_BACKOFF_DELAY: Duration = Duration::from_secs(30);
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+/// Layers of the RZD server, each one returns its own kind of data.
+pub enum RzdLayer {
+    /// Info about the train cars and free seats, the layer id 5764.
+    TrainInfo = 5764,
+    /// Schedule of the trains between two stations, the layer id 5827.
+    TrainSchedule = 5827,
+    /// Stops of the train, the layer id 5804.
+    TripStops = 5804,
+    /// Station codes search by part of the name.
+    /// It is not a timetable layer and has no id.
+    Suggester = 0,
+}
+
+impl RzdLayer {
+    /// Returns the layer id passed in the `layer_id` query parameter.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            RzdLayer::Suggester => None,
+            _ => Some(*self as u32),
+        }
+    }
+
+    /// Returns the URL of the endpoint serving the layer.
+    pub fn url(&self) -> &'static str {
+        self.url_for(Lang::default())
+    }
+
+    /// Returns the URL of the endpoint serving the layer with the names in the language.
+    /// The suggester takes the language as a query parameter.
+    pub fn url_for(&self, lang: Lang) -> &'static str {
+        match (self, lang) {
+            (RzdLayer::Suggester, _) => RZD_SUGGESTER_URL,
+            (_, Lang::Ru) => RZD_TIMETABLE_URL,
+            (_, Lang::En) => RZD_TIMETABLE_URL_EN,
+        }
+    }
+}
+
 // Some requests get a response with data at once
 // and some get a response with identifier of the answer.
 #[derive(PartialEq)]
@@ -22,165 +98,1745 @@ pub enum RzdQueryType {
     WithId,
 }
 
-// Identifier returned by the server in response to some requests.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct RzdRequestId(u64);
+/// Identifier returned by the server in response to some requests.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RzdRequestId(u64);
+
+impl RzdRequestId {
+    pub fn new(id: u64) -> Self {
+        RzdRequestId(id)
+    }
+
+    /// Performs the conversion into digits.
+    #[inline]
+    pub fn to_uint(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for RzdRequestId {
+    fn default() -> Self {
+        RzdRequestId(0)
+    }
+}
+
+impl Display for RzdRequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Query parameters of a request to the server.
+// The values are percent-encoded while building the URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RzdQuery {
+    layer: RzdLayer,
+    url: &'static str,
+    params: Vec<(&'static str, String)>,
+}
+
+impl RzdQuery {
+    // Creates a query to the layer, the layer id goes first.
+    pub fn new(layer: RzdLayer) -> Self {
+        let query = RzdQuery {
+            layer,
+            url: layer.url(),
+            params: vec![],
+        };
+
+        match layer.id() {
+            Some(id) => query.param("layer_id", id),
+            None => query,
+        }
+    }
+
+    // Sets the language of the names returned by the server.
+    pub fn lang(mut self, lang: Lang) -> Self {
+        self.url = self.layer.url_for(lang);
+        self
+    }
+
+    // Appends a parameter to the query.
+    pub fn param(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    // Builds the URL from the address of the layer and the parameters.
+    pub fn to_url(&self) -> String {
+        let query = Serializer::new(String::new())
+            .extend_pairs(self.params.iter().map(|(n, v)| (*n, v.as_str())))
+            .finish();
+
+        let url = format!("{}?{}", self.url, query);
+        debug_assert!(
+            self.is_encoded(&url),
+            "query parameters are mangled: {}",
+            url
+        );
+        url
+    }
+
+    // Checks that the parameters decoded from the URL are exactly the ones of the query,
+    // i.e. no value breaks out of its parameter or loses characters.
+    fn is_encoded(&self, url: &str) -> bool {
+        let query = match url.split_once('?') {
+            Some((_, query)) => query,
+            None => return self.params.is_empty(),
+        };
+
+        form_urlencoded::parse(query.as_bytes())
+            .map(|(n, v)| (n.into_owned(), v.into_owned()))
+            .eq(self.params.iter().map(|(n, v)| (n.to_string(), v.clone())))
+    }
+}
+
+// Returns the URL of the first request made to get data for the search.
+pub(crate) fn request_url<T, U>(search: &U) -> Result<Url>
+where
+    U: RzdClientInterface<T>,
+{
+    let url = request_target::<T, U>(search);
+    Url::parse(&url).map_err(|_| Error::ParseUrlError(url))
+}
+
+// Returns the address of the first request made for the search as it is sent,
+// e.g. to describe the failed search.
+pub(crate) fn request_target<T, U>(search: &U) -> String
+where
+    U: RzdClientInterface<T>,
+{
+    match search.query_type() {
+        RzdQueryType::Simple => search.request_data(RzdRequestId::default()),
+        RzdQueryType::WithId => search.request_id(),
+    }
+}
+
+// Builds the link to the ticket purchase on rzd.ru, `None` if a station is unknown.
+// The page lists the trains between the stations at the date, the train is picked by the number.
+pub(crate) fn booking_url(
+    leaving_code: RzdStationCode,
+    arriving_code: RzdStationCode,
+    date: TrainDate,
+    train_number: &str,
+) -> Option<Url> {
+    if leaving_code == RzdStationCode::default() || arriving_code == RzdStationCode::default() {
+        return None;
+    }
+
+    let mut url = Url::parse(RZD_BOOKING_URL).ok()?;
+    url.path_segments_mut()
+        .ok()?
+        .push(&leaving_code.to_string())
+        .push(&arriving_code.to_string())
+        .push(&date.0.format("%Y-%m-%d").to_string());
+    url.query_pairs_mut().append_pair("train", train_number);
+    Some(url)
+}
+
+pub trait RzdClientInterface<T> {
+    fn query_type(&self) -> RzdQueryType;
+    fn layer(&self) -> RzdLayer;
+
+    fn request_id(&self) -> String;
+    fn request_data(&self, id: RzdRequestId) -> String;
+
+    fn deserialize_reply_id(&self, body: &str) -> Result<Option<RzdRequestId>>;
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<T>>;
+
+    // Checks the search before sending the request not to get an opaque error of the server.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Cookies of the server session captured while getting data.
+/// Related searches, e.g. a schedule and then a train, may share one session
+/// to make the server issue less request ids.
+/// The token may be stored with `serde` and restored later.
+/// The values of the cookies are redacted in the `Debug` output.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionToken(String);
+
+impl fmt::Debug for SessionToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SessionToken")
+            .field(&redact_cookies(&self.0))
+            .finish()
+    }
+}
+
+impl SessionToken {
+    /// Returns true if no session is established yet.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Updates the session cookies with the new ones returned by the server.
+    fn merge(&mut self, cookies: &str) {
+        let mut all: BTreeMap<&str, &str> = BTreeMap::new();
+        for c in self.0.split("; ").chain(cookies.split("; ")) {
+            if let Some((name, value)) = c.split_once('=') {
+                all.insert(name, value);
+            }
+        }
+
+        self.0 = all
+            .into_iter()
+            .map(|(n, v)| format!("{}={}", n, v))
+            .collect::<Vec<String>>()
+            .join("; ");
+    }
+}
+
+/// Options of the requests made to get data for a search.
+/// The layers have very different latency, so the timeout may be set for each of them.
+/// The clock and the sleeper may be replaced, e.g. to run tests without waiting.
+#[derive(Clone)]
+pub struct RequestOptions {
+    timeout: Option<Duration>,
+    layer_timeouts: Vec<(RzdLayer, Duration)>,
+    extra_headers: Vec<(String, String)>,
+    cookies: String,
+    proxy: Option<String>,
+    no_proxy: bool,
+    correlation_id: Option<String>,
+    payload_sink: Option<Arc<dyn PayloadSink>>,
+    payload_limit: usize,
+    cache: Option<Arc<ReplyCache>>,
+    clock: Arc<dyn Clock>,
+    sleeper: Arc<dyn Sleeper>,
+    backoff: Option<Backoff>,
+    deadline: Option<Duration>,
+    url_rewriter: Option<Arc<dyn UrlRewriter>>,
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        RequestOptions {
+            timeout: None,
+            layer_timeouts: vec![],
+            extra_headers: vec![],
+            cookies: String::new(),
+            proxy: None,
+            no_proxy: false,
+            correlation_id: None,
+            payload_sink: None,
+            payload_limit: PAYLOAD_LIMIT,
+            cache: None,
+            clock: Arc::new(SystemClock),
+            sleeper: Arc::new(SystemClock),
+            backoff: None,
+            deadline: None,
+            url_rewriter: None,
+        }
+    }
+}
+
+impl fmt::Debug for RequestOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestOptions")
+            .field("timeout", &self.timeout)
+            .field("layer_timeouts", &self.layer_timeouts)
+            .field("extra_headers", &self.extra_headers)
+            .field("cookies", &redact_cookies(&self.cookies))
+            .field("proxy", &self.proxy)
+            .field("no_proxy", &self.no_proxy)
+            .field("correlation_id", &self.correlation_id)
+            .field("payload_limit", &self.payload_limit)
+            .field("backoff", &self.backoff)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        RequestOptions::default()
+    }
+
+    /// Adds a header sent with every request, e.g. a header of a corporate gateway.
+    /// A header with an invalid name or value is skipped with a warning.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        match parse_header(name, value) {
+            Some(_) => self
+                .extra_headers
+                .push((name.to_string(), value.to_string())),
+            None => warn!("{}invalid header {} is skipped", self.log_prefix(), name),
+        }
+        self
+    }
+
+    /// Sets the cookies sent with every request, e.g. `name=value; name2=value2`.
+    /// They are previously acquired cookies of the server, the session ones take precedence.
+    pub fn with_cookies(mut self, cookies: &str) -> Self {
+        self.cookies = cookies.to_string();
+        self
+    }
+
+    /// Returns the headers sent with every request.
+    #[inline]
+    pub fn extra_headers(&self) -> &[(String, String)] {
+        &self.extra_headers
+    }
+
+    /// Returns the cookies sent with every request.
+    #[inline]
+    pub fn cookies(&self) -> &str {
+        &self.cookies
+    }
+
+    /// Sets the proxy of every request, e.g. `http://proxy.local:3128`.
+    /// By default the proxies of `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// environment variables are used.
+    pub fn with_proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        self.no_proxy = false;
+        self
+    }
+
+    /// Disables the proxies, including the ones of the environment variables.
+    pub fn no_proxy(mut self) -> Self {
+        self.proxy = None;
+        self.no_proxy = true;
+        self
+    }
+
+    /// Returns the proxy set explicitly.
+    #[inline]
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Returns true if the proxies are disabled.
+    #[inline]
+    pub fn is_no_proxy(&self) -> bool {
+        self.no_proxy
+    }
+
+    /// Sets the id of the request set by the caller, e.g. to trace a complaint of a user.
+    /// The id is printed in the log and kept in the returned errors.
+    pub fn with_correlation_id(mut self, id: &str) -> Self {
+        self.correlation_id = Some(id.to_string());
+        self
+    }
+
+    /// Returns the id of the request set by the caller.
+    #[inline]
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Sets the hook rewriting the URL of every request before it is sent.
+    pub fn with_url_rewriter(mut self, rewriter: Arc<dyn UrlRewriter>) -> Self {
+        self.url_rewriter = Some(rewriter);
+        self
+    }
+
+    // Returns the URL the request is sent to, the query is kept if it is not a valid URL.
+    fn final_url(&self, query: &str) -> String {
+        match (&self.url_rewriter, Url::parse(query)) {
+            (Some(rewriter), Ok(url)) => rewriter.rewrite(url).to_string(),
+            _ => query.to_string(),
+        }
+    }
+
+    /// Sets the sink receiving the requests and the replies of the server.
+    pub fn with_payload_sink(mut self, sink: Arc<dyn PayloadSink>) -> Self {
+        self.payload_sink = Some(sink);
+        self
+    }
+
+    /// Sets the maximum number of characters of the bodies passed to the sink and the log,
+    /// by default it is 4096.
+    pub fn with_payload_limit(mut self, limit: usize) -> Self {
+        self.payload_limit = limit;
+        self
+    }
+
+    /// Sets the cache of the replies revalidated with conditional requests.
+    /// The cache may be shared by the options of many searches.
+    pub fn with_cache(mut self, cache: Arc<ReplyCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the cache of the replies.
+    #[inline]
+    pub fn cache(&self) -> Option<&ReplyCache> {
+        self.cache.as_deref()
+    }
+
+    // Passes the payload to the sink and writes it to the log at the trace level.
+    // The cookies are redacted and the body is cut to the size limit.
+    fn record(&self, kind: PayloadKind, url: &str, headers: &HeaderMap, body: &str) {
+        if self.payload_sink.is_none() && !log_enabled!(log::Level::Trace) {
+            return;
+        }
+
+        let headers = redact_headers(headers);
+        let body = cut_body(body, self.payload_limit);
+        trace!(
+            "{}{:?} {} {:?}: {}",
+            self.log_prefix(),
+            kind,
+            url,
+            headers,
+            body
+        );
+
+        if let Some(ref sink) = self.payload_sink {
+            sink.record(&Payload {
+                kind,
+                url,
+                headers: &headers,
+                body: &body,
+            });
+        }
+    }
+
+    // Returns the clock of the options.
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    // Returns the sleeper of the options.
+    pub(crate) fn sleeper(&self) -> &dyn Sleeper {
+        self.sleeper.as_ref()
+    }
+
+    // Returns the prefix of the log messages with the correlation id.
+    fn log_prefix(&self) -> String {
+        match self.correlation_id {
+            Some(ref id) => format!("[{}] ", id),
+            None => String::new(),
+        }
+    }
+
+    /// Sets the clock the delays asked by the server are counted by.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets the sleeper pausing the requests while the server prepares the data.
+    pub fn with_sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    /// Sets the timeout of every request, by default it is 30 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of the requests to the layer, it overrides the common one.
+    pub fn with_layer_timeout(mut self, layer: RzdLayer, timeout: Duration) -> Self {
+        self.layer_timeouts.retain(|(l, _)| *l != layer);
+        self.layer_timeouts.push((layer, timeout));
+        self
+    }
+
+    /// Returns the timeout of every request.
+    #[inline]
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the timeout of the requests to the layer.
+    pub fn timeout_for(&self, layer: RzdLayer) -> Option<Duration> {
+        self.layer_timeouts
+            .iter()
+            .find(|(l, _)| *l == layer)
+            .map(|(_, t)| *t)
+            .or(self.timeout)
+    }
+
+    /// Sets the repeating of the requests failed on the way to the server,
+    /// see `Error::is_transient`. By default such requests are not repeated.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Returns the repeating of the failed requests.
+    #[inline]
+    pub fn backoff(&self) -> Option<&Backoff> {
+        self.backoff.as_ref()
+    }
+
+    /// Sets the time the search has to get the data in, counted from its start.
+    /// Both the repeated requests and the polling by the request id stop at it.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the time the search has to get the data in.
+    #[inline]
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    // Returns the moment the search started now has to finish at.
+    fn deadline_from_now(&self) -> Option<DateTime<Utc>> {
+        let deadline = chrono::Duration::from_std(self.deadline?).ok()?;
+        Some(self.clock.now() + deadline)
+    }
+}
+
+/// Exponential backoff of the requests failed on the way to the server,
+/// it is separate from the polling of the data by the request id.
+///
+/// ```rust
+/// # use rzd_trains::Backoff;
+/// # use std::time::Duration;
+/// #
+/// let backoff = Backoff::new(4, Duration::from_millis(500));
+///
+/// assert_eq!(backoff.delay(0), Duration::from_millis(500));
+/// assert_eq!(backoff.delay(2), Duration::from_secs(2));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    factor: u32,
+}
+
+impl Backoff {
+    /// Takes the maximum number of the attempts including the first one
+    /// and the pause before the first repeat, the next pauses are twice as long.
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Backoff {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+            max_delay: MAX_BACKOFF_DELAY,
+            factor: 2,
+        }
+    }
+
+    /// Sets the longest pause, by default it is 30 seconds.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the number the pause is multiplied by after each repeat.
+    pub fn with_factor(mut self, factor: u32) -> Self {
+        self.factor = factor.max(1);
+        self
+    }
+
+    /// Returns the maximum number of the attempts including the first one.
+    #[inline]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the pause before the repeat, the first repeat is 0.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = self.factor.saturating_pow(retry);
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// Passes the anti-bot challenge the server redirects to instead of a reply.
+pub trait ChallengeResolver {
+    /// Takes the URL of the challenge page and returns the cookies passing it,
+    /// e.g. `name=value; name2=value2`, or `None` if the challenge can't be passed.
+    fn resolve(&self, url: &str) -> Option<String>;
+}
+
+impl<F> ChallengeResolver for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn resolve(&self, url: &str) -> Option<String> {
+        self(url)
+    }
+}
+
+/// Rewrites the URL of every request before it is sent, e.g. to add the parameters
+/// or the signature required by a proxy, or to try an alternative endpoint.
+///
+/// ```rust
+/// # use rzd_trains::{RequestOptions, Url};
+/// # use std::sync::Arc;
+/// #
+/// let options = RequestOptions::new().with_url_rewriter(Arc::new(|mut url: Url| {
+///     url.set_host(Some("rzd-proxy.local")).unwrap();
+///     url.query_pairs_mut().append_pair("key", "secret");
+///     url
+/// }));
+/// ```
+pub trait UrlRewriter: Send + Sync {
+    /// Takes the URL made by the search and returns the one to send the request to.
+    fn rewrite(&self, url: Url) -> Url;
+}
+
+impl<F> UrlRewriter for F
+where
+    F: Fn(Url) -> Url + Send + Sync,
+{
+    fn rewrite(&self, url: Url) -> Url {
+        self(url)
+    }
+}
+
+/// Direction of the payload.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PayloadKind {
+    /// The request sent to the server.
+    Request,
+    /// The reply of the server.
+    Response,
+}
+
+/// A request or a reply of the server passed to the `PayloadSink`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payload<'a> {
+    kind: PayloadKind,
+    url: &'a str,
+    headers: &'a [(String, String)],
+    body: &'a str,
+}
+
+impl Payload<'_> {
+    /// Returns the direction of the payload.
+    #[inline]
+    pub fn kind(&self) -> PayloadKind {
+        self.kind
+    }
+
+    /// Returns the URL of the request.
+    #[inline]
+    pub fn url(&self) -> &str {
+        self.url
+    }
+
+    /// Returns the headers, the values of the cookies are redacted.
+    #[inline]
+    pub fn headers(&self) -> &[(String, String)] {
+        self.headers
+    }
+
+    /// Returns the body cut to the size limit, it is empty for the requests.
+    #[inline]
+    pub fn body(&self) -> &str {
+        self.body
+    }
+}
+
+/// Receives the requests and the replies of the server, e.g. to dump them while debugging.
+pub trait PayloadSink: Send + Sync {
+    /// Takes the request or the reply.
+    fn record(&self, payload: &Payload);
+}
+
+/// Stage of the search the request is made at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RequestPhase {
+    /// The only request of the search, e.g. of the station codes.
+    Single,
+    /// The request of the id the data is prepared by.
+    RequestId,
+    /// The request of the data by the id, it is repeated until the data is ready.
+    Data,
+}
+
+/// Request a search would make, see `RzdClient::dry_run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRequest {
+    phase: RequestPhase,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+impl PlannedRequest {
+    /// Returns the stage of the search.
+    #[inline]
+    pub fn phase(&self) -> RequestPhase {
+        self.phase
+    }
+
+    /// Returns the URL of the request.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the headers, the values of the cookies are redacted.
+    #[inline]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+impl Display for PlannedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} GET {}", self.phase, self.url)?;
+        for (name, value) in self.headers.iter() {
+            write!(f, "\n\t{}: {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+// Data got from the server with the details of the request.
+struct Reply<T> {
+    data: T,
+    #[cfg(any(test, feature = "fixtures"))]
+    body: String,
+    request: String,
+    request_id: Option<RzdRequestId>,
+    attempts: u32,
+    polls: u32,
+    retries: u32,
+}
+
+/// Data got from the server with the details of the request it was got by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseMeta<T> {
+    data: T,
+    layer: RzdLayer,
+    request: String,
+    request_id: Option<RzdRequestId>,
+    attempts: u32,
+    polls: u32,
+    retries: u32,
+    duration: Duration,
+}
+
+impl<T> ResponseMeta<T> {
+    /// Returns the data.
+    #[inline]
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Performs the conversion into the data.
+    pub fn into_data(self) -> T {
+        self.data
+    }
+
+    /// Returns the layer the data is got from.
+    #[inline]
+    pub fn layer(&self) -> RzdLayer {
+        self.layer
+    }
+
+    /// Returns the URL of the request the data is got by, it holds the search parameters.
+    #[inline]
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    /// Returns the id of the reply issued by the server, if the layer uses it.
+    #[inline]
+    pub fn request_id(&self) -> Option<RzdRequestId> {
+        self.request_id
+    }
+
+    /// Returns the number of the requests made to get the data.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the number of the requests of the data by the reply id,
+    /// zero for the layers without the id.
+    #[inline]
+    pub fn polls(&self) -> u32 {
+        self.polls
+    }
+
+    /// Returns the number of the requests repeated after they failed on the way to the server,
+    /// see `RequestOptions::with_backoff`, or after the server asked to retry later.
+    #[inline]
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Returns the time spent to get the data, including the pauses between the requests.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Ticket of the data the server is still preparing, see `RzdClient::get_quick`.
+/// It holds the request id and the session the id was issued in.
+/// The ticket may be stored with `serde`, so the polling is resumed
+/// by another process, e.g. a worker of a stateless web service.
+/// The stored ticket holds the session cookies, it shouldn't be passed to the users,
+/// their values are redacted in the `Debug` output only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingTicket {
+    layer: RzdLayer,
+    request_id: RzdRequestId,
+    request: String,
+    session: SessionToken,
+    attempts: u32,
+}
+
+impl PendingTicket {
+    /// Returns the layer the data is requested from.
+    #[inline]
+    pub fn layer(&self) -> RzdLayer {
+        self.layer
+    }
+
+    /// Returns the id of the reply issued by the server.
+    #[inline]
+    pub fn request_id(&self) -> RzdRequestId {
+        self.request_id
+    }
+
+    /// Returns the number of the requests made to get the data.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// Data or the ticket to get it later, see `RzdClient::get_quick`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuickAnswer<T> {
+    /// The data is got.
+    Ready(T),
+    /// The server prepares the data, it is got later with `RzdClient::resume`.
+    Pending(PendingTicket),
+}
+
+/// The client gets data from the server.
+pub struct RzdClient<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> RzdClient<T> {
+    /// Takes a search query and makes a request to the server.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn get<U>(search: &U) -> Result<Option<T>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        RzdClient::get_with_session(search, &mut SessionToken::default())
+    }
+
+    /// Takes a search query and makes a request to the server with the options.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request,
+    /// e.g. it timed out, or received data couldn't be deserialized.
+    pub fn get_with<U>(search: &U, options: RequestOptions) -> Result<Option<T>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let mut session = SessionToken::default();
+        let result = RzdClient::fetch_with_options(search, &mut session, &options)?;
+        Ok(result.map(|r| r.data))
+    }
+
+    /// Makes the searches one by one with the options, a failed search doesn't stop the rest.
+    /// Returns the results in the order of the searches, `None` for the failed ones
+    /// and ones with nothing found, and the errors described by the URLs of their searches.
+    ///
+    /// ```rust,no_run
+    /// use rzd_trains::{RequestOptions, RouteList, RzdClient, TrainScheduleSearch, TrainType};
+    ///
+    /// let searches: Vec<TrainScheduleSearch> = (1..=3)
+    ///     .map(|d| TrainScheduleSearch::new(2004000, 2000000, (2022, 4, d), TrainType::Train, true))
+    ///     .collect();
+    ///
+    /// let (routes, errors) = RzdClient::<RouteList>::get_many(&searches, RequestOptions::new());
+    /// if !errors.is_empty() {
+    ///     eprintln!("{}", errors);
+    /// }
+    /// ```
+    pub fn get_many<U>(searches: &[U], options: RequestOptions) -> (Vec<Option<T>>, RzdErrorSet)
+    where
+        U: RzdClientInterface<T>,
+    {
+        let mut errors = RzdErrorSet::new();
+        let results = searches
+            .iter()
+            .map(
+                |search| match RzdClient::get_with(search, options.clone()) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        errors.push(request_target::<T, _>(search), e);
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        (results, errors)
+    }
+
+    /// Returns the requests the search would make without sending them,
+    /// e.g. to check the parameters. For the searches by a request id
+    /// the data is requested with `rid=0` standing for the id the server returns.
+    ///
+    /// ```rust
+    /// use rzd_trains::{RequestPhase, RouteList, RzdClient, TrainScheduleSearch, TrainType};
+    ///
+    /// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+    /// let requests = RzdClient::<RouteList>::dry_run(&q).unwrap();
+    ///
+    /// assert_eq!(requests.len(), 2);
+    /// assert_eq!(requests[0].phase(), RequestPhase::RequestId);
+    /// assert!(requests[1].url().contains("rid=0"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the search is invalid, e.g. the stations are the same.
+    pub fn dry_run<U>(search: &U) -> Result<Vec<PlannedRequest>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        RzdClient::dry_run_with(search, &RequestOptions::default())
+    }
+
+    /// Returns the requests the search would make with the options without sending them,
+    /// see `dry_run`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the search is invalid or a header of the options is invalid.
+    pub fn dry_run_with<U>(search: &U, options: &RequestOptions) -> Result<Vec<PlannedRequest>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        search.validate()?;
+        let session = SessionToken(options.cookies.clone());
+        let headers = redact_headers(&request_headers(&session, options)?);
+
+        let planned = |phase, url: String| PlannedRequest {
+            phase,
+            url: options.final_url(&url),
+            headers: headers.clone(),
+        };
+        let requests = match search.query_type() {
+            RzdQueryType::Simple => vec![planned(
+                RequestPhase::Single,
+                search.request_data(RzdRequestId::default()),
+            )],
+            RzdQueryType::WithId => vec![
+                planned(RequestPhase::RequestId, search.request_id()),
+                planned(
+                    RequestPhase::Data,
+                    search.request_data(RzdRequestId::default()),
+                ),
+            ],
+        };
+        Ok(requests)
+    }
+
+    /// Takes a search query and makes a request to the server within the session.
+    /// The session is established by the first request and updated by the following ones.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn get_with_session<U>(search: &U, session: &mut SessionToken) -> Result<Option<T>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let result = RzdClient::fetch_with_session(search, session)?;
+        Ok(result.map(|r| r.data))
+    }
+
+    // Does the same as `get_with_session` making the request with the options,
+    // for the results made of several searches.
+    pub(crate) fn get_with_session_options<U>(
+        search: &U,
+        session: &mut SessionToken,
+        options: &RequestOptions,
+    ) -> Result<Option<T>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let result = RzdClient::fetch_with_options(search, session, options)?;
+        Ok(result.map(|r| r.data))
+    }
+
+    /// Takes a search query and makes a request to the server within the session.
+    /// If the server asks to pass an anti-bot challenge, the `resolver` is called
+    /// and the request is repeated once with the cookies it returned.
+    ///
+    /// # Errors
+    ///
+    /// The method fails with `Error::BotProtection` if the challenge isn't passed,
+    /// or if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn get_with_resolver<U>(
+        search: &U,
+        session: &mut SessionToken,
+        resolver: &dyn ChallengeResolver,
+    ) -> Result<Option<T>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let result = match RzdClient::fetch_with_session(search, session) {
+            Err(Error::BotProtection(url)) => match resolver.resolve(&url) {
+                Some(cookies) => {
+                    session.merge(&cookies);
+                    RzdClient::fetch_with_session(search, session)?
+                }
+                None => return Err(Error::BotProtection(url)),
+            },
+            result => result?,
+        };
+        Ok(result.map(|r| r.data))
+    }
+
+    /// Takes a search query and makes a request to the server with the options.
+    /// The data is returned with the details of the request, e.g. to key a cache,
+    /// and the numbers of the polls and the retries made, e.g. to monitor the integration.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request,
+    /// e.g. it timed out, or received data couldn't be deserialized.
+    pub fn get_with_meta<U>(search: &U, options: RequestOptions) -> Result<Option<ResponseMeta<T>>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let started = options.clock.now();
+        let result = RzdClient::fetch_with_options(search, &mut SessionToken::default(), &options)?;
+        let duration = (options.clock.now() - started).to_std().unwrap_or_default();
+
+        Ok(result.map(|r| ResponseMeta {
+            data: r.data,
+            layer: search.layer(),
+            request: r.request,
+            request_id: r.request_id,
+            attempts: r.attempts,
+            polls: r.polls,
+            retries: r.retries,
+            duration,
+        }))
+    }
+
+    /// Takes a search query and makes a request to the server, but polls the data only once.
+    /// If the data isn't ready yet, the ticket is returned to get it later
+    /// with `RzdClient::resume`, e.g. a chat-bot replies at once
+    /// and shows the data on the next message of the user.
+    ///
+    /// ```rust,no_run
+    /// # use rzd_trains::{QuickAnswer, RequestOptions, RouteList, RzdClient};
+    /// # use rzd_trains::{TrainScheduleSearch, TrainType};
+    /// #
+    /// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+    ///
+    /// match RzdClient::<RouteList>::get_quick(&q, RequestOptions::new()).unwrap() {
+    ///     Some(QuickAnswer::Ready(list)) => println!("{}", list),
+    ///     Some(QuickAnswer::Pending(ticket)) => {
+    ///         // Later, e.g. on the next message.
+    ///         let answer = RzdClient::<RouteList>::resume(&q, ticket, RequestOptions::new());
+    ///         println!("{:?}", answer.unwrap());
+    ///     }
+    ///     None => println!("no trains"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn get_quick<U>(search: &U, options: RequestOptions) -> Result<Option<QuickAnswer<T>>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let mut session = SessionToken::default();
+        let result = Polling::new(search, &mut session, &options).answer();
+
+        match options.correlation_id {
+            Some(ref id) => result.map_err(|e| Error::Correlated(id.clone(), Box::new(e))),
+            None => result,
+        }
+    }
+
+    /// Takes the ticket returned by `RzdClient::get_quick` and polls the data once more
+    /// without a pause. If the data still isn't ready, a new ticket is returned.
+    /// The search must be the one the ticket was got for.
+    ///
+    /// # Errors
+    ///
+    /// The method fails with `Error::UnsupportedOperation` if the ticket is of another layer,
+    /// or if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn resume<U>(
+        search: &U,
+        ticket: PendingTicket,
+        options: RequestOptions,
+    ) -> Result<Option<QuickAnswer<T>>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        if ticket.layer != search.layer() {
+            return Err(Error::UnsupportedOperation);
+        }
+        let mut session = ticket.session;
+
+        let mut polling = Polling::new(search, &mut session, &options);
+        polling.started = true;
+        polling.resumed = true;
+        polling.request = ticket.request;
+        polling.request_id = ticket.request_id;
+        polling.attempts = ticket.attempts;
+        let result = polling.answer();
 
-impl RzdRequestId {
-    pub fn new(id: u64) -> Self {
-        RzdRequestId(id)
+        match options.correlation_id {
+            Some(ref id) => result.map_err(|e| Error::Correlated(id.clone(), Box::new(e))),
+            None => result,
+        }
     }
 
-    // Performs the conversion into digits.
-    #[inline]
-    pub fn to_uint(&self) -> u64 {
-        self.0
+    /// Takes a search query and returns the iterator over the states of the data polling,
+    /// e.g. to show the progress while the server prepares the data.
+    /// The iterator ends after the data or an error is returned.
+    ///
+    /// ```rust,no_run
+    /// # use rzd_trains::{PollState, RequestOptions, RouteList, RzdClient, SessionToken};
+    /// # use rzd_trains::{TrainScheduleSearch, TrainType};
+    /// #
+    /// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+    /// let (mut session, options) = (SessionToken::default(), RequestOptions::new());
+    ///
+    /// for state in RzdClient::<RouteList>::poll(&q, &mut session, &options) {
+    ///     match state.unwrap() {
+    ///         PollState::Pending(attempt) => println!("waiting, attempt {}", attempt),
+    ///         PollState::Ready(list) => println!("{}", list),
+    ///     }
+    /// }
+    /// ```
+    pub fn poll<'a, U>(
+        search: &'a U,
+        session: &'a mut SessionToken,
+        options: &'a RequestOptions,
+    ) -> Polling<'a, T, U>
+    where
+        U: RzdClientInterface<T>,
+    {
+        Polling::new(search, session, options)
     }
-}
 
-impl Default for RzdRequestId {
-    fn default() -> Self {
-        RzdRequestId(0)
+    // Returns the deserialized data and the raw reply it was taken from.
+    #[cfg(any(test, feature = "fixtures"))]
+    pub(crate) fn fetch<U>(search: &U) -> Result<Option<(T, String)>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        let result = RzdClient::fetch_with_session(search, &mut SessionToken::default())?;
+        Ok(result.map(|r| (r.data, r.body)))
     }
-}
 
-impl Display for RzdRequestId {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+    fn fetch_with_session<U>(search: &U, session: &mut SessionToken) -> Result<Option<Reply<T>>>
+    where
+        U: RzdClientInterface<T>,
+    {
+        RzdClient::fetch_with_options(search, session, &RequestOptions::default())
     }
-}
-
-pub trait RzdClientInterface<T> {
-    fn query_type(&self) -> RzdQueryType;
-
-    fn request_id(&self) -> String;
-    fn request_data(&self, id: RzdRequestId) -> String;
-
-    fn deserialize_reply_id(&self, response: Response) -> Result<Option<RzdRequestId>>;
-    fn deserialize_reply_data(&self, response: Response) -> Result<Option<T>>;
-}
-
-/// The client gets data from the server.
-pub struct RzdClient<T> {
-    _marker: PhantomData<T>,
-}
 
-impl<T> RzdClient<T> {
-    /// Takes a search query and makes a request to the server.
-    ///
-    /// # Errors
-    ///
-    /// The method fails if there was an error while processing request
-    /// or received data couldn't be deserialized.
-    pub fn get<U>(search: &U) -> Result<Option<T>>
+    fn fetch_with_options<U>(
+        search: &U,
+        session: &mut SessionToken,
+        options: &RequestOptions,
+    ) -> Result<Option<Reply<T>>>
     where
         U: RzdClientInterface<T>,
     {
-        match search.query_type() {
-            RzdQueryType::Simple => RzdClient::simple_request(search),
-            RzdQueryType::WithId => RzdClient::request_with_id(search),
+        if !options.cookies.is_empty() {
+            let mut cookies = SessionToken(options.cookies.clone());
+            cookies.merge(&session.0);
+            *session = cookies;
+        }
+
+        let result = match search.query_type() {
+            RzdQueryType::Simple => {
+                let deadline = options.deadline_from_now();
+                RzdClient::simple_request(search, session, options, deadline)
+            }
+            RzdQueryType::WithId => RzdClient::request_with_id(search, session, options),
+        };
+
+        match options.correlation_id {
+            Some(ref id) => result.map_err(|e| Error::Correlated(id.clone(), Box::new(e))),
+            None => result,
         }
     }
 
     // Getting data with a single request to the server.
-    fn simple_request<U>(search: &U) -> Result<Option<T>>
+    fn simple_request<U>(
+        search: &U,
+        session: &mut SessionToken,
+        options: &RequestOptions,
+        deadline: Option<DateTime<Utc>>,
+    ) -> Result<Option<Reply<T>>>
     where
         U: RzdClientInterface<T>,
     {
+        search.validate()?;
         let request = search.request_data(RzdRequestId::default());
-        debug!("request: {}", request);
+        debug!("{}request: {}", options.log_prefix(), request);
 
-        let result = send_blocking_request(&request, request_headers_default())?;
+        let mut headers = request_headers(session, options)?;
+        if let Some(ref cache) = options.cache {
+            cache.add_validators(&request, &mut headers);
+        }
+        let mut retries = 0;
+        let result = send_request(
+            &request,
+            headers,
+            options,
+            search.layer(),
+            deadline,
+            &mut retries,
+        )?;
 
-        let result = match result {
+        let body = match result {
             None => return Ok(None),
-            Some(r) => r,
+            Some(r) => {
+                session.merge(&get_cookies_string(&mut r.cookies()));
+                read_cached_body(&request, r, options)?
+            }
         };
 
-        match search.deserialize_reply_data(result)? {
-            Some(r) => Ok(Some(r)),
+        match search.deserialize_reply_data(&body)? {
+            Some(data) => Ok(Some(Reply {
+                data,
+                #[cfg(any(test, feature = "fixtures"))]
+                body,
+                request,
+                request_id: None,
+                attempts: 1,
+                polls: 0,
+                retries,
+            })),
             None => Ok(None),
         }
     }
 
     // Getting data with a additional request to the server.
-    fn request_with_id<U>(search: &U) -> Result<Option<T>>
+    fn request_with_id<U>(
+        search: &U,
+        session: &mut SessionToken,
+        options: &RequestOptions,
+    ) -> Result<Option<Reply<T>>>
     where
         U: RzdClientInterface<T>,
     {
-        let (reply_id, cookies) = RzdClient::get_reply_id(search)?;
-
-        let mut headers = request_headers_default();
-        let cookie_header = HeaderValue::from_bytes(cookies.as_bytes())?;
-        headers.insert(COOKIE, cookie_header);
-
-        let request = search.request_data(reply_id);
-        debug!("request: {}", request);
-
-        for _ in 1..4 {
-            // If server wasn't be on time to create an answer
-            // then it sends a new `RzdRequestId`.
-            thread::sleep(Duration::from_millis(1500));
-
-            let result = send_blocking_request(request.clone().as_ref(), headers.clone())?;
-
-            let result = match result {
-                None => return Ok(None),
-                Some(r) => r,
-            };
-
-            match search.deserialize_reply_data(result)? {
-                Some(r) => return Ok(Some(r)),
-                None => debug!("reply is incorrect"),
+        let mut polling = Polling::new(search, session, options);
+        while let Some(step) = polling.step()? {
+            if let Step::Ready(reply) = step {
+                return Ok(Some(reply));
             }
         }
-
-        Err(Error::RzdServerOverloaded)
+        Ok(None)
     }
 
-    fn get_reply_id<U>(search: &U) -> Result<(RzdRequestId, String)>
+    fn get_reply_id<U>(
+        search: &U,
+        session: &mut SessionToken,
+        options: &RequestOptions,
+        deadline: Option<DateTime<Utc>>,
+        retries: &mut u32,
+    ) -> Result<RzdRequestId>
     where
         U: RzdClientInterface<T>,
     {
+        search.validate()?;
         let request = search.request_id();
-        debug!("request: {}", request);
+        debug!("{}request: {}", options.log_prefix(), request);
 
-        let result = send_blocking_request(&request, request_headers_default())?;
+        let result = send_request(
+            &request,
+            request_headers(session, options)?,
+            options,
+            search.layer(),
+            deadline,
+            retries,
+        )?;
 
         let result = match result {
             None => return Err(Error::FailRzdResponse),
             Some(r) => r,
         };
 
-        let cookies = get_cookies_string(&mut result.cookies());
+        session.merge(&get_cookies_string(&mut result.cookies()));
+        let body = read_body(&request, result, options)?;
 
-        let reply_id = match search.deserialize_reply_id(result)? {
+        let reply_id = match search.deserialize_reply_id(&body)? {
             None => return Err(Error::FailRzdResponse),
             Some(r) => r,
         };
 
-        Ok((reply_id, cookies))
+        Ok(reply_id)
+    }
+}
+
+/// State of the data polling.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollState<T> {
+    /// The server prepares the data, the number of the tries made is kept.
+    Pending(u32),
+    /// The data is got.
+    Ready(T),
+}
+
+// Step of the polling with the details of the request.
+enum Step<T> {
+    Pending(u32),
+    Ready(Reply<T>),
+}
+
+/// Blocking iterator over the states of the data polling, see `RzdClient::poll`.
+pub struct Polling<'a, T, U> {
+    search: &'a U,
+    session: &'a mut SessionToken,
+    options: &'a RequestOptions,
+    started: bool,
+    resumed: bool,
+    finished: bool,
+    request: String,
+    request_id: RzdRequestId,
+    tries: u32,
+    attempts: u32,
+    retries: u32,
+    reissued: u32,
+    deadline: Option<DateTime<Utc>>,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T, U> Polling<'a, T, U>
+where
+    U: RzdClientInterface<T>,
+{
+    fn new(search: &'a U, session: &'a mut SessionToken, options: &'a RequestOptions) -> Self {
+        Polling {
+            search,
+            session,
+            options,
+            started: false,
+            resumed: false,
+            finished: false,
+            request: String::new(),
+            request_id: RzdRequestId::default(),
+            tries: 0,
+            attempts: 0,
+            retries: 0,
+            reissued: 0,
+            deadline: options.deadline_from_now(),
+            _marker: PhantomData,
+        }
+    }
+
+    // Makes the next request, `None` is returned when the polling is over.
+    fn step(&mut self) -> Result<Option<Step<T>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let (search, options) = (self.search, self.options);
+
+        if !self.started {
+            self.started = true;
+
+            if search.query_type() == RzdQueryType::Simple {
+                self.finished = true;
+                let reply =
+                    RzdClient::simple_request(search, self.session, options, self.deadline)?;
+                return Ok(reply.map(Step::Ready));
+            }
+
+            self.request_id = RzdClient::get_reply_id(
+                search,
+                self.session,
+                options,
+                self.deadline,
+                &mut self.retries,
+            )?;
+            self.request = search.request_data(self.request_id);
+            debug!("{}request: {}", options.log_prefix(), self.request);
+            return Ok(Some(Step::Pending(0)));
+        }
+
+        if self.tries == POLL_TRIES {
+            self.finished = true;
+            return Err(Error::RzdServerOverloaded {
+                layer: search.layer(),
+                attempts: self.attempts,
+            });
+        }
+        // The resumed polling doesn't pause, the time has passed already.
+        let delay = match self.resumed {
+            true => Duration::ZERO,
+            false => POLL_DELAY,
+        };
+        if !fits_deadline(self.deadline, delay, options) {
+            self.finished = true;
+            return Err(Error::DeadlineExceeded {
+                layer: search.layer(),
+                attempts: self.attempts,
+            });
+        }
+        self.tries += 1;
+        self.attempts += 1;
+        self.resumed = false;
+        if !delay.is_zero() {
+            options.sleeper.sleep(delay);
+        }
+
+        let result = send_request(
+            &self.request,
+            request_headers(self.session, options)?,
+            options,
+            search.layer(),
+            self.deadline,
+            &mut self.retries,
+        )?;
+
+        let body = match result {
+            None => {
+                self.finished = true;
+                return Ok(None);
+            }
+            Some(r) => {
+                self.session.merge(&get_cookies_string(&mut r.cookies()));
+                read_body(&self.request, r, options)?
+            }
+        };
+
+        // If server wasn't be on time to create an answer
+        // then it sends a new `RzdRequestId`, the polling goes on with it.
+        if let Ok(Some(id)) = search.deserialize_reply_id(&body) {
+            if self.reissued == MAX_REISSUED_IDS {
+                self.tries = POLL_TRIES;
+                return Ok(Some(Step::Pending(self.attempts)));
+            }
+            self.reissued += 1;
+            self.tries = 0;
+
+            self.request_id = id;
+            self.request = search.request_data(id);
+            debug!(
+                "{}new request id is issued, request: {}",
+                options.log_prefix(),
+                self.request
+            );
+            return Ok(Some(Step::Pending(self.attempts)));
+        }
+
+        match search.deserialize_reply_data(&body)? {
+            Some(data) => {
+                self.finished = true;
+                Ok(Some(Step::Ready(Reply {
+                    data,
+                    #[cfg(any(test, feature = "fixtures"))]
+                    body,
+                    request: self.request.clone(),
+                    request_id: Some(self.request_id),
+                    // The request of the id is counted too.
+                    attempts: self.attempts + 1,
+                    polls: self.attempts,
+                    retries: self.retries,
+                })))
+            }
+            None => {
+                debug!("{}reply is incorrect", options.log_prefix());
+                Ok(Some(Step::Pending(self.attempts)))
+            }
+        }
+    }
+}
+
+impl<T, U> Polling<'_, T, U>
+where
+    U: RzdClientInterface<T>,
+{
+    // Makes the requests until the data is got or one more poll is made,
+    // then the ticket to go on with the polling later is returned.
+    fn answer(&mut self) -> Result<Option<QuickAnswer<T>>> {
+        let attempts = self.attempts;
+        loop {
+            match self.step()? {
+                None => return Ok(None),
+                Some(Step::Ready(reply)) => return Ok(Some(QuickAnswer::Ready(reply.data))),
+                Some(Step::Pending(_)) if self.attempts > attempts => {
+                    return Ok(Some(QuickAnswer::Pending(self.ticket())))
+                }
+                Some(Step::Pending(_)) => {}
+            }
+        }
+    }
+
+    fn ticket(&self) -> PendingTicket {
+        PendingTicket {
+            layer: self.search.layer(),
+            request_id: self.request_id,
+            request: self.request.clone(),
+            session: self.session.clone(),
+            attempts: self.attempts,
+        }
+    }
+}
+
+impl<T, U> Iterator for Polling<'_, T, U>
+where
+    U: RzdClientInterface<T>,
+{
+    type Item = Result<PollState<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(Some(Step::Pending(n))) => Some(Ok(PollState::Pending(n))),
+            Ok(Some(Step::Ready(reply))) => Some(Ok(PollState::Ready(reply.data))),
+            Ok(None) => None,
+            Err(e) => {
+                self.finished = true;
+                Some(Err(match self.options.correlation_id {
+                    Some(ref id) => Error::Correlated(id.clone(), Box::new(e)),
+                    None => e,
+                }))
+            }
+        }
+    }
+}
+
+// Returns true if the pause ends before the deadline.
+fn fits_deadline(
+    deadline: Option<DateTime<Utc>>,
+    delay: Duration,
+    options: &RequestOptions,
+) -> bool {
+    match deadline {
+        Some(d) => options.clock.now() + chrono::Duration::from_std(delay).unwrap_or_default() <= d,
+        None => true,
+    }
+}
+
+// Sends the request and repeats it by the backoff of the options
+// while it fails on the way to the server and the deadline allows it.
+// The repeated requests are added to `retries`.
+fn send_request(
+    query: &str,
+    headers: HeaderMap,
+    options: &RequestOptions,
+    layer: RzdLayer,
+    deadline: Option<DateTime<Utc>>,
+    retries: &mut u32,
+) -> Result<Option<Response>> {
+    let mut retry = 0;
+    loop {
+        let error = match send_retrying_after(query, headers.clone(), options, layer, retries) {
+            Err(e) if e.is_transient() => e,
+            result => return result,
+        };
+
+        let delay = match options.backoff {
+            Some(ref b) if retry + 1 < b.max_attempts => b.delay(retry),
+            _ => return Err(error),
+        };
+        if !fits_deadline(deadline, delay, options) {
+            return Err(error);
+        }
+
+        warn!(
+            "{}request failed: {}, repeat after {:?}",
+            options.log_prefix(),
+            error.message(Lang::En),
+            delay
+        );
+        options.sleeper.sleep(delay);
+        retry += 1;
+        *retries += 1;
+    }
+}
+
+// Sends the request and repeats it once if the server asks to retry a bit later.
+fn send_retrying_after(
+    query: &str,
+    headers: HeaderMap,
+    options: &RequestOptions,
+    layer: RzdLayer,
+    retries: &mut u32,
+) -> Result<Option<Response>> {
+    let timeout = options.timeout_for(layer);
+    match send_blocking_request(query, headers.clone(), timeout, options) {
+        Err(Error::HttpFailure(e)) => match e.retry_after_at(options.clock.now()) {
+            Some(delay) if delay <= MAX_RETRY_AFTER => {
+                warn!(
+                    "{}server returned {}, retry after {:?}",
+                    options.log_prefix(),
+                    e.status(),
+                    delay
+                );
+                options.sleeper.sleep(delay);
+                *retries += 1;
+                send_blocking_request(query, headers, timeout, options)
+            }
+            _ => Err(Error::HttpFailure(e)),
+        },
+        result => result,
     }
 }
 
-fn send_blocking_request(query: &str, headers: HeaderMap) -> Result<Option<Response>> {
-    let request = reqwest::blocking::Client::new().get(query).headers(headers);
+fn send_blocking_request(
+    query: &str,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    options: &RequestOptions,
+) -> Result<Option<Response>> {
+    let query = &options.final_url(query);
+
+    // Redirects are not followed, the server redirects only to a challenge page.
+    let mut client = reqwest::blocking::Client::builder().redirect(Policy::none());
+    if let Some(timeout) = timeout {
+        client = client.timeout(timeout);
+    }
+    if options.no_proxy {
+        client = client.no_proxy();
+    } else if let Some(ref proxy) = options.proxy {
+        client = client.proxy(Proxy::all(proxy)?);
+    }
+    let client = client.build()?;
+
+    options.record(PayloadKind::Request, query, &headers, "");
+    let result = client.get(query).headers(headers).send()?;
+
+    // The cached reply is taken by the caller.
+    if result.status() == StatusCode::NOT_MODIFIED {
+        debug!("{}reply is not modified", options.log_prefix());
+        return Ok(Some(result));
+    }
+
+    if result.status().is_redirection() {
+        let target = match result.headers().get(LOCATION) {
+            Some(l) => l.to_str().unwrap_or(query).to_string(),
+            None => query.to_string(),
+        };
+        warn!("{}server redirected to {}", options.log_prefix(), target);
+        return Err(Error::BotProtection(target));
+    }
 
-    let result = request.send()?;
+    let is_html = result
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .is_some_and(|t| t.contains("text/html"));
+    if is_html {
+        warn!("{}server returned a web page", options.log_prefix());
+        return Err(Error::BotProtection(query.to_string()));
+    }
 
     if !result.status().is_success() {
-        error!("server returned {}", result.status());
-        return Err(Error::FailRzdResponse);
+        let status = result.status().as_u16();
+        let headers = result.headers().clone();
+        let body = result.text().unwrap_or_default();
+        options.record(PayloadKind::Response, query, &headers, &body);
+        return Err(Error::HttpFailure(HttpFailure::new(
+            query, status, &headers, &body,
+        )));
     }
 
     if let Some(0) = result.content_length() {
-        warn!("response body is empty");
+        warn!("{}response body is empty", options.log_prefix());
         return Ok(None);
     }
 
     Ok(Some(result))
 }
 
+// Reads the reply, a web page instead of JSON is a challenge page.
+fn read_body(query: &str, response: Response, options: &RequestOptions) -> Result<String> {
+    let headers = response.headers().clone();
+    let body = response.text()?;
+    options.record(PayloadKind::Response, query, &headers, &body);
+
+    if is_html(&body) {
+        warn!("{}server returned a web page", options.log_prefix());
+        return Err(Error::BotProtection(query.to_string()));
+    }
+    Ok(body)
+}
+
+// Reads the reply and caches it, or takes the cached one if the server answered
+// that it is not modified.
+fn read_cached_body(query: &str, response: Response, options: &RequestOptions) -> Result<String> {
+    let cache = match options.cache {
+        Some(ref c) => c,
+        None => return read_body(query, response, options),
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return cache.revalidate(query).ok_or(Error::FailRzdResponse);
+    }
+
+    let headers = response.headers().clone();
+    let body = read_body(query, response, options)?;
+    cache.store(query, &headers, &body);
+    Ok(body)
+}
+
+// Returns the headers with the values of the cookies replaced.
+fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(n, v)| {
+            let value = if n == COOKIE || n == SET_COOKIE {
+                "<redacted>".to_string()
+            } else {
+                v.to_str().unwrap_or("<binary>").to_string()
+            };
+            (n.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+// Returns the cookies `name=value; ...` with the values replaced.
+fn redact_cookies(cookies: &str) -> String {
+    cookies
+        .split("; ")
+        .filter(|c| !c.is_empty())
+        .map(|c| match c.split_once('=') {
+            Some((name, _)) => format!("{}=<redacted>", name),
+            None => "<redacted>".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn cut_body(body: &str, limit: usize) -> String {
+    match body.char_indices().nth(limit) {
+        Some((i, _)) => format!("{}...", &body[..i]),
+        None => body.to_string(),
+    }
+}
+
+fn is_html(body: &str) -> bool {
+    let start: String = body
+        .trim_start()
+        .chars()
+        .take(14)
+        .collect::<String>()
+        .to_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
 fn request_headers_default() -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(USER_AGENT, HeaderValue::from_static(APP_USER_AGENT));
@@ -188,6 +1844,31 @@ fn request_headers_default() -> HeaderMap {
     headers
 }
 
+// Both the name and the value must be valid to send the header.
+fn parse_header(name: &str, value: &str) -> Option<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+    let value = HeaderValue::from_str(value).ok()?;
+    Some((name, value))
+}
+
+// Default headers with the cookies of the session if it is established
+// and the extra headers of the options.
+fn request_headers(session: &SessionToken, options: &RequestOptions) -> Result<HeaderMap> {
+    let mut headers = request_headers_default();
+    for (name, value) in options.extra_headers.iter() {
+        match parse_header(name, value) {
+            Some((n, v)) => {
+                headers.insert(n, v);
+            }
+            None => warn!("{}invalid header {} is skipped", options.log_prefix(), name),
+        }
+    }
+    if !session.is_empty() {
+        headers.insert(COOKIE, HeaderValue::from_bytes(session.0.as_bytes())?);
+    }
+    Ok(headers)
+}
+
 fn get_cookies_string(cookies_iter: &mut dyn Iterator<Item = Cookie>) -> String {
     let mut cookies: Vec<String> = cookies_iter
         .map(|c| format!("{}={}", c.name(), c.value()))
@@ -196,3 +1877,502 @@ fn get_cookies_string(cookies_iter: &mut dyn Iterator<Item = Cookie>) -> String
     cookies.dedup();
     cookies.join("; ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cut_body, is_html, redact_headers, request_headers, send_request, Backoff, Payload,
+        PayloadKind, PayloadSink, PendingTicket, Polling, RequestOptions, RequestPhase, RzdClient,
+        RzdLayer, RzdQuery, RzdRequestId, SessionToken, MAX_BACKOFF_DELAY, POLL_DELAY,
+    };
+    use crate::clock::MockClock;
+    use crate::error::{Error, HttpFailure, RzdErrorSet};
+    use crate::Lang;
+    use crate::{RouteList, TrainScheduleSearch, TrainType};
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use url::Url;
+
+    #[test]
+    fn dry_run_test() {
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        let options = RequestOptions::new()
+            .with_cookies("JSESSIONID=1")
+            .with_header("X-Trace", "42");
+        let requests = RzdClient::<RouteList>::dry_run_with(&q, &options).unwrap();
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].phase(), RequestPhase::RequestId);
+        assert!(requests[0].url().contains("code0=2004000"));
+        assert_eq!(requests[1].phase(), RequestPhase::Data);
+        assert!(requests[1].url().ends_with("rid=0"));
+        assert!(requests[1]
+            .headers()
+            .contains(&("cookie".to_string(), "<redacted>".to_string())));
+        assert!(requests[1].to_string().contains("\n\tx-trace: 42"));
+
+        let q = crate::StationCodeSearch::new("москва").unwrap();
+        let requests = RzdClient::<crate::StationList>::dry_run(&q).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].phase(), RequestPhase::Single);
+
+        let q = TrainScheduleSearch::new(2004000, 2004000, (2022, 4, 1), TrainType::Train, true);
+        assert!(RzdClient::<RouteList>::dry_run(&q).is_err());
+    }
+
+    #[test]
+    fn url_rewriter_test() {
+        let options = RequestOptions::new().with_url_rewriter(Arc::new(|mut url: Url| {
+            url.set_host(Some("rzd-proxy.local")).unwrap();
+            url.query_pairs_mut().append_pair("sig", "abc");
+            url
+        }));
+        assert_eq!(options.final_url("not a url"), "not a url");
+
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        let requests = RzdClient::<RouteList>::dry_run_with(&q, &options).unwrap();
+        for r in &requests {
+            assert!(r.url().starts_with("https://rzd-proxy.local/"));
+            assert!(r.url().ends_with("&sig=abc"));
+        }
+        assert!(requests[1].url().contains("rid=0"));
+    }
+
+    #[test]
+    fn get_many_test() {
+        let searches = [
+            TrainScheduleSearch::new(2004000, 2004000, (2022, 4, 1), TrainType::Train, true),
+            TrainScheduleSearch::new(0, 2000000, (2022, 4, 2), TrainType::Train, true),
+        ];
+        let (routes, errors) = RzdClient::<RouteList>::get_many(&searches, RequestOptions::new());
+
+        assert_eq!(routes, [None, None]);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.errors()[1].0.contains("dt0=02.04.2022"));
+        assert!(matches!(errors.errors()[0].1, Error::InvalidRoute { .. }));
+
+        let text = errors.to_string();
+        assert!(text.starts_with("2 ошибки\n\thttps://pass.rzd.ru/"));
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.ends_with(": станции отправления и прибытия не заданы или совпадают"));
+        assert!(std::error::Error::source(&errors).is_some());
+
+        assert!(errors.into_result(()).is_err());
+        assert_eq!(RzdErrorSet::new().into_result(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn layer_test() {
+        assert_eq!(RzdLayer::TrainInfo.id(), Some(5764));
+        assert_eq!(RzdLayer::TrainSchedule.id(), Some(5827));
+        assert_eq!(RzdLayer::TripStops.id(), Some(5804));
+        assert_eq!(RzdLayer::Suggester.id(), None);
+        assert_eq!(RzdLayer::Suggester.url(), "https://pass.rzd.ru/suggester");
+        assert_eq!(
+            RzdLayer::TripStops.url_for(Lang::En),
+            "https://pass.rzd.ru/timetable/public/en"
+        );
+    }
+
+    #[test]
+    fn query_to_url_test() {
+        let q = RzdQuery::new(RzdLayer::TrainInfo)
+            .param("tnum0", "001А")
+            .param("name", "a&b=c d");
+
+        assert_eq!(
+            q.to_url(),
+            "https://pass.rzd.ru/timetable/public/ru\
+            ?layer_id=5764\
+            &tnum0=001%D0%90\
+            &name=a%26b%3Dc+d"
+        );
+
+        let q = RzdQuery::new(RzdLayer::TrainInfo).lang(Lang::En);
+        assert_eq!(
+            q.to_url(),
+            "https://pass.rzd.ru/timetable/public/en?layer_id=5764"
+        );
+
+        let q = RzdQuery::new(RzdLayer::Suggester)
+            .param("empty", "")
+            .param("plus", "1+1%")
+            .param("hash", "#?/")
+            .param("line", "a\nб ё");
+        assert_eq!(
+            q.to_url(),
+            "https://pass.rzd.ru/suggester\
+            ?empty=\
+            &plus=1%2B1%25\
+            &hash=%23%3F%2F\
+            &line=a%0A%D0%B1+%D1%91"
+        );
+        assert!(q.is_encoded(&q.to_url()));
+        assert!(!q.is_encoded("https://pass.rzd.ru/suggester?empty=&plus=1+1%"));
+    }
+
+    #[test]
+    fn resume_test() {
+        let ticket = PendingTicket {
+            layer: RzdLayer::TrainInfo,
+            request_id: RzdRequestId::new(42),
+            request: RzdQuery::new(RzdLayer::TrainInfo).param("rid", 42).to_url(),
+            session: SessionToken::default(),
+            attempts: 2,
+        };
+        assert_eq!(ticket.request_id().to_uint(), 42);
+        assert_eq!(ticket.attempts(), 2);
+
+        let json = serde_json::to_string(&ticket).unwrap();
+        assert!(json.contains(r#""layer":"TrainInfo""#));
+        assert_eq!(
+            serde_json::from_str::<PendingTicket>(&json).unwrap(),
+            ticket
+        );
+
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        assert!(matches!(
+            RzdClient::<RouteList>::resume(&q, ticket, RequestOptions::new()),
+            Err(Error::UnsupportedOperation)
+        ));
+    }
+
+    #[test]
+    fn session_token_test() {
+        let mut session = SessionToken::default();
+        assert!(session.is_empty());
+
+        session.merge("JSESSIONID=1; lang=ru");
+        session.merge("JSESSIONID=2");
+        assert_eq!(session.0, "JSESSIONID=2; lang=ru");
+
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SessionToken>(&json).unwrap(),
+            session
+        );
+        assert_eq!(
+            format!("{:?}", session),
+            r#"SessionToken("JSESSIONID=<redacted>; lang=<redacted>")"#
+        );
+
+        let ticket = PendingTicket {
+            layer: RzdLayer::TrainInfo,
+            request_id: RzdRequestId::new(42),
+            request: RzdQuery::new(RzdLayer::TrainInfo).param("rid", 42).to_url(),
+            session,
+            attempts: 1,
+        };
+        assert!(!format!("{:?}", ticket).contains("JSESSIONID=2"));
+
+        let options = RequestOptions::new().with_cookies("JSESSIONID=1");
+        let debug = format!("{:?}", options);
+        assert!(debug.contains(r#"cookies: "JSESSIONID=<redacted>""#));
+        assert!(!debug.contains("JSESSIONID=1"));
+    }
+
+    #[test]
+    fn is_html_test() {
+        assert!(is_html(
+            "<!DOCTYPE html><html><body>challenge</body></html>"
+        ));
+        assert!(is_html("\n  <HTML lang=\"ru\">"));
+        assert!(!is_html("{\"result\":\"OK\"}"));
+        assert!(!is_html("[]"));
+        assert!(!is_html(""));
+    }
+
+    #[test]
+    fn http_failure_test() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("5"));
+
+        let body = "x".repeat(1000);
+        let e = HttpFailure::new("https://pass.rzd.ru", 503, &headers, &body);
+        assert_eq!(e.status(), 503);
+        assert_eq!(e.header("Retry-After"), Some("5"));
+        assert_eq!(e.retry_after(), Some(Duration::from_secs(5)));
+        assert_eq!(e.body().len(), 512);
+        assert_eq!(e.to_string(), "сервер \"РЖД\" вернул код ошибки 503");
+
+        let error = Error::HttpFailure(e.clone());
+        assert_eq!(error.rzd_code().as_deref(), Some("503"));
+        assert_eq!(
+            error.message(Lang::En),
+            "the RZD server returned the status 503"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let e = HttpFailure::new("https://pass.rzd.ru", 429, &headers, "");
+        assert_eq!(e.retry_after(), Some(Duration::ZERO));
+        assert!(e.body().is_empty());
+
+        let e = HttpFailure::new("https://pass.rzd.ru", 500, &HeaderMap::new(), "");
+        assert_eq!(e.retry_after(), None);
+    }
+
+    #[test]
+    fn request_options_test() {
+        let options = RequestOptions::new()
+            .with_timeout(Duration::from_secs(30))
+            .with_layer_timeout(RzdLayer::Suggester, Duration::from_secs(5))
+            .with_layer_timeout(RzdLayer::Suggester, Duration::from_secs(2));
+
+        assert_eq!(options.timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            options.timeout_for(RzdLayer::Suggester),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            options.timeout_for(RzdLayer::TrainSchedule),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(RequestOptions::new().timeout_for(RzdLayer::TrainInfo), None);
+    }
+
+    #[test]
+    fn mock_clock_test() {
+        let clock = Arc::new(MockClock::default());
+        let options = RequestOptions::new()
+            .with_clock(clock.clone())
+            .with_sleeper(clock.clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let e = HttpFailure::new("https://pass.rzd.ru", 503, &headers, "");
+        let delay = e.retry_after_at(options.clock.now()).unwrap();
+        assert_eq!(delay, Duration::from_secs(30));
+
+        options.sleeper.sleep(delay);
+        assert_eq!(*clock.0.lock().unwrap(), vec![Duration::from_secs(30)]);
+    }
+
+    #[test]
+    fn backoff_test() {
+        let backoff = Backoff::new(0, Duration::from_secs(1));
+        assert_eq!(backoff.max_attempts(), 1);
+        assert_eq!(backoff.delay(0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(8));
+        assert_eq!(backoff.delay(40), MAX_BACKOFF_DELAY);
+
+        let backoff = backoff
+            .with_factor(3)
+            .with_max_delay(Duration::from_secs(5));
+        assert_eq!(backoff.delay(1), Duration::from_secs(3));
+        assert_eq!(backoff.delay(2), Duration::from_secs(5));
+
+        let e = Error::HttpFailure(HttpFailure::new(
+            "https://pass.rzd.ru",
+            502,
+            &HeaderMap::new(),
+            "",
+        ));
+        assert!(e.is_transient());
+        let e = Error::HttpFailure(HttpFailure::new(
+            "https://pass.rzd.ru",
+            404,
+            &HeaderMap::new(),
+            "",
+        ));
+        assert!(!e.is_transient());
+        assert!(!Error::FailRzdResponse.is_transient());
+    }
+
+    #[test]
+    fn transport_retry_test() {
+        // Nothing listens on the port, so the connection is refused at once.
+        let query = "http://127.0.0.1:1/";
+        let clock = Arc::new(MockClock::default());
+        let options = RequestOptions::new()
+            .with_clock(clock.clone())
+            .with_sleeper(clock.clone())
+            .with_backoff(Backoff::new(3, Duration::from_secs(1)));
+
+        let mut retries = 0;
+        let result = send_request(
+            query,
+            HeaderMap::new(),
+            &options,
+            RzdLayer::TrainSchedule,
+            None,
+            &mut retries,
+        );
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(
+            *clock.0.lock().unwrap(),
+            vec![Duration::from_secs(1), Duration::from_secs(2)]
+        );
+        assert_eq!(retries, 2);
+
+        // The second pause doesn't fit the deadline.
+        clock.0.lock().unwrap().clear();
+        let options = options.with_deadline(Duration::from_millis(1500));
+        let deadline = options.deadline_from_now();
+        let mut retries = 0;
+        let result = send_request(
+            query,
+            HeaderMap::new(),
+            &options,
+            RzdLayer::TrainSchedule,
+            deadline,
+            &mut retries,
+        );
+        assert!(result.is_err());
+        assert_eq!(*clock.0.lock().unwrap(), vec![Duration::from_secs(1)]);
+        assert_eq!(retries, 1);
+    }
+
+    #[test]
+    fn polling_test() {
+        // The first id is reissued while the data is prepared.
+        let requests = Arc::new(Mutex::new(vec![]));
+        let log = requests.clone();
+        let clock = Arc::new(MockClock::default());
+        let options = crate::fixtures::serve(move |request| {
+            log.lock().unwrap().push(request.to_string());
+            if request.contains("rid=42") {
+                r#"{"result":"RID","RID":43}"#.to_string()
+            } else if request.contains("rid=43") {
+                crate::fixtures::load_fixture("train_schedule").unwrap()
+            } else {
+                r#"{"result":"RID","RID":42}"#.to_string()
+            }
+        })
+        .with_clock(clock.clone())
+        .with_sleeper(clock.clone());
+
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        let routes = RzdClient::<RouteList>::get_with(&q, options)
+            .unwrap()
+            .unwrap();
+        assert!(!routes.as_ref()[0].trains().as_ref().is_empty());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(clock.pauses(), vec![POLL_DELAY, POLL_DELAY]);
+    }
+
+    #[test]
+    fn polling_deadline_test() {
+        let clock = Arc::new(MockClock::default());
+        let options = RequestOptions::new()
+            .with_clock(clock.clone())
+            .with_sleeper(clock.clone())
+            .with_deadline(Duration::from_secs(1));
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        let mut session = SessionToken::default();
+
+        let mut polling = Polling::<RouteList, _>::new(&q, &mut session, &options);
+        polling.started = true;
+        assert!(matches!(
+            polling.step(),
+            Err(Error::DeadlineExceeded {
+                layer: RzdLayer::TrainSchedule,
+                attempts: 0
+            })
+        ));
+        assert!(clock.0.lock().unwrap().is_empty());
+        assert!(matches!(polling.step(), Ok(None)));
+    }
+
+    #[test]
+    fn extra_headers_test() {
+        let options = RequestOptions::new()
+            .with_header("X-Gateway-Token", "secret")
+            .with_header("bad name", "skipped")
+            .with_header("X-Bad-Value", "a\nb")
+            .with_cookies("lang=ru");
+        assert_eq!(options.cookies(), "lang=ru");
+        assert_eq!(options.extra_headers().len(), 1);
+
+        let mut session = SessionToken::default();
+        session.merge("JSESSIONID=1");
+
+        let headers = request_headers(&session, &options).unwrap();
+        assert_eq!(headers["x-gateway-token"], "secret");
+        assert_eq!(headers["cookie"], "JSESSIONID=1");
+        assert_eq!(headers.len(), 4);
+    }
+
+    #[test]
+    fn proxy_test() {
+        let options = RequestOptions::new();
+        assert_eq!(options.proxy(), None);
+        assert!(!options.is_no_proxy());
+
+        let options = options.with_proxy("http://proxy.local:3128");
+        assert_eq!(options.proxy(), Some("http://proxy.local:3128"));
+
+        let options = options.no_proxy();
+        assert_eq!(options.proxy(), None);
+        assert!(options.is_no_proxy());
+    }
+
+    #[test]
+    fn correlation_id_test() {
+        let options = RequestOptions::new();
+        assert_eq!(options.correlation_id(), None);
+        assert_eq!(options.log_prefix(), "");
+
+        let options = options.with_correlation_id("user-42");
+        assert_eq!(options.correlation_id(), Some("user-42"));
+        assert_eq!(options.log_prefix(), "[user-42] ");
+
+        let e = Error::Correlated("user-42".to_string(), Box::new(Error::TooShortQuery));
+        assert_eq!(e.correlation_id(), Some("user-42"));
+        assert_eq!(e.to_string(), "[user-42] передан слишком короткий запрос");
+        assert!(matches!(e.root(), Error::TooShortQuery));
+        assert!(matches!(e.into_root(), Error::TooShortQuery));
+        assert!(matches!(Error::Empty.root(), Error::Empty));
+        let e = Error::Correlated("user-42".to_string(), Box::new(Error::TooShortQuery));
+        assert!(std::error::Error::source(&e).is_some());
+    }
+
+    #[derive(Default)]
+    struct MockSink(Mutex<Vec<String>>);
+
+    impl PayloadSink for MockSink {
+        fn record(&self, payload: &Payload) {
+            self.0.lock().unwrap().push(format!(
+                "{:?} {} {:?} {}",
+                payload.kind(),
+                payload.url(),
+                payload.headers(),
+                payload.body()
+            ));
+        }
+    }
+
+    #[test]
+    fn payload_sink_test() {
+        let sink = Arc::new(MockSink::default());
+        let options = RequestOptions::new()
+            .with_payload_sink(sink.clone())
+            .with_payload_limit(4);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("set-cookie", HeaderValue::from_static("JSESSIONID=1"));
+        options.record(
+            PayloadKind::Response,
+            "https://pass.rzd.ru",
+            &headers,
+            "абвгд",
+        );
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![r#"Response https://pass.rzd.ru [("set-cookie", "<redacted>")] абвг..."#]
+        );
+        assert_eq!(cut_body("abc", 4), "abc");
+        assert_eq!(
+            redact_headers(&HeaderMap::new()),
+            Vec::<(String, String)>::new()
+        );
+    }
+}
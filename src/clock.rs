@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use std::thread;
+use std::time::Duration;
+
+/// Source of the current time, e.g. to compute the delay asked by the server.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Pauses the requests, e.g. while the server prepares the data.
+pub trait Sleeper: Send + Sync {
+    /// Blocks for the duration.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The system clock blocking the current thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl Sleeper for SystemClock {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// The clock of the tests stopped at a moment, it records the pauses instead of blocking.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct MockClock(pub(crate) std::sync::Mutex<Vec<Duration>>);
+
+#[cfg(test)]
+impl MockClock {
+    // Returns the pauses made so far.
+    pub(crate) fn pauses(&self) -> Vec<Duration> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Sleeper for MockClock {
+    fn sleep(&self, duration: Duration) {
+        self.0.lock().unwrap().push(duration);
+    }
+}
@@ -1,7 +1,10 @@
 //! Deserializers for the core types.
 
+use serde::de::Error as DeError;
 use serde::{Deserialize, Deserializer};
 
+use crate::{Carrier, ServerTimestamp, TrainDate, TrainTime, TripDuration};
+
 pub fn des_null_to_default<'de, D, T>(de: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
@@ -10,3 +13,180 @@ where
     let v = Option::<T>::deserialize(de)?;
     Ok(v.unwrap_or_default())
 }
+
+// Takes a price given either as a number or as a string, e.g. "1099" or 1099.5,
+// rounded to whole rubles.
+pub fn des_price<'de, D>(de: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Option::<serde_json::Value>::deserialize(de)?;
+    let price = match v {
+        Some(serde_json::Value::Number(n)) => n.as_f64(),
+        Some(serde_json::Value::String(s)) => return Ok(parse_price(&s)),
+        _ => None,
+    };
+    Ok(price
+        .filter(|p| p.is_finite() && *p >= 0.0)
+        .map(|p| p.round() as u32))
+}
+
+// Takes a price given as a string, e.g. "1099.5", rounded to whole rubles.
+pub fn parse_price(s: &str) -> Option<u32> {
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|p| p.is_finite() && *p >= 0.0)
+        .map(|p| p.round() as u32)
+}
+
+// List whose malformed entries are skipped instead of failing the whole reply,
+// the reasons of the skipped entries are kept in `errors`.
+#[derive(Debug)]
+pub struct Lenient<T> {
+    pub items: Vec<T>,
+    pub errors: Vec<String>,
+}
+
+impl<T> Default for Lenient<T> {
+    fn default() -> Self {
+        Lenient {
+            items: vec![],
+            errors: vec![],
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Lenient<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values = Option::<Vec<serde_json::Value>>::deserialize(deserializer)?;
+        let mut list = Lenient::default();
+        for v in values.unwrap_or_default() {
+            match serde_json::from_value(v) {
+                Ok(item) => list.items.push(item),
+                Err(e) => list.errors.push(e.to_string()),
+            }
+        }
+        Ok(list)
+    }
+}
+
+impl<'de> Deserialize<'de> for TrainDate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(DeError::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for TrainTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(DeError::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for ServerTimestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(DeError::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for TripDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(DeError::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Carrier {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Carrier::from(s.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{des_price, Carrier, Lenient, TrainDate, TrainTime, TripDuration};
+
+    #[test]
+    fn train_date_test() {
+        let d: TrainDate = serde_json::from_str(r#""01.04.2022""#).unwrap();
+        assert_eq!(d, TrainDate::new(2022, 4, 1));
+
+        assert!(serde_json::from_str::<TrainDate>(r#""2022-04-01""#).is_err());
+        assert!(serde_json::from_str::<TrainDate>(r#""31.02.2022""#).is_err());
+        assert!(serde_json::from_str::<TrainDate>(r#""01.x.04.2022""#).is_err());
+    }
+
+    #[test]
+    fn train_time_test() {
+        let t: TrainTime = serde_json::from_str(r#""05:07""#).unwrap();
+        assert_eq!(t, TrainTime::try_new(5, 7).unwrap());
+
+        assert!(serde_json::from_str::<TrainTime>(r#""5""#).is_err());
+    }
+
+    #[test]
+    fn trip_duration_test() {
+        let d: TripDuration = serde_json::from_str(r#""33:05""#).unwrap();
+        assert_eq!(d, TripDuration::new(33, 5).unwrap());
+
+        let c: Carrier = serde_json::from_str(r#""ФПК""#).unwrap();
+        assert_eq!(c, Carrier::Fpk);
+    }
+
+    #[test]
+    fn price_test() {
+        #[derive(serde::Deserialize)]
+        struct Price(#[serde(deserialize_with = "des_price")] Option<u32>);
+
+        let price = |s: &str| serde_json::from_str::<Price>(s).unwrap().0;
+
+        assert_eq!(price("1099"), Some(1099));
+        assert_eq!(price("1099.6"), Some(1100));
+        assert_eq!(price(r#""660""#), Some(660));
+        assert_eq!(price(r#""бесплатно""#), None);
+        assert_eq!(price("null"), None);
+        assert_eq!(price("-1"), None);
+    }
+
+    #[test]
+    fn lenient_test() {
+        let list: Lenient<TrainTime> =
+            serde_json::from_str(r#"["05:07", "5", "23:59", 1]"#).unwrap();
+        assert_eq!(
+            list.items,
+            vec![
+                TrainTime::try_new(5, 7).unwrap(),
+                TrainTime::try_new(23, 59).unwrap()
+            ]
+        );
+        assert_eq!(list.errors.len(), 2);
+
+        let list: Lenient<TrainTime> = serde_json::from_str("null").unwrap();
+        assert!(list.items.is_empty() && list.errors.is_empty());
+    }
+}
@@ -1,21 +1,191 @@
-use reqwest::header::InvalidHeaderValue as HeaderError;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, InvalidHeaderValue as HeaderError, RETRY_AFTER};
 use reqwest::Error as ReqwestError;
+use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 use std::fmt;
+use std::io::Error as IoError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::RzdLayer;
+use crate::{non_empty, Lang, RzdStationCode, ServerTimestamp};
+
+// Length of the reply body kept in `HttpFailure`.
+const BODY_SNIPPET_LEN: usize = 512;
+
+// The errors are displayed in English instead of Russian.
+static ENGLISH_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Sets the language of the errors written by `Display`, Russian by default.
+/// The setting is global, e.g. for the logs of a service read by the non-Russian-speaking team.
+/// The messages of the server, e.g. of `Error::RzdError`, are kept as the server returned them,
+/// in Russian.
+///
+/// # Examples
+///
+/// ```rust
+/// # use rzd_trains::{set_error_lang, Error, Lang};
+/// #
+/// set_error_lang(Lang::En);
+///
+/// assert_eq!(Error::TooShortQuery.to_string(), "the query is too short");
+/// ```
+pub fn set_error_lang(lang: Lang) {
+    ENGLISH_ERRORS.store(lang == Lang::En, Ordering::Relaxed);
+}
+
+/// Returns the language of the errors written by `Display`.
+pub fn error_lang() -> Lang {
+    match ENGLISH_ERRORS.load(Ordering::Relaxed) {
+        true => Lang::En,
+        false => Lang::Ru,
+    }
+}
+
+/// Diagnostics of the Express-3 gateway the reply is made by.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GatewayInfo {
+    request_number: Option<u64>,
+    address: String,
+    version: String,
+    error_code: Option<String>,
+    #[serde(default)]
+    received: Option<ServerTimestamp>,
+    #[serde(default)]
+    sent: Option<ServerTimestamp>,
+}
+
+impl GatewayInfo {
+    pub(crate) fn new(
+        request_number: Option<u64>,
+        address: String,
+        version: String,
+        error_code: Option<String>,
+    ) -> Self {
+        GatewayInfo {
+            request_number,
+            address,
+            version,
+            error_code,
+            received: None,
+            sent: None,
+        }
+    }
+
+    // Sets the local times of the gateway the request is received and the reply is sent.
+    pub(crate) fn with_local_times(
+        mut self,
+        received: Option<ServerTimestamp>,
+        sent: Option<ServerTimestamp>,
+    ) -> Self {
+        self.received = received;
+        self.sent = sent;
+        self
+    }
+
+    /// Returns the number of the request in the gateway, `ReqExpressZK`.
+    #[inline]
+    pub fn request_number(&self) -> Option<u64> {
+        self.request_number
+    }
+
+    /// Returns the address of the gateway, `ReqAddress`.
+    #[inline]
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the address of the gateway, `None` if the server omitted it.
+    pub fn address_opt(&self) -> Option<&str> {
+        non_empty(&self.address)
+    }
+
+    /// Returns the version of the gateway.
+    #[inline]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the version of the gateway, `None` if the server omitted it.
+    pub fn version_opt(&self) -> Option<&str> {
+        non_empty(&self.version)
+    }
+
+    /// Returns the code of the error reported by the gateway.
+    #[inline]
+    pub fn error_code(&self) -> Option<&str> {
+        self.error_code.as_deref()
+    }
+
+    /// Returns the local time the gateway received the request at, `ReqLocalRecv`.
+    #[inline]
+    pub fn received_at(&self) -> Option<ServerTimestamp> {
+        self.received
+    }
+
+    /// Returns the local time the gateway sent the reply at, `ReqLocalSend`.
+    #[inline]
+    pub fn sent_at(&self) -> Option<ServerTimestamp> {
+        self.sent
+    }
+}
 
 /// Errors returned the sever.
-#[derive(Debug, Default, PartialEq)]
-pub struct RzdErrors(Vec<String>);
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RzdErrors(
+    Vec<String>,
+    Option<Box<GatewayInfo>>,
+    #[serde(default)] Option<ServerTimestamp>,
+);
 
 impl RzdErrors {
     pub fn new(errors: Vec<String>) -> Self {
-        RzdErrors(errors)
+        RzdErrors(errors, None, None)
+    }
+
+    /// Attaches the timestamp of the reply the errors are returned in.
+    pub fn with_timestamp(mut self, timestamp: ServerTimestamp) -> Self {
+        self.2 = Some(timestamp);
+        self
+    }
+
+    // Attaches the timestamp given as the string of the reply, the malformed one is skipped.
+    pub(crate) fn with_reply_timestamp(self, timestamp: &str) -> Self {
+        match timestamp.parse() {
+            Ok(t) => self.with_timestamp(t),
+            Err(_) => self,
+        }
+    }
+
+    /// Returns the timestamp of the reply the errors are returned in, if the server sent it.
+    #[inline]
+    pub fn server_timestamp(&self) -> Option<ServerTimestamp> {
+        self.2
+    }
+
+    /// Attaches the diagnostics of the gateway the errors are returned by.
+    pub fn with_gateway(mut self, gateway: GatewayInfo) -> Self {
+        self.1 = Some(Box::new(gateway));
+        self
+    }
+
+    /// Returns the diagnostics of the gateway the errors are returned by.
+    #[inline]
+    pub fn gateway(&self) -> Option<&GatewayInfo> {
+        self.1.as_deref()
     }
 
     /// Performs the conversion into `Vec`
     pub fn to_vec(self) -> Vec<String> {
         self.0
     }
+
+    /// Returns true if there are no errors.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl fmt::Display for RzdErrors {
@@ -26,8 +196,164 @@ impl fmt::Display for RzdErrors {
 
 impl StdError for RzdErrors {}
 
+/// Errors of the searches made together, e.g. by `RzdClient::get_many`,
+/// each of them is kept with the description of its search.
+#[derive(Debug, Clone, Default)]
+pub struct RzdErrorSet(Vec<(String, Error)>);
+
+impl RzdErrorSet {
+    pub fn new() -> Self {
+        RzdErrorSet::default()
+    }
+
+    /// Adds the error of the search described by the context, e.g. its URL.
+    pub fn push(&mut self, context: impl Into<String>, error: Error) {
+        self.0.push((context.into(), error));
+    }
+
+    /// Returns true if there are no errors.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of the errors.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the errors with the descriptions of their searches.
+    #[inline]
+    pub fn errors(&self) -> &[(String, Error)] {
+        &self.0
+    }
+
+    /// Returns the value if there are no errors, otherwise the errors.
+    pub fn into_result<T>(self, value: T) -> std::result::Result<T, RzdErrorSet> {
+        match self.is_empty() {
+            true => Ok(value),
+            false => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for RzdErrorSet {
+    /// Writes the number of the errors and every error on its own line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = self.0.len() as u32;
+        let lang = error_lang();
+        let errors = match lang {
+            Lang::Ru => crate::plural(n, "ошибка", "ошибки", "ошибок"),
+            Lang::En if n == 1 => "error",
+            Lang::En => "errors",
+        };
+        write!(f, "{} {}", n, errors)?;
+        for (context, error) in self.0.iter() {
+            write!(f, "\n\t{}: {}", context, error.message(lang))?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for RzdErrorSet {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.first().map(|(_, e)| e as &(dyn StdError + 'static))
+    }
+}
+
+/// The reply of the server finished with an unsuccessful HTTP status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpFailure {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl HttpFailure {
+    pub(crate) fn new(url: &str, status: u16, headers: &HeaderMap, body: &str) -> Self {
+        let headers = headers
+            .iter()
+            .filter_map(|(n, v)| Some((n.as_str().to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+
+        HttpFailure {
+            url: url.to_string(),
+            status,
+            headers,
+            body: body.chars().take(BODY_SNIPPET_LEN).collect(),
+        }
+    }
+
+    /// Returns the URL of the request.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the HTTP status code.
+    #[inline]
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the value of the header, the name is case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the headers of the reply.
+    #[inline]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the beginning of the reply body, it is empty if the server sent no body.
+    #[inline]
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Returns the delay the server asks to wait before the next request, `Retry-After`.
+    /// The header holds either a number of seconds or a date.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after_at(Utc::now())
+    }
+
+    // Returns the `Retry-After` delay counted from the time `now`.
+    pub(crate) fn retry_after_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let value = self.header(RETRY_AFTER.as_str())?.trim();
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        let delay = date.with_timezone(&Utc) - now;
+        Some(delay.to_std().unwrap_or_default())
+    }
+
+    /// Returns the description of the failure in the language.
+    pub fn message(&self, lang: Lang) -> String {
+        match lang {
+            Lang::Ru => format!("сервер \"РЖД\" вернул код ошибки {}", self.status),
+            Lang::En => format!("the RZD server returned the status {}", self.status),
+        }
+    }
+}
+
+impl fmt::Display for HttpFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message(error_lang()))
+    }
+}
+
+impl StdError for HttpFailure {}
+
 /// The Errors wrapper that may occur.
-#[derive(Debug)]
+/// The errors of the underlying libraries are shared, so the error may be cloned.
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Error {
     /// An unsupported operation was called.
@@ -38,91 +364,283 @@ pub enum Error {
     ParseTimeError(String),
     /// Parsing of the station code failed.
     ParseStationCodeError(String),
+    /// Parsing of the price failed.
+    ParsePriceError(String),
+    /// Parsing of the URL of the request failed, the URL is kept.
+    ParseUrlError(String),
     /// A too short query passed.
     TooShortQuery,
     /// An empty number of the train passed.
     EmptyTrainNumber,
+    /// A required parameter of the search is not set, its name is kept.
+    MissingParameter(&'static str),
+    /// The departure and the arrival stations are the same or not set.
+    InvalidRoute {
+        from: RzdStationCode,
+        to: RzdStationCode,
+    },
     /// The request finished with an error.
-    ReqwestError(ReqwestError),
+    ReqwestError(Arc<ReqwestError>),
     /// The server returned a broken header.
-    ReqwestHeaderError(HeaderError),
+    ReqwestHeaderError(Arc<HeaderError>),
     /// Data serialization failed.
     SerializeError(String),
     /// Data deserialization failed.
     DeserializeError(String),
-    /// The server is probably overloaded.
-    RzdServerOverloaded,
+    /// Reading or writing of a file failed.
+    IoError(Arc<IoError>),
+    /// Writing to the SQLite database failed.
+    #[cfg(feature = "sqlite")]
+    SqliteError(Arc<rusqlite::Error>),
+    /// The server is probably overloaded, no data is got from the layer after the tries.
+    RzdServerOverloaded { layer: RzdLayer, attempts: u32 },
+    /// No data is got from the layer before the deadline of the search.
+    DeadlineExceeded { layer: RzdLayer, attempts: u32 },
     /// The server returned a bad reply.
     FailRzdResponse,
+    /// The server replied with an unsuccessful HTTP status.
+    HttpFailure(HttpFailure),
     /// The server returned an error description.
     RzdError(RzdErrors),
+    /// The date is outside the period of the advance sale,
+    /// the length of the period in days is kept if the server tells it,
+    /// and the errors of the server as they are returned.
+    OutsideSaleHorizon {
+        max_days: Option<u32>,
+        source: RzdErrors,
+    },
+    /// The server redirected to an anti-bot challenge page, the URL of the page is kept.
+    BotProtection(String),
+    /// The search is not finished, e.g. its worker panicked, the description of the search is kept.
+    SearchAborted(String),
+    /// The error of the request with the correlation id set by the caller,
+    /// see `Error::root` for the error itself.
+    Correlated(String, Box<Error>),
     /// Dummy error by default.
     Empty,
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::UnsupportedOperation => {
-                write!(f, "операция не поддерживается")
-            }
-            Error::ParseDateError(ref s) => {
-                debug!("parsing date error: {}", s);
-                write!(f, "ошибка преобразования даты")
+impl Error {
+    /// Returns the description of the error in the language.
+    /// The errors of the server are described by it in Russian only,
+    /// so `Error::RzdError` returns the text of the server in any language.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::{Error, Lang, RzdErrors};
+    /// #
+    /// let e = Error::TooShortQuery;
+    ///
+    /// assert_eq!(e.message(Lang::Ru), "передан слишком короткий запрос");
+    /// assert_eq!(e.message(Lang::En), "the query is too short");
+    ///
+    /// let e = Error::RzdError(RzdErrors::new(vec!["неверная дата отправления".to_string()]));
+    /// assert_eq!(e.message(Lang::En), "неверная дата отправления");
+    /// ```
+    pub fn message(&self, lang: Lang) -> String {
+        let (ru, en) = match *self {
+            Error::UnsupportedOperation => ("операция не поддерживается", "unsupported operation"),
+            Error::ParseDateError(_) => ("ошибка преобразования даты", "failed to parse the date"),
+            Error::ParseTimeError(_) => {
+                ("ошибка преобразования времени", "failed to parse the time")
             }
-            Error::ParseTimeError(ref s) => {
-                debug!("parsing time error: {}", s);
-                write!(f, "ошибка преобразования времени")
+            Error::ParseStationCodeError(_) => (
+                "ошибка преобразования кода станции",
+                "failed to parse the station code",
+            ),
+            Error::ParsePriceError(_) => {
+                ("ошибка преобразования цены", "failed to parse the price")
             }
-            Error::ParseStationCodeError(ref s) => {
-                debug!("parsing station code error: {}", s);
-                write!(f, "ошибка преобразования кода станции")
+            Error::ParseUrlError(_) => ("ошибка преобразования URL", "failed to parse the URL"),
+            Error::TooShortQuery => ("передан слишком короткий запрос", "the query is too short"),
+            Error::EmptyTrainNumber => (
+                "передан некорректный номер поезда",
+                "the train number is incorrect",
+            ),
+            Error::MissingParameter(_) => (
+                "не задан обязательный параметр запроса",
+                "a required parameter of the search is not set",
+            ),
+            Error::InvalidRoute { .. } => (
+                "станции отправления и прибытия не заданы или совпадают",
+                "the departure and the arrival stations are not set or the same",
+            ),
+            Error::ReqwestError(_) => (
+                "не удалось получить данные с сервера \"РЖД\"",
+                "failed to get data from the RZD server",
+            ),
+            Error::ReqwestHeaderError(_) | Error::FailRzdResponse => (
+                "сервер \"РЖД\" вернул некорректные данные",
+                "the RZD server returned malformed data",
+            ),
+            Error::SerializeError(_) => ("не удалось упаковать данные", "failed to serialize data"),
+            Error::DeserializeError(_) => (
+                "не удалось распаковать данные",
+                "failed to deserialize data",
+            ),
+            Error::IoError(_) => (
+                "ошибка чтения или записи файла",
+                "failed to read or write the file",
+            ),
+            #[cfg(feature = "sqlite")]
+            Error::SqliteError(_) => (
+                "ошибка записи в базу данных SQLite",
+                "failed to write to the SQLite database",
+            ),
+            Error::RzdServerOverloaded { .. } => (
+                "удаленный сервер перегружен, измените запрос или попробуйте позднее",
+                "the server is overloaded, change the query or try again later",
+            ),
+            Error::DeadlineExceeded { .. } => (
+                "истекло время ожидания данных от сервера \"РЖД\"",
+                "the deadline of the search is exceeded",
+            ),
+            Error::HttpFailure(ref e) => return e.message(lang),
+            // The server describes the errors in Russian only.
+            Error::RzdError(ref e) => return e.to_string(),
+            // The text of the server is kept, it is in Russian only.
+            Error::OutsideSaleHorizon {
+                max_days,
+                ref source,
+            } => {
+                return match (lang, max_days) {
+                    (Lang::Ru, _) => source.to_string(),
+                    (Lang::En, Some(days)) => format!(
+                        "the date is beyond the {}-day advance sale period: {}",
+                        days, source
+                    ),
+                    (Lang::En, None) => {
+                        format!("the date is beyond the advance sale period: {}", source)
+                    }
+                }
             }
-            Error::TooShortQuery => {
-                write!(f, "передан слишком короткий запрос")
-            }
-            Error::EmptyTrainNumber => {
-                write!(f, "передан некорректный номер поезда")
-            }
-            Error::ReqwestError(ref e) => {
-                error!("{}", e);
-                write!(f, "не удалось получить данные с сервера \"РЖД\"")
-            }
-            Error::ReqwestHeaderError(ref e) => {
-                error!("{}", e);
-                write!(f, "сервер \"РЖД\" вернул некорректные данные")
+            Error::BotProtection(_) => (
+                "сервер \"РЖД\" запросил проверку на робота",
+                "the RZD server asked to pass an anti-bot challenge",
+            ),
+            Error::SearchAborted(_) => ("поиск прерван", "the search is aborted"),
+            Error::Correlated(_, ref e) => return e.message(lang),
+            Error::Empty => ("ошибок нет", "no errors"),
+        };
+
+        match lang {
+            Lang::Ru => ru.to_string(),
+            Lang::En => en.to_string(),
+        }
+    }
+
+    /// Returns the code of the error reported by the server, if any.
+    /// It is the HTTP status or the code of the Express-3 gateway.
+    pub fn rzd_code(&self) -> Option<String> {
+        match *self.root() {
+            Error::HttpFailure(ref e) => Some(e.status().to_string()),
+            Error::RzdError(ref e) | Error::OutsideSaleHorizon { source: ref e, .. } => {
+                Some(e.gateway()?.error_code()?.to_string())
             }
-            Error::SerializeError(ref e) => {
-                error!("{}", e);
-                write!(f, "не удалось упаковать данные")
+            _ => None,
+        }
+    }
+
+    /// Returns true if the request failed on the way to the server and may be repeated,
+    /// e.g. on a timeout or a 502 status of a gateway.
+    pub fn is_transient(&self) -> bool {
+        match *self.root() {
+            Error::ReqwestError(ref e) => e.is_timeout() || e.is_connect(),
+            Error::HttpFailure(ref e) => matches!(e.status(), 408 | 429 | 500 | 502 | 503 | 504),
+            _ => false,
+        }
+    }
+
+    /// Returns the id of the request set by the caller, if any.
+    pub fn correlation_id(&self) -> Option<&str> {
+        match *self {
+            Error::Correlated(ref id, _) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Returns the error itself without the correlation id, it is the error to match on.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::Error;
+    /// #
+    /// let e = Error::Correlated("user-42".to_string(), Box::new(Error::TooShortQuery));
+    ///
+    /// assert!(matches!(e.root(), Error::TooShortQuery));
+    /// assert_eq!(e.to_string(), "[user-42] передан слишком короткий запрос");
+    /// ```
+    pub fn root(&self) -> &Error {
+        match *self {
+            Error::Correlated(_, ref e) => e.root(),
+            ref e => e,
+        }
+    }
+
+    /// Performs the conversion into the error itself without the correlation id.
+    pub fn into_root(self) -> Error {
+        match self {
+            Error::Correlated(_, e) => e.into_root(),
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::ParseDateError(ref s) => debug!("parsing date error: {}", s),
+            Error::ParseTimeError(ref s) => debug!("parsing time error: {}", s),
+            Error::ParseStationCodeError(ref s) => debug!("parsing station code error: {}", s),
+            Error::ParsePriceError(ref s) => debug!("parsing price error: {}", s),
+            Error::MissingParameter(name) => debug!("missing parameter: {}", name),
+            Error::InvalidRoute { from, to } => debug!("invalid route: {} - {}", from, to),
+            Error::ReqwestError(ref e) => error!("{}", e),
+            Error::ReqwestHeaderError(ref e) => error!("{}", e),
+            Error::SerializeError(ref e) => error!("{}", e),
+            Error::DeserializeError(ref e) => error!("{}", e),
+            Error::IoError(ref e) => error!("{}", e),
+            #[cfg(feature = "sqlite")]
+            Error::SqliteError(ref e) => error!("{}", e),
+            Error::RzdServerOverloaded { layer, attempts } => {
+                debug!("no reply of the layer {:?} after {} tries", layer, attempts)
             }
-            Error::DeserializeError(ref e) => {
-                error!("{}", e);
-                write!(f, "не удалось распаковать данные")
+            Error::DeadlineExceeded { layer, attempts } => {
+                debug!("deadline of the layer {:?} after {} tries", layer, attempts)
             }
-            Error::RzdServerOverloaded => {
-                write!(
-                    f,
-                    "удаленный сервер перегружен, измените запрос или попробуйте позднее"
-                )
+            Error::HttpFailure(ref e) => {
+                error!("{} returned {}: {}", e.url(), e.status(), e.body())
             }
-            Error::FailRzdResponse => {
-                write!(f, "сервер \"РЖД\" вернул некорректные данные")
+            Error::OutsideSaleHorizon { max_days, .. } => {
+                debug!("outside the sale horizon of {:?} days", max_days)
             }
-            Error::RzdError(ref e) => e.fmt(f),
-            Error::Empty => {
-                write!(f, "ошибок нет")
+            Error::BotProtection(ref url) => debug!("challenge page: {}", url),
+            Error::SearchAborted(ref search) => error!("search aborted: {}", search),
+            Error::Correlated(ref id, ref e) => {
+                write!(f, "[{}] ", id)?;
+                return e.fmt(f);
             }
+            _ => {}
         }
+
+        write!(f, "{}", self.message(error_lang()))
     }
 }
 
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
-            Error::ReqwestError(ref e) => Some(e),
-            Error::ReqwestHeaderError(ref e) => Some(e),
+            Error::ReqwestError(ref e) => Some(e.as_ref()),
+            Error::ReqwestHeaderError(ref e) => Some(e.as_ref()),
+            Error::IoError(ref e) => Some(e.as_ref()),
+            #[cfg(feature = "sqlite")]
+            Error::SqliteError(ref e) => Some(e.as_ref()),
             Error::RzdError(ref e) => Some(e),
+            Error::OutsideSaleHorizon { ref source, .. } => Some(source),
+            Error::HttpFailure(ref e) => Some(e),
+            Error::Correlated(_, ref e) => Some(e.as_ref()),
             _ => None,
         }
     }
@@ -130,18 +648,69 @@ impl StdError for Error {
 
 impl From<ReqwestError> for Error {
     fn from(error: ReqwestError) -> Error {
-        Error::ReqwestError(error)
+        Error::ReqwestError(Arc::new(error))
     }
 }
 
 impl From<HeaderError> for Error {
     fn from(error: HeaderError) -> Error {
-        Error::ReqwestHeaderError(error)
+        Error::ReqwestHeaderError(Arc::new(error))
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Error {
+        Error::IoError(Arc::new(error))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Error {
+        Error::SqliteError(Arc::new(error))
     }
 }
 
 impl From<RzdErrors> for Error {
+    /// The errors telling the date is outside the period of the advance sale
+    /// become `Error::OutsideSaleHorizon`.
     fn from(error: RzdErrors) -> Error {
-        Error::RzdError(error)
+        let mut outside = false;
+        let mut max_days = None;
+        for m in &error.0 {
+            if let Some(days) = sale_horizon(m) {
+                outside = true;
+                max_days = max_days.or(days);
+            }
+        }
+
+        match outside {
+            true => Error::OutsideSaleHorizon {
+                max_days,
+                source: error,
+            },
+            false => Error::RzdError(error),
+        }
     }
 }
+
+// Returns `Some` if the message tells the date is outside the period of the advance sale,
+// with the length of the period if there is a number of days,
+// e.g. "дата отправления находится за пределами периода 90 дней".
+fn sale_horizon(message: &str) -> Option<Option<u32>> {
+    let message = message.to_lowercase();
+    if !message.contains("за пределами периода") {
+        return None;
+    }
+
+    let words: Vec<&str> = message
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let days = words
+        .windows(2)
+        .find(|w| w[1].starts_with("дн") || w[1].starts_with("сут"))
+        .and_then(|w| w[0].parse().ok());
+
+    Some(days)
+}
@@ -0,0 +1,182 @@
+//! Reply fixtures for the deserializer tests.
+//!
+//! The fixtures are the replies of the RZD server stored as JSON files
+//! in the `tests/fixtures/` directory of the crate.
+//!
+//! ```rust,no_run
+//! # use rzd_trains::fixtures::save_fixture;
+//! # use rzd_trains::{RouteList, TrainScheduleSearch, TrainType};
+//! #
+//! let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+//!
+//! let path = save_fixture::<RouteList, _>(&q, "train_schedule").unwrap();
+//! ```
+
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+use crate::client::RequestOptions;
+use crate::client::{RzdClient, RzdClientInterface};
+use crate::error::Error;
+use crate::Result;
+
+// Technical fields of the replies which differ from request to request.
+const VOLATILE_KEYS: &[&str] = &[
+    "timestamp",
+    "ReqExpressZK",
+    "ReqExpressDateTime",
+    "ReqLocalRecv",
+    "ReqLocalSend",
+    "ReqAddress",
+];
+
+/// Returns the path of the fixture file by its name.
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{}.json", name))
+}
+
+/// Makes a request to the server and stores the anonymized reply
+/// as the fixture with the given name.
+///
+/// # Errors
+///
+/// The method fails if the request failed or the file couldn't be written.
+pub fn save_fixture<T, U>(search: &U, name: &str) -> Result<Option<PathBuf>>
+where
+    U: RzdClientInterface<T>,
+{
+    let body = match RzdClient::fetch(search)? {
+        Some((_data, body)) => body,
+        None => return Ok(None),
+    };
+
+    let path = fixture_path(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, anonymize(&body)?)?;
+    info!("fixture saved to {}", path.display());
+
+    Ok(Some(path))
+}
+
+/// Reads the fixture with the given name.
+///
+/// # Errors
+///
+/// The method fails if the file couldn't be read.
+pub fn load_fixture(name: &str) -> Result<String> {
+    let body = fs::read_to_string(fixture_path(name))?;
+    Ok(body)
+}
+
+/// Removes the technical fields which differ from request to request
+/// and formats the reply to be readable.
+///
+/// # Errors
+///
+/// The method fails if the reply isn't a valid JSON.
+pub fn anonymize(body: &str) -> Result<String> {
+    let mut value: Value = match serde_json::from_str(body) {
+        Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
+        Ok(v) => v,
+    };
+
+    remove_volatile_keys(&mut value);
+
+    match serde_json::to_string_pretty(&value) {
+        Err(e) => Err(Error::SerializeError(format!("{}", e))),
+        Ok(s) => Ok(s),
+    }
+}
+
+// Answers the requests on a local port with the replies made from the request line,
+// e.g. `GET /timetable/public/ru?layer_id=5827 HTTP/1.1`.
+// Returns the options sending the requests there.
+#[cfg(test)]
+pub(crate) fn serve(reply: impl Fn(&str) -> String + Send + 'static) -> RequestOptions {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use url::Url;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            let _ = reader.read_line(&mut request);
+            // The headers end with an empty line.
+            let mut header = String::from("-");
+            while !header.trim_end().is_empty() {
+                header.clear();
+                if reader.read_line(&mut header).unwrap_or(0) == 0 {
+                    break;
+                }
+            }
+
+            let body = reply(&request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+
+    RequestOptions::new().with_url_rewriter(Arc::new(move |mut url: Url| {
+        let _ = url.set_scheme("http");
+        let _ = url.set_host(Some("127.0.0.1"));
+        let _ = url.set_port(Some(port));
+        url
+    }))
+}
+
+fn remove_volatile_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in VOLATILE_KEYS {
+                map.remove(*key);
+            }
+            for v in map.values_mut() {
+                remove_volatile_keys(v);
+            }
+        }
+        Value::Array(list) => {
+            for v in list.iter_mut() {
+                remove_volatile_keys(v);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{anonymize, load_fixture};
+
+    #[test]
+    fn anonymize_test() {
+        let answer = r#"{"result":"OK","tp":[{"list":[],"timestamp":"01.04.2022"}],"timestamp":"20.03.2022 18:28:31.458"}"#;
+
+        assert_eq!(
+            anonymize(answer).unwrap(),
+            "{\n  \"result\": \"OK\",\n  \"tp\": [\n    {\n      \"list\": []\n    }\n  ]\n}"
+        );
+        assert!(anonymize("{").is_err());
+    }
+
+    #[test]
+    fn load_fixture_test() {
+        assert!(load_fixture("train_info").is_ok());
+        assert!(load_fixture("not_existing").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Property-based tests feeding malformed replies into the hand-written deserializers.
+//!
+//! Every reply parser must never panic and must report a broken payload with a typed error.
+//! The untouched replies must be parsed, and the replies with a broken required field must fail.
+
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::fixtures::load_fixture;
+use crate::{parse_schedule_reply, parse_train_reply, parse_trip_reply, Result};
+
+/// Keys met in the replies, so that generated objects hit the deserializers' branches.
+const KEYS: &[&str] = &[
+    "result",
+    "type",
+    "tp",
+    "list",
+    "lst",
+    "value",
+    "rid",
+    "fail_msg",
+    "number",
+    "number2",
+    "date0",
+    "time0",
+    "date1",
+    "time1",
+    "code0",
+    "code1",
+    "station0",
+    "station1",
+    "cars",
+    "seats",
+    "tariff",
+    "freeSeats",
+    "timeInWay",
+    "brand",
+    "carrier",
+    "Error",
+    "content",
+    "Code",
+    "GtExpress_Response",
+    "Routes",
+    "Route",
+    "Stop",
+    "Train",
+    "Station",
+    "CodeStation",
+    "DepTime",
+    "ArvTime",
+    "Number",
+    "DepDate",
+    "subtrainCatName",
+    "depth",
+    "msgList",
+];
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".*".prop_map(Value::String),
+        prop::sample::select(vec![
+            "OK",
+            "FAIL",
+            "RID",
+            "REQUEST_ID",
+            "01.04.2022",
+            "23:55",
+            "24:61",
+            "32.13.2022",
+            "СК ФИРМ",
+            "null",
+        ])
+        .prop_map(|s| Value::String(s.to_string())),
+    ]
+}
+
+fn json_value() -> impl Strategy<Value = Value> {
+    scalar().prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+            prop::collection::vec((prop::sample::select(KEYS), inner), 0..8).prop_map(|fields| {
+                Value::Object(
+                    fields
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .collect::<Map<_, _>>(),
+                )
+            }),
+        ]
+    })
+}
+
+/// Drops or replaces the values of a valid reply, driven by the `choices`.
+/// Returns true if the reply is changed.
+fn mutate(value: &mut Value, choices: &mut impl Iterator<Item = (u8, Value)>) -> bool {
+    let mut changed = false;
+    match value {
+        Value::Object(map) => {
+            let keys: Vec<String> = map.keys().cloned().collect();
+            for key in keys {
+                match choices.next() {
+                    Some((0, _)) => {
+                        map.remove(&key);
+                        changed = true;
+                    }
+                    Some((1, v)) => {
+                        changed |= map.insert(key, v.clone()) != Some(v);
+                    }
+                    Some(_) => changed |= mutate(map.get_mut(&key).unwrap(), choices),
+                    None => break,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                changed |= mutate(item, choices);
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+/// The reply of the fixture mutated and whether it is changed.
+fn mutated(fixture: &str) -> impl Strategy<Value = (String, bool)> {
+    let reply: Value = serde_json::from_str(&load_fixture(fixture).unwrap()).unwrap();
+    prop::collection::vec((0u8..16, scalar()), 0..256).prop_map(move |choices| {
+        let mut reply = reply.clone();
+        let changed = mutate(&mut reply, &mut choices.into_iter());
+        (reply.to_string(), changed)
+    })
+}
+
+/// The reply of the fixture with the value of the top-level field replaced,
+/// or with the field dropped if the value is `None`.
+fn with_field(fixture: &str, key: &str, value: Option<Value>) -> String {
+    let mut reply: Value = serde_json::from_str(&load_fixture(fixture).unwrap()).unwrap();
+    let map = reply.as_object_mut().unwrap();
+    match value {
+        Some(v) => map.insert(key.to_string(), v),
+        None => map.remove(key),
+    };
+    reply.to_string()
+}
+
+// Statuses of the reply taken as successful or as waiting for the data.
+fn is_status(value: &Option<Value>) -> bool {
+    matches!(value, Some(Value::String(s)) if ["OK", "RID", "REQUEST_ID"].contains(&s.as_str()))
+}
+
+/// Checks the error is typed, the untouched reply must be parsed with the data.
+fn check<T>(result: Result<Option<T>>, changed: bool) {
+    match result {
+        Err(e) => {
+            assert!(!matches!(e, Error::Empty), "untyped error");
+            assert!(changed, "untouched reply failed: {:?}", e);
+        }
+        Ok(None) => assert!(changed, "no data in untouched reply"),
+        Ok(Some(_)) => {}
+    }
+}
+
+/// Checks the reply with a broken required field fails with a typed error.
+fn check_broken<T>(result: Result<Option<T>>) {
+    match result {
+        Err(e) => assert!(!matches!(e, Error::Empty), "untyped error"),
+        Ok(_) => panic!("broken reply is accepted"),
+    }
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_text_test(body in ".*") {
+        check(parse_schedule_reply(&body), true);
+        check(parse_train_reply(&body), true);
+        check(parse_trip_reply(&body), true);
+    }
+
+    #[test]
+    fn arbitrary_json_test(value in json_value()) {
+        let body = value.to_string();
+        check(parse_schedule_reply(&body), true);
+        check(parse_train_reply(&body), true);
+        check(parse_trip_reply(&body), true);
+    }
+
+    #[test]
+    fn mutated_schedule_test((body, changed) in mutated("train_schedule")) {
+        check(parse_schedule_reply(&body), changed);
+    }
+
+    #[test]
+    fn mutated_train_test((body, changed) in mutated("train_info")) {
+        check(parse_train_reply(&body), changed);
+    }
+
+    #[test]
+    fn mutated_trip_test((body, changed) in mutated("trip_info")) {
+        check(parse_trip_reply(&body), changed);
+    }
+
+    #[test]
+    fn broken_status_test(value in prop::option::of(scalar())) {
+        prop_assume!(!is_status(&value));
+        check_broken(parse_schedule_reply(&with_field("train_schedule", "result", value.clone())));
+        check_broken(parse_train_reply(&with_field("train_info", "result", value)));
+    }
+
+    #[test]
+    fn broken_list_test(value in scalar()) {
+        prop_assume!(!value.is_array());
+        check_broken(parse_schedule_reply(&with_field("train_schedule", "tp", Some(value.clone()))));
+        check_broken(parse_train_reply(&with_field("train_info", "lst", Some(value.clone()))));
+        check_broken(parse_trip_reply(&with_field("trip_info", "GtExpress_Response", Some(value))));
+    }
+}
@@ -0,0 +1,142 @@
+//! Distances between the stations and a point, enabled by the `geo` feature.
+//!
+//! The server returns no locations of the stations, so the crate bundles a dataset of them
+//! for the main stations, it is applied by `enrich_coordinates`. The locations of the other
+//! stations are set by `StationItem::with_coordinates`.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::station_codes::Coordinates;
+use crate::{StationItem, StationList};
+
+// Lines of the dataset are `code;latitude;longitude;name`.
+const STATION_COORDINATES: &str = include_str!("station_coordinates.csv");
+
+// Locations of the dataset by the station code, the malformed lines are skipped.
+fn dataset() -> HashMap<u32, Coordinates> {
+    STATION_COORDINATES
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut fields = l.split(';');
+            let code = fields.next()?.parse().ok()?;
+            let latitude = fields.next()?.parse().ok()?;
+            let longitude = fields.next()?.parse().ok()?;
+            Some((code, Coordinates::new(latitude, longitude)))
+        })
+        .collect()
+}
+
+/// Sets the locations of the stations from the bundled dataset,
+/// the stations missing in it keep their locations.
+/// Returns the number of the stations the location is set for.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use rzd_trains::{enrich_coordinates, Coordinates, RzdClient, StationCodeSearch, StationList};
+///
+/// let q = StationCodeSearch::new("москва").unwrap();
+/// let mut stations = RzdClient::<StationList>::get(&q).unwrap().unwrap();
+///
+/// enrich_coordinates(&mut stations);
+/// stations.sort_by_distance(Coordinates::new(55.7558, 37.6173));
+/// ```
+pub fn enrich_coordinates(stations: &mut StationList) -> usize {
+    let dataset = dataset();
+    let mut found = 0;
+    for s in stations.0.iter_mut() {
+        if let Some(c) = dataset.get(&s.code().to_uint()) {
+            s.coordinates = Some(*c);
+            found += 1;
+        }
+    }
+    found
+}
+
+impl StationList {
+    /// Sorts the stations by the distance to the point, nearest first,
+    /// the stations without the location go last in the same order.
+    pub fn sort_by_distance(&mut self, point: Coordinates) {
+        self.0
+            .sort_by(|a, b| match (a.distance_km(point), b.distance_km(point)) {
+                (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+    }
+
+    /// Returns the station nearest to the point among the ones with the location.
+    pub fn nearest(&self, point: Coordinates) -> Option<&StationItem> {
+        self.iter()
+            .filter_map(|s| s.distance_km(point).map(|d| (s, d)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(s, _)| s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dataset, enrich_coordinates, STATION_COORDINATES};
+    use crate::station_codes::Coordinates;
+    use crate::{ResultList, RzdStationCode, StationItem, StationList};
+
+    fn stations() -> StationList {
+        let item = |name: &str, code| StationItem::new(name.to_string(), RzdStationCode::new(code));
+        ResultList::new(vec![
+            item("ТОРЖОК", 2004601),
+            item("МОСКВА", 2000000),
+            item("САНКТ-ПЕТЕРБУРГ", 2004000),
+        ])
+    }
+
+    #[test]
+    fn dataset_test() {
+        let lines = STATION_COORDINATES
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .count();
+        let dataset = dataset();
+        assert_eq!(dataset.len(), lines);
+        assert!(dataset
+            .values()
+            .all(|c| c.latitude().abs() <= 90.0 && c.longitude().abs() <= 180.0));
+
+        let spb = dataset[&2004000];
+        assert_eq!(dataset[&2000000].distance_km(spb).round(), 634.0);
+    }
+
+    #[test]
+    fn enrich_coordinates_test() {
+        let mut stations = stations();
+        let point = Coordinates::new(43.0, 131.0);
+        stations.0[0] = stations.0[0].clone().with_coordinates(point);
+
+        assert_eq!(enrich_coordinates(&mut stations), 2);
+        let found: Vec<Option<Coordinates>> = stations.iter().map(|s| s.coordinates()).collect();
+        assert_eq!(
+            found,
+            [
+                Some(point),
+                Some(Coordinates::new(55.7558, 37.6173)),
+                Some(Coordinates::new(59.9386, 30.3141))
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_distance_test() {
+        let mut stations = stations();
+        enrich_coordinates(&mut stations);
+
+        // Tver is nearer to Moscow.
+        let tver = Coordinates::new(56.8587, 35.9176);
+        assert_eq!(stations.nearest(tver).unwrap().name(), "МОСКВА");
+
+        stations.sort_by_distance(Coordinates::new(59.9, 30.3));
+        let names: Vec<&str> = stations.iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["САНКТ-ПЕТЕРБУРГ", "МОСКВА", "ТОРЖОК"]);
+    }
+}
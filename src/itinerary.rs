@@ -0,0 +1,272 @@
+//! Neutral model of the trips to mix the trains with the other kinds of transport,
+//! e.g. in multi-modal planners.
+
+use chrono::NaiveDateTime;
+
+use crate::train_schedule::TrainInfo;
+use crate::{Route, RouteList, TrainDate, TrainTime};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A point of departure or arrival.
+pub struct Place {
+    name: String,
+    code: String,
+}
+
+impl Place {
+    /// Creates `Place` from the name and the code in any coding system.
+    pub fn new(name: impl Into<String>, code: impl Into<String>) -> Self {
+        Place {
+            name: name.into(),
+            code: code.into(),
+        }
+    }
+
+    /// Returns the name of the place.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the code of the place, e.g. the RZD station code.
+    #[inline]
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A trip by one vehicle without changes.
+///
+/// The times are local ones as the carrier shows them.
+pub struct Leg {
+    origin: Place,
+    destination: Place,
+    departure: Option<NaiveDateTime>,
+    arrival: Option<NaiveDateTime>,
+    vehicle_id: String,
+    operator: Option<String>,
+}
+
+impl Leg {
+    /// Creates `Leg` between the places made by the vehicle.
+    pub fn new(origin: Place, destination: Place, vehicle_id: impl Into<String>) -> Self {
+        Leg {
+            origin,
+            destination,
+            departure: None,
+            arrival: None,
+            vehicle_id: vehicle_id.into(),
+            operator: None,
+        }
+    }
+
+    /// Sets the departure time.
+    pub fn with_departure(mut self, departure: NaiveDateTime) -> Self {
+        self.departure = Some(departure);
+        self
+    }
+
+    /// Sets the arrival time.
+    pub fn with_arrival(mut self, arrival: NaiveDateTime) -> Self {
+        self.arrival = Some(arrival);
+        self
+    }
+
+    /// Sets the name of the operator.
+    pub fn with_operator(mut self, operator: impl Into<String>) -> Self {
+        self.operator = Some(operator.into());
+        self
+    }
+
+    /// Returns the place of departure.
+    #[inline]
+    pub fn origin(&self) -> &Place {
+        &self.origin
+    }
+
+    /// Returns the place of arrival.
+    #[inline]
+    pub fn destination(&self) -> &Place {
+        &self.destination
+    }
+
+    /// Returns the departure time, if known.
+    #[inline]
+    pub fn departure(&self) -> Option<NaiveDateTime> {
+        self.departure
+    }
+
+    /// Returns the arrival time, if known.
+    #[inline]
+    pub fn arrival(&self) -> Option<NaiveDateTime> {
+        self.arrival
+    }
+
+    /// Returns the id of the vehicle, e.g. the train or the flight number.
+    #[inline]
+    pub fn vehicle_id(&self) -> &str {
+        &self.vehicle_id
+    }
+
+    /// Returns the name of the operator, if known.
+    #[inline]
+    pub fn operator(&self) -> Option<&str> {
+        self.operator.as_deref()
+    }
+}
+
+impl From<&TrainInfo> for Leg {
+    fn from(train: &TrainInfo) -> Self {
+        let origin = Place::new(
+            train.leaving_station(),
+            train.leaving_station_code().to_string(),
+        );
+        let destination = Place::new(
+            train.arriving_station(),
+            train.arriving_station_code().to_string(),
+        );
+
+        Leg {
+            origin,
+            destination,
+            departure: date_time(train.leaving_date(), train.leaving_time()),
+            arrival: date_time(train.arriving_date(), train.arriving_time()),
+            vehicle_id: train.train_number().to_string(),
+            operator: train.carrier().name_opt().map(str::to_string),
+        }
+    }
+}
+
+// Joins the date and the time, both are required.
+fn date_time(date: Option<TrainDate>, time: Option<TrainTime>) -> Option<NaiveDateTime> {
+    Some(date?.0.and_time(time?.0))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A trip made of the legs in the order of travel.
+pub struct Itinerary(Vec<Leg>);
+
+impl Itinerary {
+    /// Creates `Itinerary` from the legs in the order of travel.
+    pub fn new(legs: Vec<Leg>) -> Self {
+        Itinerary(legs)
+    }
+
+    /// Returns the legs.
+    #[inline]
+    pub fn legs(&self) -> &[Leg] {
+        &self.0
+    }
+
+    /// Returns the place the trip starts at.
+    pub fn origin(&self) -> Option<&Place> {
+        self.0.first().map(|l| l.origin())
+    }
+
+    /// Returns the place the trip ends at.
+    pub fn destination(&self) -> Option<&Place> {
+        self.0.last().map(|l| l.destination())
+    }
+
+    /// Returns the departure time of the first leg.
+    pub fn departure(&self) -> Option<NaiveDateTime> {
+        self.0.first().and_then(|l| l.departure())
+    }
+
+    /// Returns the arrival time of the last leg.
+    pub fn arrival(&self) -> Option<NaiveDateTime> {
+        self.0.last().and_then(|l| l.arrival())
+    }
+}
+
+impl From<&TrainInfo> for Itinerary {
+    fn from(train: &TrainInfo) -> Self {
+        Itinerary(vec![Leg::from(train)])
+    }
+}
+
+impl Route {
+    /// Returns the trains of the route as the alternative one-leg itineraries.
+    pub fn itineraries(&self) -> Vec<Itinerary> {
+        self.trains().iter().map(Itinerary::from).collect()
+    }
+}
+
+impl From<&RouteList> for Vec<Itinerary> {
+    /// Takes the trains of all routes as the alternative one-leg itineraries.
+    fn from(routes: &RouteList) -> Self {
+        routes.iter().flat_map(|r| r.itineraries()).collect()
+    }
+}
+
+impl From<RouteList> for Vec<Itinerary> {
+    fn from(routes: RouteList) -> Self {
+        Vec::from(&routes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Itinerary, Leg, Place};
+    use crate::fixtures::load_fixture;
+    use crate::parse_schedule_reply;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn route_list_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let trains = routes.as_ref()[0].trains().as_ref().len();
+
+        let itineraries: Vec<Itinerary> = Vec::from(&routes);
+        assert_eq!(itineraries.len(), trains);
+
+        let leg = &itineraries[0].legs()[0];
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+        assert_eq!(leg.vehicle_id(), train.train_number());
+        assert_eq!(leg.origin().name(), train.leaving_station());
+        assert_eq!(
+            leg.origin().code(),
+            train.leaving_station_code().to_string()
+        );
+        assert_eq!(
+            leg.departure().map(|d| d.date()),
+            train.leaving_date().map(|d| d.0)
+        );
+        assert!(leg.arrival() > leg.departure());
+    }
+
+    #[test]
+    fn itinerary_test() {
+        let at = |h| {
+            NaiveDate::from_ymd_opt(2022, 4, 1)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        let itinerary = Itinerary::new(vec![
+            Leg::new(
+                Place::new("Москва", "2000000"),
+                Place::new("Тверь", "2044001"),
+                "751А",
+            )
+            .with_departure(at(6))
+            .with_arrival(at(8)),
+            Leg::new(
+                Place::new("Тверь", "TVR"),
+                Place::new("Ржев", "RZV"),
+                "bus 15",
+            )
+            .with_departure(at(9))
+            .with_operator("Тверьавтотранс"),
+        ]);
+
+        assert_eq!(itinerary.origin().unwrap().name(), "Москва");
+        assert_eq!(itinerary.destination().unwrap().code(), "RZV");
+        assert_eq!(itinerary.departure(), Some(at(6)));
+        assert_eq!(itinerary.arrival(), None);
+        assert_eq!(itinerary.legs()[1].operator(), Some("Тверьавтотранс"));
+        assert_eq!(Itinerary::default().origin(), None);
+    }
+}
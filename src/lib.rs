@@ -29,37 +29,114 @@
 #[macro_use]
 extern crate log;
 
-use chrono::{Datelike, NaiveDate, NaiveTime, Timelike, Utc};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
 use std::{fmt, fmt::Debug, fmt::Display, str::FromStr};
 
 mod error;
-pub use crate::error::{Error, RzdErrors};
+pub use crate::error::{
+    error_lang, set_error_lang, Error, GatewayInfo, HttpFailure, RzdErrorSet, RzdErrors,
+};
 
 /// A `Result` alias where the `Err` case is `rzd_trains::Error`.
 type Result<T> = std::result::Result<T, Error>;
 
+mod clock;
+pub use crate::clock::{Clock, Sleeper, SystemClock};
+
 mod client;
-pub use client::RzdClient;
+pub use client::{
+    Backoff, ChallengeResolver, Payload, PayloadKind, PayloadSink, PendingTicket, PlannedRequest,
+    PollState, Polling, QuickAnswer, RequestOptions, RequestPhase, ResponseMeta, RzdClient,
+    RzdLayer, RzdRequestId, SessionToken, UrlRewriter,
+};
+/// URL of the request made for the search, see e.g. `TrainScheduleSearch::request_url`.
+pub use url::Url;
+
+mod cache;
+pub use crate::cache::{CacheStats, ReplyCache};
 
 mod ser;
 
 mod des;
 
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+
 mod station_codes;
-pub use crate::station_codes::{StationCodeSearch, StationItem};
+pub use crate::station_codes::{
+    resolve_stations, resolve_stations_with, Coordinates, Matcher, ResolvedStation,
+    StationCodeSearch, StationGroup, StationItem, StationSearchResult,
+};
 pub type StationList = ResultList<StationItem>;
 
 mod train_schedule;
-pub use crate::train_schedule::{Route, TrainScheduleSearch};
+pub use crate::train_schedule::{
+    find_first_available, find_first_available_with, parse_schedule_reply, AvailabilityCell,
+    AvailabilityMatrix, Route, SaleState, ScheduleOptions, StopPolicy, TrainScheduleSearch,
+    TrainSummary, TransferSearchMode,
+};
 pub type RouteList = ResultList<Route>;
 
 mod train_info;
-pub use crate::train_info::{TrainItem, TrainSearch};
+pub use crate::train_info::{
+    parse_train_reply, CarCount, CarListSearch, CarOffer, CarOrder, CompositionDiff, GroupedCars,
+    TrainComposition, TrainItem, TrainSearch, TrainSearchBuilder,
+};
 pub type TrainInfoList = ResultList<TrainItem>;
 
+mod seats;
+pub use crate::seats::{CarSeats, TrainSeats};
+
 mod trip_info;
-pub use crate::trip_info::{TripStations, TripStopsSearch};
+pub use crate::trip_info::{parse_trip_reply, TripLeg, TripStations, TripStopsSearch};
+
+mod scheduler;
+pub use crate::scheduler::Scheduler;
+
+mod trip_report;
+pub use crate::trip_report::TripReport;
+
+mod itinerary;
+pub use crate::itinerary::{Itinerary, Leg, Place};
+
+mod matrix;
+pub use crate::matrix::{matrix_search, MatrixCell, MatrixSearch, TravelMatrix};
+
+mod weekly;
+pub use crate::weekly::{weekly_timetable, WeeklyRun, WeeklySearch, WeeklyTimetable, WeeklyTrain};
+
+mod stats;
+#[cfg(feature = "allocations")]
+pub use crate::stats::CountingAllocator;
+pub use crate::stats::{parse_stats, ParseStats};
+
+mod notify;
+pub use crate::notify::{Change, ChannelSink, LogSink, NotificationSink};
+
+mod schema;
+pub use crate::schema::SCHEMA_VERSION;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite::{export_to_sqlite, SqliteExport};
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+
+#[cfg(feature = "report")]
+mod report;
+
+#[cfg(feature = "geo")]
+mod geo;
+#[cfg(feature = "geo")]
+pub use crate::geo::enrich_coordinates;
+
+#[cfg(test)]
+mod fuzz;
 
 #[repr(u8)]
 #[derive(Copy, Clone, PartialEq)]
@@ -70,7 +147,7 @@ enum RouteDirection {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 /// Type of the trains.
 pub enum TrainType {
     /// Long-distance trains.
@@ -81,14 +158,148 @@ pub enum TrainType {
     AllTrains = 3,
 }
 
-#[repr(u8)]
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Category of the train.
+pub enum TrainCategory {
+    /// Firm long-distance trains.
+    Firm,
+    /// Fast long-distance trains.
+    Fast,
+    /// Passenger trains.
+    Passenger,
+    /// Express trains.
+    Express,
+    /// The category is unknown.
+    #[default]
+    Unknown,
+}
+
+impl Display for TrainCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TrainCategory::Firm => "фирменный",
+            TrainCategory::Fast => "скорый",
+            TrainCategory::Passenger => "пассажирский",
+            TrainCategory::Express => "экспресс",
+            TrainCategory::Unknown => "",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<&str> for TrainCategory {
+    // Takes the category name used by RZD, e.g. "СК ФИРМ" or "Экспресс",
+    // or its English version, e.g. "SK FIRM" or "Express".
+    fn from(s: &str) -> Self {
+        let s = s.trim().to_uppercase();
+
+        if s.contains("ФИРМ") || s.contains("FIRM") {
+            TrainCategory::Firm
+        } else if s.contains("ЭКСПРЕСС") || s.contains("EXPRESS") {
+            TrainCategory::Express
+        } else if s.starts_with("СК") || s.starts_with("SK") || s.starts_with("FAST") {
+            TrainCategory::Fast
+        } else if s.starts_with("ПАСС") || s.starts_with("PASS") {
+            TrainCategory::Passenger
+        } else {
+            TrainCategory::Unknown
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Carrier company of the train.
+pub enum Carrier {
+    /// Federal Passenger Company, "ФПК".
+    Fpk,
+    /// Directorate of high-speed communication, "ДОСС".
+    Doss,
+    /// North-Western Suburban Passenger Company, "СЗППК".
+    Szppk,
+    /// Central Suburban Passenger Company, "ЦППК".
+    Cppk,
+    /// Other carrier with the name as the server returns it.
+    Other(String),
+    /// The carrier is unknown.
+    #[default]
+    Unknown,
+}
+
+impl Carrier {
+    /// Returns the name of the carrier as the server returns it.
+    pub fn name(&self) -> &str {
+        match self {
+            Carrier::Fpk => "ФПК",
+            Carrier::Doss => "ДОСС",
+            Carrier::Szppk => "СЗППК",
+            Carrier::Cppk => "ЦППК",
+            Carrier::Other(name) => name,
+            Carrier::Unknown => "",
+        }
+    }
+
+    /// Returns the name of the carrier, `None` if it is unknown.
+    pub fn name_opt(&self) -> Option<&str> {
+        non_empty(self.name())
+    }
+}
+
+impl Display for Carrier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl From<&str> for Carrier {
+    // Takes the carrier name used by RZD, e.g. "ФПК" or "FPK".
+    fn from(s: &str) -> Self {
+        let s = s.trim();
+
+        match s.to_uppercase().as_str() {
+            "" => Carrier::Unknown,
+            "ФПК" | "FPK" => Carrier::Fpk,
+            "ДОСС" | "DOSS" => Carrier::Doss,
+            "СЗППК" | "SZPPK" => Carrier::Szppk,
+            "ЦППК" | "CPPK" => Carrier::Cppk,
+            _ => Carrier::Other(s.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 /// What trains should it search, with free seats only or any train?
-enum ShowSeats {
+pub enum ShowSeats {
     /// Trains with free seats only.
-    FreeOnly = 1,
-    /// All trains.
-    All = 0,
+    FreeOnly,
+    /// All trains, the server may omit the trains with no seat availability data.
+    All,
+    /// All trains, including the sold out ones. Such trains are returned
+    /// with an empty list of seats and marked as sold out.
+    AllIncludingSoldOut,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Language of the names returned by the server.
+pub enum Lang {
+    /// Russian names.
+    #[default]
+    Ru,
+    /// English names in Latin letters.
+    En,
+}
+
+fn is_default_lang(lang: &Lang) -> bool {
+    *lang == Lang::default()
+}
+
+impl Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = match self {
+            Lang::Ru => "ru",
+            Lang::En => "en",
+        };
+        write!(f, "{}", code)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -108,13 +319,82 @@ impl TrainDate {
     /// assert_eq!(format!("{}", d), "01.04.2022");
     /// ```
     pub fn new(year: u32, month: u32, day: u32) -> Self {
+        TrainDate::new_with_clock(year, month, day, &SystemClock)
+    }
+
+    /// Creates `TrainDate` from a year, a month and a day.
+    /// The date of the `clock` is taken if the passed one is invalid.
+    pub fn new_with_clock(year: u32, month: u32, day: u32, clock: &dyn Clock) -> Self {
         let date = match NaiveDate::from_ymd_opt(year as i32, month, day) {
             Some(d) => d,
-            None => Utc::now().naive_utc().date(),
+            None => clock.now().naive_utc().date(),
         };
 
         TrainDate(date)
     }
+
+    /// Returns the date written in the format, the `Display` one is `DateFormat::Rzd`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::{DateFormat, TrainDate};
+    /// #
+    /// let d = TrainDate::new(2022, 4, 1);
+    ///
+    /// assert_eq!(d.format(DateFormat::Iso), "2022-04-01");
+    /// assert_eq!(d.format(DateFormat::Long), "1 апреля 2022");
+    /// ```
+    pub fn format(&self, format: DateFormat) -> String {
+        const MONTHS: [&str; 12] = [
+            "января",
+            "февраля",
+            "марта",
+            "апреля",
+            "мая",
+            "июня",
+            "июля",
+            "августа",
+            "сентября",
+            "октября",
+            "ноября",
+            "декабря",
+        ];
+        let month = MONTHS[self.0.month0() as usize];
+
+        match format {
+            DateFormat::Rzd => self.to_string(),
+            DateFormat::Iso => self.0.format("%Y-%m-%d").to_string(),
+            DateFormat::Long => format!("{} {} {}", self.0.day(), month, self.0.year()),
+            DateFormat::DayMonth => format!("{} {}", self.0.day(), month),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+/// Format of the date in the output, see `TrainDate::format`.
+pub enum DateFormat {
+    /// "01.04.2022", the format of the server and of `Display`.
+    #[default]
+    Rzd,
+    /// "2022-04-01".
+    Iso,
+    /// "1 апреля 2022".
+    Long,
+    /// "1 апреля".
+    DayMonth,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+/// Format of the time in the output, see `TrainTime::format`.
+pub enum TimeFormat {
+    /// "05:07", the format of the server and of `Display`.
+    #[default]
+    Rzd,
+    /// "05:07:30".
+    WithSeconds,
+    /// "5:07", the hours have no leading zero.
+    Short,
 }
 
 impl Display for TrainDate {
@@ -129,6 +409,19 @@ impl From<NaiveDate> for TrainDate {
     }
 }
 
+impl From<NaiveDateTime> for TrainDate {
+    fn from(dt: NaiveDateTime) -> Self {
+        TrainDate::from(dt.date())
+    }
+}
+
+impl From<(u32, u32, u32)> for TrainDate {
+    /// Takes a year, a month and a day.
+    fn from((year, month, day): (u32, u32, u32)) -> Self {
+        TrainDate::new(year, month, day)
+    }
+}
+
 impl Into<NaiveDate> for TrainDate {
     #[inline]
     fn into(self) -> NaiveDate {
@@ -139,15 +432,18 @@ impl Into<NaiveDate> for TrainDate {
 impl FromStr for TrainDate {
     type Err = Error;
 
+    /// Takes the date in the format of the server, e.g. "01.04.2022".
+    /// Unlike `TrainDate::new`, fails if the date doesn't exist, e.g. "31.02.2022".
     fn from_str(s: &str) -> Result<Self> {
-        let v: Vec<u32> = s.split(".").filter_map(|s| s.parse().ok()).collect();
+        let v: Option<Vec<u32>> = s.trim().split('.').map(|s| s.parse().ok()).collect();
 
-        let (day, month, year) = match &v[..] {
-            &[d, m, y] => (d, m, y),
-            _ => return Err(Error::ParseDateError(s.to_string())),
+        let date = match v.as_deref() {
+            Some(&[d, m, y]) => NaiveDate::from_ymd_opt(y as i32, m, d),
+            _ => None,
         };
 
-        Ok(TrainDate::new(year, month, day))
+        date.map(TrainDate)
+            .ok_or_else(|| Error::ParseDateError(s.to_string()))
     }
 }
 
@@ -174,16 +470,128 @@ impl TrainTime {
     /// ```rust
     /// # use rzd_trains::TrainTime;
     /// #
-    /// let t = TrainTime::new(5, 7);
+    /// let t = TrainTime::try_new(5, 7).unwrap();
     ///
     /// assert_eq!(format!("{}", t), "05:07");
+    /// assert!(TrainTime::try_new(24, 0).is_err());
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the hours or the minutes are out of range.
+    pub fn try_new(hours: u32, minutes: u32) -> Result<Self> {
+        match NaiveTime::from_hms_opt(hours, minutes, 0) {
+            Some(t) => Ok(TrainTime(t)),
+            None => Err(Error::ParseTimeError(format!(
+                "{:02}:{:02}",
+                hours, minutes
+            ))),
+        }
+    }
+
+    /// Creates `TrainTime` from the number of hours, minutes and seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::TrainTime;
+    /// #
+    /// let t = TrainTime::try_with_seconds(5, 7, 30).unwrap();
+    ///
+    /// assert_eq!(format!("{}", t), "05:07");
+    /// assert_eq!(format!("{}", t.with_seconds()), "05:07:30");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the hours, the minutes or the seconds are out of range.
+    pub fn try_with_seconds(hours: u32, minutes: u32, seconds: u32) -> Result<Self> {
+        match NaiveTime::from_hms_opt(hours, minutes, seconds) {
+            Some(t) => Ok(TrainTime(t)),
+            None => Err(Error::ParseTimeError(format!(
+                "{:02}:{:02}:{:02}",
+                hours, minutes, seconds
+            ))),
+        }
+    }
+
+    /// Returns the seconds of the time, zero if the source had no seconds.
+    #[inline]
+    pub fn seconds(&self) -> u32 {
+        self.0.second()
+    }
+
+    /// Returns the time written in the format, the `Display` one is `TimeFormat::Rzd`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::{TimeFormat, TrainTime};
+    /// #
+    /// let t = TrainTime::try_with_seconds(5, 7, 30).unwrap();
+    ///
+    /// assert_eq!(t.format(TimeFormat::Rzd), "05:07");
+    /// assert_eq!(t.format(TimeFormat::Short), "5:07");
+    /// ```
+    pub fn format(&self, format: TimeFormat) -> String {
+        match format {
+            TimeFormat::Rzd => self.to_string(),
+            TimeFormat::WithSeconds => self.with_seconds().to_string(),
+            TimeFormat::Short => format!("{}:{:02}", self.0.hour(), self.0.minute()),
+        }
+    }
+
+    /// Returns the time displayed as "HH:MM:SS".
+    #[inline]
+    pub fn with_seconds(&self) -> TrainTimeWithSeconds {
+        TrainTimeWithSeconds(self.0)
+    }
+
+    /// Creates `TrainTime` from the number of hours and minutes,
+    /// 00:00 is taken if they are out of range.
+    #[deprecated(note = "the invalid time turns into 00:00, use `try_new` instead")]
     pub fn new(hours: u32, minutes: u32) -> Self {
-        let time = match NaiveTime::from_hms_opt(hours, minutes, 0) {
-            Some(d) => d,
-            None => NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        TrainTime::lossy(hours, minutes)
+    }
+
+    // Takes 00:00 instead of the invalid time.
+    fn lossy(hours: u32, minutes: u32) -> Self {
+        TrainTime(NaiveTime::from_hms_opt(hours, minutes, 0).unwrap_or(NaiveTime::MIN))
+    }
+
+    /// Returns the part of the day the time falls into.
+    pub fn day_part(&self) -> DayPart {
+        match self.0.hour() {
+            0..=5 => DayPart::Night,
+            6..=11 => DayPart::Morning,
+            12..=17 => DayPart::Afternoon,
+            _ => DayPart::Evening,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+/// Part of the day, the parts go in the order of the day starting from midnight.
+pub enum DayPart {
+    /// From 00:00 to 05:59.
+    Night,
+    /// From 06:00 to 11:59.
+    Morning,
+    /// From 12:00 to 17:59.
+    Afternoon,
+    /// From 18:00 to 23:59.
+    Evening,
+}
+
+impl Display for DayPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DayPart::Night => "ночь",
+            DayPart::Morning => "утро",
+            DayPart::Afternoon => "день",
+            DayPart::Evening => "вечер",
         };
-        TrainTime(time)
+        write!(f, "{}", name)
     }
 }
 
@@ -193,9 +601,33 @@ impl Display for TrainTime {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The time of the train displayed with the seconds, see `TrainTime::with_seconds`.
+pub struct TrainTimeWithSeconds(NaiveTime);
+
+impl Display for TrainTimeWithSeconds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M:%S"))
+    }
+}
+
 impl From<NaiveTime> for TrainTime {
     fn from(t: NaiveTime) -> Self {
-        TrainTime::new(t.hour(), t.minute())
+        TrainTime(t.with_nanosecond(0).unwrap_or(t))
+    }
+}
+
+impl From<NaiveDateTime> for TrainTime {
+    fn from(dt: NaiveDateTime) -> Self {
+        TrainTime::from(dt.time())
+    }
+}
+
+impl From<(u32, u32)> for TrainTime {
+    /// Takes the number of hours and minutes, 00:00 is taken if they are out of range,
+    /// see `TrainTime::try_new`.
+    fn from((hours, minutes): (u32, u32)) -> Self {
+        TrainTime::lossy(hours, minutes)
     }
 }
 
@@ -212,13 +644,37 @@ impl FromStr for TrainTime {
     fn from_str(s: &str) -> Result<Self> {
         let v: Vec<u32> = s.split(":").filter_map(|s| s.parse().ok()).collect();
 
-        let (h, m) = match &v[..] {
-            &[h, m, _s] => (h, m),
-            &[h, m] => (h, m),
+        let (h, m, sec) = match &v[..] {
+            &[h, m, sec] => (h, m, sec),
+            &[h, m] => (h, m, 0),
             _ => return Err(Error::ParseTimeError(s.to_string())),
         };
 
-        Ok(TrainTime::new(h, m))
+        TrainTime::try_with_seconds(h, m, sec).map_err(|_| Error::ParseTimeError(s.to_string()))
+    }
+}
+
+// Takes the value omitted by the server, empty or blank, as missing.
+pub(crate) fn non_empty(value: &str) -> Option<&str> {
+    match value.trim().is_empty() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+// Parses the time of the reply, the malformed or invalid one is reported to the warnings
+// with the description of the value, the missing one is skipped.
+fn parse_reply_time<T: FromStr>(s: &str, what: &str, warnings: &mut Vec<String>) -> Option<T> {
+    if s.trim().is_empty() {
+        return None;
+    }
+
+    match s.parse() {
+        Ok(t) => Some(t),
+        Err(_) => {
+            warnings.push(format!("{}: некорректное время \"{}\"", what, s));
+            None
+        }
     }
 }
 
@@ -233,6 +689,331 @@ macro_rules! parse_train_time {
     };
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A duration of the trip, unlike `TrainTime` it may be longer than a day.
+pub struct TripDuration(u32);
+
+impl TripDuration {
+    /// Creates `TripDuration` from the number of hours and minutes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::TripDuration;
+    /// #
+    /// let d = TripDuration::new(33, 5).unwrap();
+    ///
+    /// assert_eq!(format!("{}", d), "33:05");
+    /// assert_eq!(d.as_minutes(), 1985);
+    /// assert!(TripDuration::new(u32::MAX, 0).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the number of minutes overflows.
+    pub fn new(hours: u32, minutes: u32) -> Result<Self> {
+        match hours.checked_mul(60).and_then(|h| h.checked_add(minutes)) {
+            Some(d) => Ok(TripDuration(d)),
+            None => Err(Error::ParseTimeError(format!("{}:{:02}", hours, minutes))),
+        }
+    }
+
+    /// Creates `TripDuration` from the number of minutes.
+    #[inline]
+    pub fn from_minutes(minutes: u32) -> Self {
+        TripDuration(minutes)
+    }
+
+    /// Returns the whole duration in minutes.
+    #[inline]
+    pub fn as_minutes(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the number of whole hours.
+    #[inline]
+    pub fn hours(&self) -> u32 {
+        self.0 / 60
+    }
+
+    /// Returns the minutes above the whole hours.
+    #[inline]
+    pub fn minutes(&self) -> u32 {
+        self.0 % 60
+    }
+}
+
+impl Display for TripDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hours(), self.minutes())
+    }
+}
+
+impl FromStr for TripDuration {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let error = || Error::ParseTimeError(s.to_string());
+        let v = s
+            .split(':')
+            .map(|s| s.parse::<u32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| error())?;
+
+        match &v[..] {
+            &[h, m] if m < 60 => TripDuration::new(h, m).map_err(|_| error()),
+            _ => Err(error()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A full timestamp of the reply or of the gateway, e.g. "01.04.2022 13:58:10.003",
+/// the seconds and the milliseconds may be missing in the source.
+///
+/// # Examples
+///
+/// ```rust
+/// # use rzd_trains::ServerTimestamp;
+/// #
+/// let t: ServerTimestamp = "01.04.2022 13:58:10.003".parse().unwrap();
+///
+/// assert_eq!(format!("{}", t.date()), "01.04.2022");
+/// assert_eq!(format!("{}", t.time().with_seconds()), "13:58:10");
+/// assert_eq!(t.millis(), 3);
+/// assert!("20.03.2022 00:00".parse::<ServerTimestamp>().is_ok());
+/// ```
+pub struct ServerTimestamp(NaiveDateTime);
+
+impl ServerTimestamp {
+    /// Returns the date of the timestamp.
+    #[inline]
+    pub fn date(&self) -> TrainDate {
+        TrainDate(self.0.date())
+    }
+
+    /// Returns the time of the timestamp, with the seconds.
+    #[inline]
+    pub fn time(&self) -> TrainTime {
+        TrainTime::from(self.0.time())
+    }
+
+    /// Returns the milliseconds of the timestamp.
+    #[inline]
+    pub fn millis(&self) -> u32 {
+        self.0.and_utc().timestamp_subsec_millis()
+    }
+}
+
+impl Display for ServerTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.format("%d.%m.%Y %H:%M:%S%.3f"))
+    }
+}
+
+impl FromStr for ServerTimestamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        const FORMATS: &[&str] = &["%d.%m.%Y %H:%M:%S%.f", "%d.%m.%Y %H:%M"];
+
+        FORMATS
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(s.trim(), f).ok())
+            .map(ServerTimestamp)
+            .ok_or_else(|| Error::ParseDateError(s.to_string()))
+    }
+}
+
+impl From<NaiveDateTime> for ServerTimestamp {
+    fn from(dt: NaiveDateTime) -> Self {
+        ServerTimestamp(dt)
+    }
+}
+
+impl From<ServerTimestamp> for NaiveDateTime {
+    fn from(t: ServerTimestamp) -> Self {
+        t.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Kind of the seats or berths.
+pub enum SeatKind {
+    /// Lower berth in the compartment.
+    Lower,
+    /// Upper berth in the compartment.
+    Upper,
+    /// Lower side berth.
+    LowerSide,
+    /// Upper side berth.
+    UpperSide,
+    /// Whole compartment or a compartment car.
+    Compartment,
+    /// Open-plan sleeping car, "плацкарт".
+    OpenBerth,
+    /// Seat in a sitting car.
+    Seat,
+    /// Sleeping car with 2-berth compartments, "СВ" or "люкс".
+    Luxury,
+    /// Unknown kind with the name given by the server.
+    Other(String),
+}
+
+impl SeatKind {
+    // Takes the code given by the server, e.g. "dn", or the name, e.g. "Нижнее",
+    // if the code is unknown.
+    fn from_code_or_name(code: &str, name: &str) -> Self {
+        match SeatKind::from(code) {
+            SeatKind::Other(_) => SeatKind::from(name),
+            kind => kind,
+        }
+    }
+}
+
+impl Display for SeatKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SeatKind::Lower => "нижнее",
+            SeatKind::Upper => "верхнее",
+            SeatKind::LowerSide => "нижнее боковое",
+            SeatKind::UpperSide => "верхнее боковое",
+            SeatKind::Compartment => "купе",
+            SeatKind::OpenBerth => "плацкарт",
+            SeatKind::Seat => "сидячее",
+            SeatKind::Luxury => "СВ",
+            SeatKind::Other(s) => s,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<&str> for SeatKind {
+    // Takes the code or the name used by RZD, e.g. "dn", "Нижнее" or "Плац",
+    // the English names are taken too, e.g. "Lower" or "Coupe".
+    fn from(s: &str) -> Self {
+        match s.trim().to_uppercase().as_str() {
+            "DN" | "НИЖНЕЕ" | "LOWER" => SeatKind::Lower,
+            "UP" | "ВЕРХНЕЕ" | "UPPER" => SeatKind::Upper,
+            "LSD" | "НИЖНЕЕ БОКОВОЕ" | "LOWER SIDE" => SeatKind::LowerSide,
+            "USD" | "ВЕРХНЕЕ БОКОВОЕ" | "UPPER SIDE" => SeatKind::UpperSide,
+            "KUPE" | "КУПЕ" | "COUPE" | "COMPARTMENT" => SeatKind::Compartment,
+            "ПЛАЦ" | "ПЛАЦКАРТНЫЙ" | "PLATS" | "PLATSKART" | "RESERVED SEAT" => {
+                SeatKind::OpenBerth
+            }
+            "СИД" | "СИДЯЧИЙ" | "SEAT" | "SITTING" => SeatKind::Seat,
+            "ЛЮКС" | "СВ" | "LUX" | "LUXURY" | "SV" => SeatKind::Luxury,
+            _ => SeatKind::Other(s.trim().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+/// A code of the class of service of the train car, e.g. "2Э" for the improved compartment.
+/// The code is stored in upper case and Latin letters looking like Cyrillic ones are replaced.
+pub struct ServiceClass(String);
+
+impl ServiceClass {
+    /// Creates a code from the string, e.g. "2э" or "2T".
+    pub fn new(code: &str) -> Self {
+        let code = code
+            .trim()
+            .to_uppercase()
+            .chars()
+            .map(to_cyrillic)
+            .collect();
+        ServiceClass(code)
+    }
+
+    /// Returns the code.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for ServiceClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for ServiceClass {
+    fn from(code: &str) -> Self {
+        ServiceClass::new(code)
+    }
+}
+
+// Replaces the upper case Latin letter looking like a Cyrillic one.
+fn to_cyrillic(c: char) -> char {
+    match c {
+        'A' => 'А',
+        'B' => 'В',
+        'E' => 'Е',
+        'K' => 'К',
+        'M' => 'М',
+        'H' => 'Н',
+        'O' => 'О',
+        'P' => 'Р',
+        'C' => 'С',
+        'T' => 'Т',
+        'X' => 'Х',
+        'Y' => 'У',
+        c => c,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// Number of the train, e.g. "119А", or the numbers of both directions, e.g. "105Ж/106Я".
+pub struct TrainNumber(Vec<String>);
+
+impl TrainNumber {
+    /// Parses the number, the letters are changed to upper case Cyrillic ones.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the number is empty.
+    pub fn parse(s: &str) -> Result<Self> {
+        let numbers: Vec<String> = s
+            .split('/')
+            .map(|n| n.trim().to_uppercase().chars().map(to_cyrillic).collect())
+            .filter(|n: &String| !n.is_empty())
+            .collect();
+
+        match numbers.is_empty() {
+            true => Err(Error::EmptyTrainNumber),
+            false => Ok(TrainNumber(numbers)),
+        }
+    }
+
+    /// Returns the numbers, two or more for a paired number.
+    /// The parity of the number of the direction differs between the routes,
+    /// so the caller chooses the one to search by, or tries each of them.
+    #[inline]
+    pub fn variants(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Returns true if the numbers of both directions are given.
+    pub fn is_paired(&self) -> bool {
+        self.0.len() > 1
+    }
+}
+
+impl Display for TrainNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join("/"))
+    }
+}
+
+impl FromStr for TrainNumber {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        TrainNumber::parse(s)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 /// A digital designation of the station used by RZD.
 pub struct RzdStationCode(u32);
@@ -248,6 +1029,131 @@ impl RzdStationCode {
     pub fn to_uint(&self) -> u32 {
         self.0
     }
+
+    /// Returns the kind of the code by the number of its digits.
+    /// The ESR codes may have leading zeros, so any shorter non-zero code is taken as ESR.
+    pub fn kind(&self) -> StationCodeKind {
+        match self.0 {
+            1_000_000..=9_999_999 => StationCodeKind::Express,
+            1..=999_999 => StationCodeKind::Esr,
+            _ => StationCodeKind::Unknown,
+        }
+    }
+
+    /// Converts the six-digit ESR code into the seven-digit Express-3 one by the directory.
+    /// The Express-3 code is returned as is.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the code is not found in the directory or has unknown kind.
+    pub fn to_express(&self, directory: &StationCodeDirectory) -> Result<RzdStationCode> {
+        match self.kind() {
+            StationCodeKind::Express => Ok(*self),
+            StationCodeKind::Esr => directory
+                .express(self.0)
+                .ok_or_else(|| Error::ParseStationCodeError(self.to_string())),
+            StationCodeKind::Unknown => Err(Error::ParseStationCodeError(self.to_string())),
+        }
+    }
+
+    // Checks that the stations of the route are set and differ.
+    fn check_route(from: RzdStationCode, to: RzdStationCode) -> Result<()> {
+        match from.0 == 0 || to.0 == 0 || from == to {
+            true => Err(Error::InvalidRoute { from, to }),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns true if the code seems to denote a whole city, not a single station,
+    /// e.g. 2000000 for Moscow with all its terminals.
+    /// The Express-3 codes of the cities end with three zeros.
+    pub fn is_city_aggregate(&self) -> bool {
+        self.kind() == StationCodeKind::Express && self.0.is_multiple_of(1000)
+    }
+
+    /// Returns the codes of the terminals of the city known by the directory,
+    /// or the code itself if it is a single station or the terminals are unknown.
+    pub fn expand(&self, directory: &StationCodeDirectory) -> Vec<RzdStationCode> {
+        match directory.terminals(*self) {
+            [] => vec![*self],
+            t => t.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Kind of the station code.
+pub enum StationCodeKind {
+    /// Seven-digit code of the Express-3 system used by the server, e.g. 2000000.
+    Express,
+    /// Six-digit code of the unified network marking (ESR), e.g. 060073,
+    /// the number has no leading zeros, i.e. 60073.
+    /// The server finds no trains by it.
+    Esr,
+    /// The code of unknown kind.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Directory of the ESR and the Express-3 codes of the stations.
+/// There is no deterministic mapping between them, so the pairs are loaded by the user.
+pub struct StationCodeDirectory {
+    by_esr: HashMap<u32, u32>,
+    by_express: HashMap<u32, u32>,
+    terminals: HashMap<u32, Vec<RzdStationCode>>,
+}
+
+impl StationCodeDirectory {
+    pub fn new() -> Self {
+        StationCodeDirectory::default()
+    }
+
+    /// Adds the pair of the ESR and the Express-3 codes of the station.
+    pub fn insert(&mut self, esr: u32, express: u32) {
+        self.by_esr.insert(esr, express);
+        self.by_express.insert(express, esr);
+    }
+
+    /// Returns the Express-3 code of the station with the ESR code.
+    pub fn express(&self, esr: u32) -> Option<RzdStationCode> {
+        self.by_esr.get(&esr).map(|c| RzdStationCode(*c))
+    }
+
+    /// Returns the ESR code of the station with the Express-3 code.
+    pub fn esr(&self, code: RzdStationCode) -> Option<u32> {
+        self.by_express.get(&code.0).copied()
+    }
+
+    /// Adds the terminal to the city, e.g. 2006004 to 2000000 for Moscow.
+    pub fn insert_terminal(&mut self, city: u32, terminal: u32) {
+        let terminals = self.terminals.entry(city).or_default();
+        if !terminals.contains(&RzdStationCode(terminal)) {
+            terminals.push(RzdStationCode(terminal));
+        }
+    }
+
+    /// Returns the terminals of the city, the list is empty if the city is unknown.
+    pub fn terminals(&self, city: RzdStationCode) -> &[RzdStationCode] {
+        self.terminals.get(&city.0).map_or(&[], |t| t.as_slice())
+    }
+
+    /// Returns the city the terminal belongs to.
+    pub fn city(&self, terminal: RzdStationCode) -> Option<RzdStationCode> {
+        self.terminals
+            .iter()
+            .find(|(_, t)| t.contains(&terminal))
+            .map(|(c, _)| RzdStationCode(*c))
+    }
+}
+
+impl FromIterator<(u32, u32)> for StationCodeDirectory {
+    fn from_iter<I: IntoIterator<Item = (u32, u32)>>(pairs: I) -> Self {
+        let mut directory = StationCodeDirectory::new();
+        for (esr, express) in pairs {
+            directory.insert(esr, express);
+        }
+        directory
+    }
 }
 
 impl Default for RzdStationCode {
@@ -256,6 +1162,12 @@ impl Default for RzdStationCode {
     }
 }
 
+impl From<u32> for RzdStationCode {
+    fn from(code: u32) -> Self {
+        RzdStationCode::new(code)
+    }
+}
+
 impl Display for RzdStationCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -273,6 +1185,89 @@ impl FromStr for RzdStationCode {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+/// Name of the station suitable to show, e.g. `Москва` with the terminal `Ярославский вокзал`
+/// instead of `МОСКВА (ЯРОСЛАВСКИЙ ВОКЗАЛ)` as the server returns it.
+pub struct StationName {
+    name: String,
+    terminal: Option<String>,
+}
+
+impl StationName {
+    /// Takes the name as the server returns it, trims extra spaces, changes the letters
+    /// of the name to the title case and splits the terminal in parentheses off.
+    pub fn parse(s: &str) -> Self {
+        let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let (name, terminal) = match (s.find('('), s.strip_suffix(')')) {
+            (Some(i), Some(rest)) if i > 0 => (&s[..i], Some(&rest[i + 1..])),
+            _ => (s.as_str(), None),
+        };
+
+        StationName {
+            name: title_case(name.trim()),
+            terminal: terminal
+                .map(|t| capitalize(&t.trim().to_lowercase()))
+                .filter(|t| !t.is_empty()),
+        }
+    }
+
+    /// Returns the name of the station without the terminal.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the terminal of the station, e.g. a railway terminal of the city.
+    #[inline]
+    pub fn terminal(&self) -> Option<&str> {
+        self.terminal.as_deref()
+    }
+}
+
+impl Display for StationName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.terminal {
+            Some(t) => write!(f, "{} ({})", self.name, t),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// Changes the words to the title case, the prepositions inside the compound names
+// are kept lowercase, e.g. `Ростов-на-Дону`.
+fn title_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, word) in s.split_inclusive([' ', '-', '.']).enumerate() {
+        let lower = word.to_lowercase();
+        let bare = lower.trim_end_matches([' ', '-', '.']);
+        if i > 0 && matches!(bare, "на" | "в" | "и" | "у" | "под") && word.ends_with('-') {
+            out.push_str(&lower);
+            continue;
+        }
+
+        out.push_str(&capitalize(&lower));
+    }
+    out
+}
+
+// Chooses the form of the Russian noun for the number, e.g. `1 вагон`, `3 вагона`, `5 вагонов`.
+fn plural<'a>(n: u32, one: &'a str, few: &'a str, many: &'a str) -> &'a str {
+    match (n % 10, n % 100) {
+        (_, 11..=14) => many,
+        (1, _) => one,
+        (2..=4, _) => few,
+        _ => many,
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! parse_station_code {
@@ -319,7 +1314,42 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Advisory messages returned by the server along with the found data,
+/// e.g. when the sale opens.
+pub struct Warnings(Vec<String>);
+
+impl Warnings {
+    /// Creates a new list of the messages from `Vec`.
+    pub fn new(v: Vec<String>) -> Self {
+        Warnings(v)
+    }
+
+    /// Returns true if there are no messages.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Performs the conversion into `Vec`.
+    pub fn to_vec(self) -> Vec<String> {
+        self.0
+    }
+}
+
+impl AsRef<[String]> for Warnings {
+    #[inline]
+    fn as_ref(&self) -> &[String] {
+        self.0.as_slice()
+    }
+}
+
+impl Display for Warnings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// List of the request results.
 pub struct ResultList<T>(Vec<T>)
 where
@@ -358,6 +1388,66 @@ where
         }
     }
 
+    /// Performs the conversion into a YAML document with the sequence of the items.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if an item couldn't be serialized.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self).map_err(|e| Error::SerializeError(format!("{}", e)))
+    }
+
+    /// Performs the conversion into a TOML document, the items are written as
+    /// the array of tables `[[items]]` since TOML has no top-level arrays.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if an item couldn't be serialized,
+    /// e.g. if it has an array with missing values.
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String> {
+        #[derive(Serialize)]
+        struct Document<'a, T: Serialize> {
+            items: &'a [T],
+        }
+
+        toml::to_string(&Document { items: &self.0 })
+            .map_err(|e| Error::SerializeError(format!("{}", e)))
+    }
+
+    /// Writes the items as JSON Lines, one JSON object per line,
+    /// every item is serialized straight into the writer.
+    /// Wrap the file into `BufWriter`, the writer is not buffered here.
+    ///
+    /// ```rust,no_run
+    /// use rzd_trains::{RouteList, RzdClient, TrainScheduleSearch, TrainType};
+    /// use std::fs::OpenOptions;
+    /// use std::io::BufWriter;
+    ///
+    /// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+    /// let routes = RzdClient::<RouteList>::get(&q).unwrap().unwrap();
+    ///
+    /// let file = OpenOptions::new().create(true).append(true).open("routes.ndjson").unwrap();
+    /// routes.write_ndjson(BufWriter::new(file)).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The method fails if an item couldn't be serialized or written,
+    /// the items before it are written already.
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> Result<()> {
+        for item in self.0.iter() {
+            serde_json::to_writer(&mut writer, item).map_err(|e| match e.is_io() {
+                true => Error::from(std::io::Error::from(e)),
+                false => Error::SerializeError(format!("{}", e)),
+            })?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
     /// Creates a non-consuming iterator.
     pub fn iter(&self) -> ResultListIter<T> {
         ResultListIter::<T> {
@@ -372,20 +1462,70 @@ where
             item: self.0.into_iter(),
         }
     }
+
+    /// Returns a wrapper displaying not more than `limit` items
+    /// and the number of the rest ones, e.g. to log a huge list.
+    pub fn display_limit(&self, limit: usize) -> ResultListDisplay<'_, T> {
+        ResultListDisplay { list: self, limit }
+    }
 }
 
-impl<T> Display for ResultList<T>
+/// The list displayed with the limited number of items, see `ResultList::display_limit`.
+pub struct ResultListDisplay<'a, T: Debug + Display + Serialize> {
+    list: &'a ResultList<T>,
+    limit: usize,
+}
+
+impl<T> Display for ResultListDisplay<'_, T>
 where
     T: Debug + Display + Serialize,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for s in &self.0 {
-            write!(f, "{}\n", s)?;
+        let items = &self.list.0;
+        write_items(f, &items[..items.len().min(self.limit)])?;
+
+        if items.len() > self.limit {
+            let rest = items.len() - self.limit;
+            match f.alternate() {
+                true => write!(f, "\n... и ещё {}", rest),
+                false => writeln!(f, "... и ещё {}", rest),
+            }?;
         }
         Ok(())
     }
 }
 
+impl<T> Display for ResultList<T>
+where
+    T: Debug + Display + Serialize,
+{
+    /// Writes every item followed by a line break.
+    /// The alternate form `{:#}` writes every item on a single line
+    /// with no line break after the last one.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_items(f, &self.0)
+    }
+}
+
+// Writes the items in the default or the compact alternate form.
+fn write_items<T: Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    if !f.alternate() {
+        for s in items {
+            writeln!(f, "{}", s)?;
+        }
+        return Ok(());
+    }
+
+    for (i, s) in items.iter().enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        let s = s.to_string();
+        write!(f, "{}", s.split_whitespace().collect::<Vec<_>>().join(" "))?;
+    }
+    Ok(())
+}
+
 impl<T> Default for ResultList<T>
 where
     T: Debug + Display + Serialize,
@@ -433,7 +1573,46 @@ impl<T: Debug + Display + Serialize> Iterator for ResultListIntoIter<T> {
 #[cfg(test)]
 mod tests {
     use super::{parse_station_code, parse_train_date, parse_train_time};
-    use super::{RzdStationCode, TrainDate, TrainTime};
+    use super::{
+        Carrier, Clock, DateFormat, ResultList, RzdStationCode, SeatKind, ServerTimestamp,
+        ServiceClass, StationCodeDirectory, StationCodeKind, StationName, TimeFormat,
+        TrainCategory, TrainDate, TrainNumber, TrainTime, TripDuration,
+    };
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+    struct FixedClock;
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            Utc.with_ymd_and_hms(2022, 4, 1, 12, 0, 0).unwrap()
+        }
+    }
+
+    #[test]
+    fn plural_test() {
+        let cars = |n| super::plural(n, "вагон", "вагона", "вагонов");
+        assert_eq!(cars(1), "вагон");
+        assert_eq!(cars(3), "вагона");
+        assert_eq!(cars(5), "вагонов");
+        assert_eq!(cars(12), "вагонов");
+        assert_eq!(cars(21), "вагон");
+        assert_eq!(cars(104), "вагона");
+    }
+
+    #[test]
+    fn train_category_test() {
+        assert_eq!(TrainCategory::from("СК ФИРМ"), TrainCategory::Firm);
+        assert_eq!(TrainCategory::from("СК"), TrainCategory::Fast);
+        assert_eq!(
+            TrainCategory::from("Пассажирский"),
+            TrainCategory::Passenger
+        );
+        assert_eq!(TrainCategory::from("Экспресс"), TrainCategory::Express);
+        assert_eq!(TrainCategory::from(""), TrainCategory::Unknown);
+
+        assert_eq!(TrainCategory::from("SK FIRM"), TrainCategory::Firm);
+        assert_eq!(TrainCategory::from("Passenger"), TrainCategory::Passenger);
+    }
 
     #[test]
     fn train_date_test() {
@@ -445,7 +1624,286 @@ mod tests {
 
     #[test]
     fn train_time_test() {
-        assert_eq!(parse_train_time!("23:05"), Some(TrainTime::new(23, 5)));
+        assert_eq!(
+            parse_train_time!("23:05"),
+            Some(TrainTime::try_new(23, 5).unwrap())
+        );
+        assert_eq!(parse_train_time!("24:05"), None);
+        assert!(matches!(
+            TrainTime::try_new(23, 60),
+            Err(crate::Error::ParseTimeError(t)) if t == "23:60"
+        ));
+        assert_eq!(TrainTime::from((25, 0)), TrainTime::try_new(0, 0).unwrap());
+
+        let t = parse_train_time!("23:05:42").unwrap();
+        assert_eq!(t.seconds(), 42);
+        assert_eq!(t.to_string(), "23:05");
+        assert_eq!(t.with_seconds().to_string(), "23:05:42");
+        assert_eq!(t, TrainTime::try_with_seconds(23, 5, 42).unwrap());
+        assert_eq!(
+            TrainTime::try_new(23, 5)
+                .unwrap()
+                .with_seconds()
+                .to_string(),
+            "23:05:00"
+        );
+        assert_eq!(parse_train_time!("23:05:60"), None);
+
+        let mut warnings = vec![];
+        let time: Option<TrainTime> = super::parse_reply_time("25:70", "поезд 119А", &mut warnings);
+        assert_eq!(time, None);
+        assert_eq!(warnings, ["поезд 119А: некорректное время \"25:70\""]);
+        assert_eq!(
+            super::parse_reply_time::<TrainTime>(" ", "", &mut warnings),
+            None
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn output_format_test() {
+        let d = TrainDate::new(2022, 12, 31);
+        assert_eq!(d.format(DateFormat::default()), "31.12.2022");
+        assert_eq!(d.format(DateFormat::Iso), "2022-12-31");
+        assert_eq!(d.format(DateFormat::Long), "31 декабря 2022");
+        assert_eq!(
+            TrainDate::new(2022, 1, 5).format(DateFormat::DayMonth),
+            "5 января"
+        );
+
+        let t = TrainTime::try_with_seconds(23, 5, 9).unwrap();
+        assert_eq!(t.format(TimeFormat::default()), "23:05");
+        assert_eq!(t.format(TimeFormat::WithSeconds), "23:05:09");
+        assert_eq!(t.format(TimeFormat::Short), "23:05");
+        assert_eq!(
+            TrainTime::try_new(0, 0).unwrap().format(TimeFormat::Short),
+            "0:00"
+        );
+    }
+
+    #[test]
+    fn server_timestamp_test() {
+        let t: ServerTimestamp = "01.04.2022 13:58:10.003".parse().unwrap();
+        assert_eq!(t.date(), TrainDate::new(2022, 4, 1));
+        assert_eq!(t.time(), TrainTime::try_with_seconds(13, 58, 10).unwrap());
+        assert_eq!(t.millis(), 3);
+        assert_eq!(t.to_string(), "01.04.2022 13:58:10.003");
+
+        let t: ServerTimestamp = "20.03.2022 19:04:56".parse().unwrap();
+        assert_eq!((t.time().seconds(), t.millis()), (56, 0));
+        let t: ServerTimestamp = "20.03.2022 00:00".parse().unwrap();
+        assert_eq!(t.to_string(), "20.03.2022 00:00:00.000");
+
+        assert!("32.03.2022 00:00".parse::<ServerTimestamp>().is_err());
+        assert!("20.03.2022".parse::<ServerTimestamp>().is_err());
+    }
+
+    #[test]
+    fn trip_duration_test() {
+        let d = "33:45".parse::<TripDuration>().unwrap();
+        assert_eq!((d.hours(), d.minutes()), (33, 45));
+        assert_eq!(d.to_string(), "33:45");
+        assert!("05:75".parse::<TripDuration>().is_err());
+        assert!("".parse::<TripDuration>().is_err());
+        assert!("33:x:45".parse::<TripDuration>().is_err());
+        assert!("33::45".parse::<TripDuration>().is_err());
+        assert!("33:45:00".parse::<TripDuration>().is_err());
+        assert!("71582789:00".parse::<TripDuration>().is_err());
+        assert_eq!(
+            TripDuration::new(71582788, 15).unwrap().as_minutes(),
+            u32::MAX
+        );
+        assert!(TripDuration::new(71582788, 16).is_err());
+    }
+
+    #[test]
+    fn seat_kind_test() {
+        assert_eq!(SeatKind::from("dn"), SeatKind::Lower);
+        assert_eq!(SeatKind::from("Верхнее"), SeatKind::Upper);
+        assert_eq!(SeatKind::from("Плац"), SeatKind::OpenBerth);
+        assert_eq!(SeatKind::from("Coupe"), SeatKind::Compartment);
+        assert_eq!(SeatKind::from("lower side"), SeatKind::LowerSide);
+        assert_eq!(
+            SeatKind::from(" Базовый "),
+            SeatKind::Other("Базовый".to_string())
+        );
+        assert_eq!(
+            SeatKind::from_code_or_name("Сид", "Базовый"),
+            SeatKind::Seat
+        );
+        assert_eq!(
+            SeatKind::from_code_or_name("", "Купе"),
+            SeatKind::Compartment
+        );
+        assert_eq!(SeatKind::Other("Мягкий".to_string()).to_string(), "Мягкий");
+    }
+
+    #[test]
+    fn service_class_test() {
+        assert_eq!(ServiceClass::new(" 2э").as_str(), "2Э");
+        assert_eq!(ServiceClass::new("2T"), ServiceClass::new("2Т"));
+        assert_eq!(ServiceClass::from("1b").to_string(), "1В");
+        assert_ne!(ServiceClass::new("2К"), ServiceClass::new("2Э"));
+    }
+
+    #[test]
+    fn conversions_test() {
+        let dt = NaiveDate::from_ymd_opt(2022, 4, 1)
+            .unwrap()
+            .and_hms_opt(23, 5, 0)
+            .unwrap();
+
+        assert_eq!(TrainDate::from(dt), TrainDate::new(2022, 4, 1));
+        assert_eq!(TrainDate::from((2022, 4, 1)), TrainDate::new(2022, 4, 1));
+        assert_eq!(TrainTime::from(dt), TrainTime::try_new(23, 5).unwrap());
+        assert_eq!(TrainTime::from((23, 5)), TrainTime::try_new(23, 5).unwrap());
+        assert_eq!(RzdStationCode::from(2000000), RzdStationCode::new(2000000));
+    }
+
+    #[test]
+    fn train_date_fallback_test() {
+        assert_eq!(
+            TrainDate::new_with_clock(2022, 2, 30, &FixedClock),
+            TrainDate::new(2022, 4, 1)
+        );
+        assert_eq!(
+            TrainDate::new_with_clock(2022, 5, 9, &FixedClock),
+            TrainDate::new(2022, 5, 9)
+        );
+    }
+
+    #[test]
+    fn train_number_test() {
+        let number = TrainNumber::parse(" 105ж / 106Я ").unwrap();
+        assert!(number.is_paired());
+        assert_eq!(number.to_string(), "105Ж/106Я");
+        assert_eq!(number.variants(), &["105Ж".to_string(), "106Я".to_string()]);
+
+        let number: TrainNumber = "119A".parse().unwrap();
+        assert!(!number.is_paired());
+        assert_eq!(number.variants(), &["119А".to_string()]);
+
+        assert!(TrainNumber::parse(" / ").is_err());
+    }
+
+    #[test]
+    fn result_list_display_test() {
+        let list = ResultList::new(vec![
+            TrainTime::try_new(5, 7).unwrap(),
+            TrainTime::try_new(6, 8).unwrap(),
+            TrainTime::try_new(7, 9).unwrap(),
+        ]);
+        assert_eq!(list.to_string(), "05:07\n06:08\n07:09\n");
+        assert_eq!(format!("{:#}", list), "05:07\n06:08\n07:09");
+        assert_eq!(
+            list.display_limit(2).to_string(),
+            "05:07\n06:08\n... и ещё 1\n"
+        );
+        assert_eq!(
+            format!("{:#}", list.display_limit(5)),
+            "05:07\n06:08\n07:09"
+        );
+
+        let list = ResultList::new(vec![Carrier::Other("a\n b".to_string())]);
+        assert_eq!(format!("{:#}", list), "a b");
+    }
+
+    #[test]
+    fn write_ndjson_test() {
+        let answer = crate::fixtures::load_fixture("train_schedule").unwrap();
+        let routes = crate::parse_schedule_reply(&answer).unwrap().unwrap();
+        let trains = routes.as_ref()[0].trains();
+
+        let mut out = vec![];
+        trains.write_ndjson(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(out.ends_with("}\n"));
+        let train: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(train["train_number"], "713В");
+
+        let mut out: &mut [u8] = &mut [0; 10];
+        assert!(matches!(
+            trains.write_ndjson(&mut out),
+            Err(crate::Error::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn carrier_test() {
+        assert_eq!(Carrier::from("ФПК"), Carrier::Fpk);
+        assert_eq!(Carrier::from(" сзппк "), Carrier::Szppk);
+        assert_eq!(Carrier::from(""), Carrier::Unknown);
+        assert_eq!(
+            Carrier::from("Гранд Сервис Экспресс").to_string(),
+            "Гранд Сервис Экспресс"
+        );
+        assert_eq!(serde_json::to_string(&Carrier::Doss).unwrap(), r#""ДОСС""#);
+    }
+
+    #[test]
+    fn station_name_test() {
+        let name = StationName::parse(" МОСКВА  (ЯРОСЛАВСКИЙ ВОКЗАЛ) ");
+        assert_eq!(name.name(), "Москва");
+        assert_eq!(name.terminal(), Some("Ярославский вокзал"));
+        assert_eq!(name.to_string(), "Москва (Ярославский вокзал)");
+
+        let name = StationName::parse("РОСТОВ-НА-ДОНУ");
+        assert_eq!(name.name(), "Ростов-на-Дону");
+        assert_eq!(name.terminal(), None);
+
+        assert_eq!(StationName::parse("С-ПЕТЕР-ГЛ").name(), "С-Петер-Гл");
+        assert_eq!(StationName::parse("()").name(), "()");
+    }
+
+    #[test]
+    fn station_code_kind_test() {
+        assert_eq!(
+            RzdStationCode::new(2000000).kind(),
+            StationCodeKind::Express
+        );
+        assert_eq!(RzdStationCode::new(181102).kind(), StationCodeKind::Esr);
+        assert_eq!(RzdStationCode::new(60073).kind(), StationCodeKind::Esr);
+        assert_eq!(RzdStationCode::new(5).kind(), StationCodeKind::Esr);
+        assert_eq!(
+            RzdStationCode::new(10_000_000).kind(),
+            StationCodeKind::Unknown
+        );
+        assert_eq!(RzdStationCode::new(0).kind(), StationCodeKind::Unknown);
+
+        let directory: StationCodeDirectory = [(181102, 2004001)].into_iter().collect();
+        assert_eq!(
+            RzdStationCode::new(181102).to_express(&directory).unwrap(),
+            RzdStationCode::new(2004001)
+        );
+        assert_eq!(
+            RzdStationCode::new(2000000).to_express(&directory).unwrap(),
+            RzdStationCode::new(2000000)
+        );
+        assert!(RzdStationCode::new(181103).to_express(&directory).is_err());
+        assert_eq!(directory.esr(RzdStationCode::new(2004001)), Some(181102));
+    }
+
+    #[test]
+    fn city_aggregate_test() {
+        let moscow = RzdStationCode::new(2000000);
+        assert!(moscow.is_city_aggregate());
+        assert!(!RzdStationCode::new(2006004).is_city_aggregate());
+        assert!(!RzdStationCode::new(181000).is_city_aggregate());
+
+        let mut directory = StationCodeDirectory::new();
+        assert_eq!(moscow.expand(&directory), vec![moscow]);
+
+        directory.insert_terminal(2000000, 2006004);
+        directory.insert_terminal(2000000, 2000002);
+        directory.insert_terminal(2000000, 2006004);
+        assert_eq!(
+            moscow.expand(&directory),
+            vec![RzdStationCode::new(2006004), RzdStationCode::new(2000002)]
+        );
+        assert_eq!(directory.city(RzdStationCode::new(2000002)), Some(moscow));
+        assert_eq!(directory.city(moscow), None);
     }
 
     #[test]
@@ -0,0 +1,367 @@
+//! Search of the trains between every origin and every destination,
+//! e.g. to choose the pair of the cities to travel between.
+
+use std::fmt;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use crate::client::{request_target, RequestOptions};
+use crate::error::RzdErrorSet;
+use crate::scheduler::Scheduler;
+use crate::train_schedule::TrainInfo;
+use crate::{Result, RouteList, RzdStationCode, StationCodeDirectory};
+use crate::{TrainDate, TrainScheduleSearch, TrainType, TripDuration};
+
+// Number of the parallel searches of `matrix_search`.
+const MATRIX_WORKERS: usize = 2;
+// Pause between the searches of `matrix_search` not to overload the server.
+const MATRIX_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, PartialEq)]
+/// The best trip found between the origin and the destination.
+pub struct MatrixCell {
+    from: RzdStationCode,
+    to: RzdStationCode,
+    trains: usize,
+    best_duration: Option<TripDuration>,
+    best_price: Option<u32>,
+}
+
+impl MatrixCell {
+    /// Returns the code of the origin.
+    #[inline]
+    pub fn from(&self) -> RzdStationCode {
+        self.from
+    }
+
+    /// Returns the code of the destination.
+    #[inline]
+    pub fn to(&self) -> RzdStationCode {
+        self.to
+    }
+
+    /// Returns the number of the trains found.
+    #[inline]
+    pub fn trains(&self) -> usize {
+        self.trains
+    }
+
+    /// Returns the shortest duration of the trip among the trains.
+    #[inline]
+    pub fn best_duration(&self) -> Option<TripDuration> {
+        self.best_duration
+    }
+
+    /// Returns the lowest price of a ticket among the trains.
+    #[inline]
+    pub fn best_price(&self) -> Option<u32> {
+        self.best_price
+    }
+}
+
+impl fmt::Display for MatrixCell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}: поездов {}", self.from, self.to, self.trains)?;
+        if let Some(d) = self.best_duration {
+            write!(f, ", в пути от {}", d)?;
+        }
+        if let Some(p) = self.best_price {
+            write!(f, ", от {} руб.", p)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The best trips between the origins and the destinations,
+/// the cells go by the origins, then by the destinations.
+pub struct TravelMatrix(Vec<MatrixCell>);
+
+impl TravelMatrix {
+    /// Returns the cells of the pairs searched successfully.
+    #[inline]
+    pub fn cells(&self) -> &[MatrixCell] {
+        &self.0
+    }
+
+    /// Returns the cell of the origin and the destination.
+    pub fn get(&self, from: RzdStationCode, to: RzdStationCode) -> Option<&MatrixCell> {
+        self.0.iter().find(|c| c.from == from && c.to == to)
+    }
+
+    /// Returns the pair with the shortest trip.
+    pub fn best_by_duration(&self) -> Option<&MatrixCell> {
+        self.0
+            .iter()
+            .filter(|c| c.best_duration.is_some())
+            .min_by_key(|c| c.best_duration)
+    }
+
+    /// Returns the pair with the cheapest ticket.
+    pub fn best_by_price(&self) -> Option<&MatrixCell> {
+        self.0
+            .iter()
+            .filter(|c| c.best_price.is_some())
+            .min_by_key(|c| c.best_price)
+    }
+}
+
+impl fmt::Display for TravelMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.0 {
+            writeln!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Search of the trains between every origin and every destination on the date.
+///
+/// The repeated stations are searched once. With the directory of the terminals,
+/// the terminal of a city also listed is not searched separately,
+/// its trains are taken from the search of the city.
+///
+/// ```rust,no_run
+/// # use rzd_trains::{MatrixSearch, RequestOptions, Scheduler, StationCodeDirectory};
+/// # use std::time::Duration;
+/// #
+/// let mut directory = StationCodeDirectory::new();
+/// directory.insert_terminal(2000000, 2006004);
+///
+/// let search = MatrixSearch::new(&[2000000, 2006004], &[2004000, 2010000], (2022, 4, 1))
+///     .with_directory(directory);
+///
+/// let scheduler = Scheduler::new(2, 10, Duration::from_secs(1), RequestOptions::new());
+/// let (matrix, errors) = search.run(&scheduler);
+/// if let Some(best) = matrix.best_by_price() {
+///     println!("{}", best);
+/// }
+/// ```
+pub struct MatrixSearch {
+    origins: Vec<RzdStationCode>,
+    destinations: Vec<RzdStationCode>,
+    date: TrainDate,
+    directory: StationCodeDirectory,
+}
+
+impl MatrixSearch {
+    /// Creates `MatrixSearch` of the trains from the origins to the destinations on the date,
+    /// the repeated codes are dropped keeping the order.
+    pub fn new<C>(origins: &[C], destinations: &[C], date: impl Into<TrainDate>) -> Self
+    where
+        C: Copy + Into<RzdStationCode>,
+    {
+        let unique = |codes: &[C]| {
+            let mut unique: Vec<RzdStationCode> = vec![];
+            for c in codes.iter().map(|c| (*c).into()) {
+                if !unique.contains(&c) {
+                    unique.push(c);
+                }
+            }
+            unique
+        };
+
+        MatrixSearch {
+            origins: unique(origins),
+            destinations: unique(destinations),
+            date: date.into(),
+            directory: StationCodeDirectory::default(),
+        }
+    }
+
+    /// Sets the directory to find the terminals of the cities.
+    pub fn with_directory(mut self, directory: StationCodeDirectory) -> Self {
+        self.directory = directory;
+        self
+    }
+
+    /// Returns the pairs of the stations to be searched, without the same ones
+    /// and the terminals of the cities also listed.
+    pub fn pairs(&self) -> Vec<(RzdStationCode, RzdStationCode)> {
+        let searched = |codes: &[RzdStationCode]| -> Vec<RzdStationCode> {
+            codes
+                .iter()
+                .copied()
+                .filter(|c| !self.covered(*c, codes))
+                .collect()
+        };
+        let destinations = searched(&self.destinations);
+
+        searched(&self.origins)
+            .into_iter()
+            .flat_map(|from| destinations.iter().map(move |to| (from, *to)))
+            .filter(|(from, to)| from != to)
+            .collect()
+    }
+
+    /// Makes the searches by the scheduler, in parallel and with the pauses of the scheduler.
+    /// Returns the matrix of the pairs with the results and the errors
+    /// described by the URLs of their searches.
+    pub fn run(&self, scheduler: &Scheduler) -> (TravelMatrix, RzdErrorSet) {
+        let replies: Vec<(TrainScheduleSearch, Receiver<Result<Option<RouteList>>>)> = self
+            .pairs()
+            .into_iter()
+            .map(|(from, to)| {
+                let q = TrainScheduleSearch::new(from, to, self.date, TrainType::AllTrains, true);
+                let rx = scheduler.enqueue::<RouteList, _>(q.clone(), 0);
+                (q, rx)
+            })
+            .collect();
+
+        let mut errors = RzdErrorSet::new();
+        let mut found: Vec<((RzdStationCode, RzdStationCode), RouteList)> = vec![];
+        for (q, rx) in replies {
+            let pair = (q.leaving_code(), q.arriving_code());
+            match rx.recv() {
+                Ok(Ok(Some(routes))) => found.push((pair, routes)),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => errors.push(request_target::<RouteList, _>(&q), e),
+                // The scheduler is dropped before the search is made.
+                Err(_) => {}
+            }
+        }
+
+        (self.matrix(&found), errors)
+    }
+
+    // Returns true if the station is a terminal of a city also listed.
+    fn covered(&self, code: RzdStationCode, codes: &[RzdStationCode]) -> bool {
+        match self.directory.city(code) {
+            Some(city) => city != code && codes.contains(&city),
+            None => false,
+        }
+    }
+
+    // Returns the station the trains of the listed one are searched by.
+    fn searched_by(&self, code: RzdStationCode, codes: &[RzdStationCode]) -> RzdStationCode {
+        match self.covered(code, codes) {
+            true => self.directory.city(code).unwrap_or(code),
+            false => code,
+        }
+    }
+
+    // Makes the cells of all listed pairs from the routes found for the searched ones.
+    fn matrix(&self, found: &[((RzdStationCode, RzdStationCode), RouteList)]) -> TravelMatrix {
+        let mut cells = vec![];
+        for from in &self.origins {
+            for to in &self.destinations {
+                if from == to {
+                    continue;
+                }
+                let pair = (
+                    self.searched_by(*from, &self.origins),
+                    self.searched_by(*to, &self.destinations),
+                );
+                if let Some((_, routes)) = found.iter().find(|(p, _)| *p == pair) {
+                    cells.push(cell(*from, *to, pair, routes));
+                }
+            }
+        }
+        TravelMatrix(cells)
+    }
+}
+
+// Takes the trains of the routes going between the stations, all trains of the route
+// are taken for the searched station, only the ones of the terminal otherwise.
+fn cell(
+    from: RzdStationCode,
+    to: RzdStationCode,
+    searched: (RzdStationCode, RzdStationCode),
+    routes: &RouteList,
+) -> MatrixCell {
+    let trains: Vec<&TrainInfo> = routes
+        .iter()
+        .flat_map(|r| r.trains().iter())
+        .filter(|t| from == searched.0 || t.leaving_station_code() == from)
+        .filter(|t| to == searched.1 || t.arriving_station_code() == to)
+        .collect();
+
+    MatrixCell {
+        from,
+        to,
+        trains: trains.len(),
+        best_duration: trains.iter().filter_map(|t| t.duration()).min(),
+        best_price: trains.iter().filter_map(|t| t.summary().min_price()).min(),
+    }
+}
+
+/// Searches the trains between every origin and every destination on the date,
+/// see `MatrixSearch`. Two searches go in parallel with a pause of a second between them.
+pub fn matrix_search<C>(
+    origins: &[C],
+    destinations: &[C],
+    date: impl Into<TrainDate>,
+) -> (TravelMatrix, RzdErrorSet)
+where
+    C: Copy + Into<RzdStationCode>,
+{
+    let search = MatrixSearch::new(origins, destinations, date);
+    let scheduler = Scheduler::new(
+        MATRIX_WORKERS,
+        search.pairs().len(),
+        MATRIX_INTERVAL,
+        RequestOptions::new(),
+    );
+    search.run(&scheduler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell, MatrixSearch};
+    use crate::fixtures::load_fixture;
+    use crate::{parse_schedule_reply, RzdStationCode, StationCodeDirectory};
+
+    #[test]
+    fn pairs_test() {
+        let mut directory = StationCodeDirectory::new();
+        directory.insert_terminal(2000000, 2006004);
+
+        let search = MatrixSearch::new(
+            &[2000000, 2006004, 2000000, 2004000],
+            &[2004000, 2000000],
+            (2022, 4, 1),
+        );
+        assert_eq!(search.pairs().len(), 4);
+
+        let search = search.with_directory(directory);
+        let codes: Vec<(u32, u32)> = search
+            .pairs()
+            .into_iter()
+            .map(|(f, t)| (f.0, t.0))
+            .collect();
+        assert_eq!(codes, [(2000000, 2004000), (2004000, 2000000)]);
+    }
+
+    #[test]
+    fn matrix_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let route = &routes.as_ref()[0];
+        let (from, to) = (route.leaving_station_code(), route.arriving_station_code());
+
+        let mut directory = StationCodeDirectory::new();
+        directory.insert_terminal(to.0, 2006004);
+        let search = MatrixSearch::new(&[from], &[to, RzdStationCode::new(2006004)], (2022, 4, 1))
+            .with_directory(directory);
+        let matrix = search.matrix(&[((from, to), routes.clone())]);
+
+        let all = matrix.get(from, to).unwrap();
+        assert_eq!(all.trains(), route.trains().as_ref().len());
+        assert!(all.best_duration().is_some());
+        assert!(all.best_price().is_some());
+
+        let terminal = matrix.get(from, RzdStationCode::new(2006004)).unwrap();
+        let expected = route
+            .trains()
+            .iter()
+            .filter(|t| t.arriving_station_code().0 == 2006004)
+            .count();
+        assert_eq!(terminal.trains(), expected);
+        assert!(terminal.trains() <= all.trains());
+        assert_eq!(matrix.best_by_price().unwrap().to(), to);
+
+        let single = cell(from, to, (from, to), &routes);
+        assert_eq!(&single, all);
+    }
+}
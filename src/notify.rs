@@ -0,0 +1,138 @@
+//! Notifications about the changed results, e.g. of the repeated searches.
+
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+/// Change of the result of a search between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<T> {
+    key: String,
+    old: Option<T>,
+    new: Option<T>,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Compares the snapshots of the search marked by the key, e.g. its description,
+    /// `None` is returned if nothing is changed.
+    /// The missing snapshot means there was no data, e.g. before the first search.
+    pub fn between(key: &str, old: Option<T>, new: Option<T>) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+
+        Some(Change {
+            key: key.to_string(),
+            old,
+            new,
+        })
+    }
+}
+
+impl<T> Change<T> {
+    /// Returns the key of the search.
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the previous snapshot.
+    #[inline]
+    pub fn old_value(&self) -> Option<&T> {
+        self.old.as_ref()
+    }
+
+    /// Returns the current snapshot.
+    #[inline]
+    pub fn new_value(&self) -> Option<&T> {
+        self.new.as_ref()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Change<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (None, Some(new)) => write!(f, "{}: появилось {}", self.key, new),
+            (Some(old), None) => write!(f, "{}: пропало {}", self.key, old),
+            (Some(old), Some(new)) => write!(f, "{}: {} -> {}", self.key, old, new),
+            (None, None) => write!(f, "{}: без изменений", self.key),
+        }
+    }
+}
+
+/// Receives the changes of the results, e.g. to pass them to a messenger or an email.
+pub trait NotificationSink<T>: Send + Sync {
+    /// Takes the change of the result.
+    fn on_change(&self, change: &Change<T>);
+}
+
+/// Sends the changes to the channel, the receiver handles them in its own thread.
+/// The changes are dropped when the receiver is gone.
+pub struct ChannelSink<T> {
+    sender: Mutex<Sender<Change<T>>>,
+}
+
+impl<T> ChannelSink<T> {
+    /// Creates `ChannelSink` sending the changes to the `sender`,
+    /// they are dropped with a debug message once its receiver is dropped.
+    pub fn new(sender: Sender<Change<T>>) -> Self {
+        ChannelSink {
+            sender: Mutex::new(sender),
+        }
+    }
+}
+
+impl<T: Clone + Send> NotificationSink<T> for ChannelSink<T> {
+    fn on_change(&self, change: &Change<T>) {
+        let sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        if sender.send(change.clone()).is_err() {
+            debug!("change of {} is dropped, no receiver", change.key);
+        }
+    }
+}
+
+/// Writes the changes to the log at the info level.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LogSink;
+
+impl<T: fmt::Display> NotificationSink<T> for LogSink {
+    fn on_change(&self, change: &Change<T>) {
+        info!("{}", change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, ChannelSink, LogSink, NotificationSink};
+    use std::sync::mpsc;
+
+    #[test]
+    fn change_test() {
+        assert_eq!(Change::between("119А", Some(3), Some(3)), None);
+
+        let change = Change::between("119А", None, Some(3)).unwrap();
+        assert_eq!(change.key(), "119А");
+        assert_eq!(change.old_value(), None);
+        assert_eq!(change.new_value(), Some(&3));
+        assert_eq!(change.to_string(), "119А: появилось 3");
+
+        let change = Change::between("119А", Some(3), Some(1)).unwrap();
+        assert_eq!(change.to_string(), "119А: 3 -> 1");
+    }
+
+    #[test]
+    fn sinks_test() {
+        let (sender, receiver) = mpsc::channel();
+        let sinks: Vec<Box<dyn NotificationSink<u32>>> =
+            vec![Box::new(LogSink), Box::new(ChannelSink::new(sender))];
+
+        let change = Change::between("119А", Some(3), Some(1)).unwrap();
+        for sink in sinks.iter() {
+            sink.on_change(&change);
+        }
+        assert_eq!(receiver.try_recv().unwrap(), change);
+
+        drop(receiver);
+        sinks[1].on_change(&change);
+    }
+}
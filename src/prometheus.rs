@@ -0,0 +1,110 @@
+//! Exposition of the free seats and the prices in the Prometheus text format,
+//! enabled by the `prometheus` feature.
+
+use std::fmt::Write;
+
+use crate::train_schedule::Route;
+use crate::ResultList;
+
+impl ResultList<Route> {
+    /// Returns the free seats of every train by the seat type and the lowest price
+    /// of the train as the gauges of the Prometheus text format,
+    /// e.g. to serve them to the alerting on the availability.
+    ///
+    /// ```text
+    /// # HELP rzd_free_seats Number of free seats on the train by the seat type.
+    /// # TYPE rzd_free_seats gauge
+    /// rzd_free_seats{from="2004000",to="2000000",date="01.04.2022",train="119А",type="Плац"} 121
+    /// # HELP rzd_min_price Lowest ticket price on the train in rubles.
+    /// # TYPE rzd_min_price gauge
+    /// rzd_min_price{from="2004000",to="2000000",date="01.04.2022",train="119А"} 795
+    /// ```
+    pub fn to_prometheus(&self) -> String {
+        let mut seats = String::new();
+        let mut prices = String::new();
+
+        for route in self.iter() {
+            let date = route
+                .requested_date()
+                .map(|d| d.to_string())
+                .unwrap_or_default();
+
+            for train in route.trains().iter() {
+                let labels = format!(
+                    "from=\"{}\",to=\"{}\",date=\"{}\",train=\"{}\"",
+                    route.leaving_station_code(),
+                    route.arriving_station_code(),
+                    date,
+                    escape(train.train_number())
+                );
+
+                // The seats of one type may be listed a few times, e.g. for the disabled.
+                let mut types: Vec<(&str, u32)> = vec![];
+                for s in train.seats().iter() {
+                    match types.iter_mut().find(|t| t.0 == s.seats_type()) {
+                        Some(t) => t.1 += s.free_seats_number(),
+                        None => types.push((s.seats_type(), s.free_seats_number())),
+                    }
+                }
+                for (seats_type, free_seats) in types {
+                    let _ = writeln!(
+                        seats,
+                        "rzd_free_seats{{{},type=\"{}\"}} {}",
+                        labels,
+                        escape(seats_type),
+                        free_seats
+                    );
+                }
+
+                if let Some(price) = train.seats().iter().filter_map(|s| s.price()).min() {
+                    let _ = writeln!(prices, "rzd_min_price{{{}}} {}", labels, price);
+                }
+            }
+        }
+
+        format!(
+            "# HELP rzd_free_seats Number of free seats on the train by the seat type.\n\
+            # TYPE rzd_free_seats gauge\n\
+            {}\
+            # HELP rzd_min_price Lowest ticket price on the train in rubles.\n\
+            # TYPE rzd_min_price gauge\n\
+            {}",
+            seats, prices
+        )
+    }
+}
+
+// Escapes the label value as the text format requires.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape;
+    use crate::fixtures::load_fixture;
+    use crate::parse_schedule_reply;
+
+    #[test]
+    fn to_prometheus_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let text = routes.to_prometheus();
+
+        assert!(text.starts_with("# HELP rzd_free_seats"));
+        assert!(text.contains(
+            "rzd_free_seats{from=\"2004000\",to=\"2000000\",date=\"01.04.2022\",\
+            train=\"119А\",type=\"Купе\"} 68\n"
+        ));
+        assert!(text.contains(
+            "rzd_min_price{from=\"2004000\",to=\"2000000\",date=\"01.04.2022\",\
+            train=\"119А\"} 795\n"
+        ));
+        assert_eq!(text.matches("rzd_min_price{").count(), 3);
+
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,284 @@
+//! Standalone HTML pages with the tables of the results,
+//! enabled by the `report` feature, e.g. to email or archive a snapshot of the availability.
+
+use std::fmt::Write;
+
+use crate::train_schedule::{Route, TrainInfo};
+use crate::{ResultList, TrainDate, TrainItem, TrainTime, TripReport, TripStations};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em}\
+    table{border-collapse:collapse;margin-bottom:1.5em}\
+    th,td{border:1px solid #999;padding:.25em .5em;text-align:left}\
+    th{background:#eee}";
+
+impl ResultList<Route> {
+    /// Returns the HTML page with the table of the trains of every route,
+    /// the times, the durations, the free seats and the lowest prices.
+    pub fn to_html(&self) -> String {
+        page("Расписание поездов", &routes_section(self))
+    }
+}
+
+impl TrainItem {
+    /// Returns the HTML page with the table of the cars of the train and their free seats.
+    pub fn to_html(&self) -> String {
+        let title = format!("Вагоны поезда № {}", self.train_number());
+        page(&title, &cars_section(self))
+    }
+}
+
+impl TripStations {
+    /// Returns the HTML page with the table of the stops of the train.
+    pub fn to_html(&self) -> String {
+        let title = format!("Остановки поезда № {}", self.train_number());
+        page(&title, &stops_section(self))
+    }
+}
+
+impl TripReport {
+    /// Returns the HTML page with the tables of the train, its cars and stops.
+    ///
+    /// ```rust,no_run
+    /// # use rzd_trains::{RzdClient, TripReport};
+    /// #
+    /// let report = RzdClient::<TripReport>::full_trip(2004000, 2000000, (2022, 4, 1), "119А")
+    ///     .unwrap()
+    ///     .unwrap();
+    /// std::fs::write("119А.html", report.to_html()).unwrap();
+    /// ```
+    pub fn to_html(&self) -> String {
+        let train = self.schedule_row();
+        let mut body = format!("<h2>Поезд № {}</h2>\n", escape(train.train_number()));
+        body.push_str(&trains_table([train]));
+        if let Some(cars) = self.cars() {
+            body.push_str(&cars_section(cars));
+        }
+        if let Some(stops) = self.stops() {
+            body.push_str(&stops_section(stops));
+        }
+
+        let title = format!("Поезд № {}", train.train_number());
+        page(&title, &body)
+    }
+}
+
+// Wraps the sections into the page with the title.
+fn page(title: &str, body: &str) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n\
+        <html lang=\"ru\">\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <title>{}</title>\n\
+        <style>{}</style>\n\
+        </head>\n\
+        <body>\n\
+        <h1>{}</h1>\n\
+        {}\
+        </body>\n\
+        </html>\n",
+        title, STYLE, title, body
+    )
+}
+
+fn routes_section(routes: &ResultList<Route>) -> String {
+    let mut section = String::new();
+    for route in routes.iter() {
+        let date = route
+            .requested_date()
+            .map(|d| format!(", {}", d))
+            .unwrap_or_default();
+        let _ = writeln!(
+            section,
+            "<h2>{} - {}{}</h2>",
+            escape(route.leaving_station_name()),
+            escape(route.arriving_station_name()),
+            date
+        );
+        section.push_str(&trains_table(route.trains().iter()));
+    }
+    section
+}
+
+fn trains_table<'a>(trains: impl IntoIterator<Item = &'a TrainInfo>) -> String {
+    let rows = trains
+        .into_iter()
+        .map(|t| {
+            vec![
+                format!("{} {}", t.train_number(), t.brand())
+                    .trim()
+                    .to_string(),
+                format!(
+                    "{} {}",
+                    t.leaving_station(),
+                    at(t.leaving_date(), t.leaving_time())
+                ),
+                format!(
+                    "{} {}",
+                    t.arriving_station(),
+                    at(t.arriving_date(), t.arriving_time())
+                ),
+                t.duration().map(|d| d.to_string()).unwrap_or_default(),
+                t.total_free_seats().to_string(),
+                or_empty(t.summary().min_price()),
+            ]
+        })
+        .collect();
+
+    table(
+        &[
+            "Поезд",
+            "Отправление",
+            "Прибытие",
+            "В пути",
+            "Мест",
+            "Цена от, руб.",
+        ],
+        rows,
+    )
+}
+
+fn cars_section(train: &TrainItem) -> String {
+    let rows = train
+        .cars()
+        .iter()
+        .map(|c| {
+            vec![
+                c.number().to_string(),
+                c.type_loc().to_string(),
+                c.service_class().to_string(),
+                c.total_free_seats().to_string(),
+                c.lower_berths_free().to_string(),
+                c.upper_berths_free().to_string(),
+                or_empty(c.min_price()),
+            ]
+        })
+        .collect();
+
+    format!(
+        "<h2>Вагоны</h2>\n{}",
+        table(
+            &[
+                "Вагон",
+                "Тип",
+                "Класс",
+                "Мест",
+                "Нижних",
+                "Верхних",
+                "Цена от, руб.",
+            ],
+            rows,
+        )
+    )
+}
+
+fn stops_section(stops: &TripStations) -> String {
+    let rows = stops
+        .stations()
+        .iter()
+        .map(|s| {
+            vec![
+                s.station().to_string(),
+                s.code().to_string(),
+                or_empty(s.arriving_time()),
+                or_empty(s.leaving_time()),
+                or_empty(s.dwell_minutes()),
+                s.trip_days().to_string(),
+            ]
+        })
+        .collect();
+
+    format!(
+        "<h2>Остановки</h2>\n{}",
+        table(
+            &[
+                "Станция",
+                "Код",
+                "Прибытие",
+                "Отправление",
+                "Стоянка, мин.",
+                "В пути, дн.",
+            ],
+            rows,
+        )
+    )
+}
+
+// Writes the table with the header row, the cells are escaped.
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut table = String::from("<table>\n<tr>");
+    for h in headers {
+        let _ = write!(table, "<th>{}</th>", escape(h));
+    }
+    table.push_str("</tr>\n");
+    for row in rows {
+        table.push_str("<tr>");
+        for cell in row {
+            let _ = write!(table, "<td>{}</td>", escape(&cell));
+        }
+        table.push_str("</tr>\n");
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+fn at(date: Option<TrainDate>, time: Option<TrainTime>) -> String {
+    let date = date.map(|d| d.to_string()).unwrap_or_default();
+    let time = time.map(|t| t.to_string()).unwrap_or_default();
+    format!("{} {}", date, time).trim().to_string()
+}
+
+fn or_empty(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// Escapes the text for the HTML page.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape;
+    use crate::fixtures::load_fixture;
+    use crate::{parse_schedule_reply, parse_train_reply, parse_trip_reply};
+
+    #[test]
+    fn to_html_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let html = routes.to_html();
+        let trains = routes.as_ref()[0].trains().as_ref().len();
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>Расписание поездов</title>"));
+        assert!(html.contains("<th>Отправление</th>"));
+        assert!(html.contains("<td>795</td>"));
+        assert_eq!(html.matches("<tr>").count(), trains + 1);
+        assert!(html.ends_with("</html>\n"));
+
+        let answer = load_fixture("train_info").unwrap();
+        let cars = parse_train_reply(&answer).unwrap().unwrap();
+        let html = cars.as_ref()[0].to_html();
+        assert!(html.contains("<th>Вагон</th>"));
+        assert_eq!(
+            html.matches("<tr>").count(),
+            cars.as_ref()[0].cars().as_ref().len() + 1
+        );
+
+        let answer = load_fixture("trip_info").unwrap();
+        let stops = parse_trip_reply(&answer).unwrap().unwrap();
+        let html = stops.to_html();
+        assert!(html.contains("<th>Стоянка, мин.</th>"));
+        assert!(html.contains(&format!("<td>{}</td>", stops.stations().as_ref()[0].code())));
+
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
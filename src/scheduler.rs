@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::client::{request_target, RequestOptions, RzdClient, RzdClientInterface};
+use crate::{error::Error, Result};
+
+type Job = Box<dyn FnOnce(&RequestOptions) + Send>;
+
+// Job in the queue, the higher priority goes first, then the earlier one.
+struct Queued {
+    priority: u8,
+    seq: u64,
+    job: Job,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct State {
+    queue: BinaryHeap<Queued>,
+    seq: u64,
+    closed: bool,
+    next_start: Option<DateTime<Utc>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    available: Condvar,
+    space: Condvar,
+    capacity: usize,
+    min_interval: Duration,
+    options: RequestOptions,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Background queue of the searches executed by a pool of workers.
+///
+/// The searches with higher priority go first, and the requests of all the workers
+/// start not more often than once per `min_interval` not to overload the server.
+/// The search panicked in a worker is reported by `Error::SearchAborted`.
+/// The queue is bounded, so the producers wait or get a refusal when it is full.
+/// The queued searches are finished when the scheduler is dropped.
+///
+/// ```rust,no_run
+/// # use rzd_trains::{RequestOptions, RouteList, Scheduler, TrainScheduleSearch, TrainType};
+/// # use std::time::Duration;
+/// #
+/// let scheduler = Scheduler::new(2, 100, Duration::from_secs(1), RequestOptions::new());
+///
+/// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+/// let reply = scheduler.enqueue::<RouteList, _>(q, 10);
+///
+/// match reply.recv().unwrap() {
+///     Ok(Some(list)) => println!("{}", list),
+///     Ok(None) => println!("Nothing found"),
+///     Err(e) => println!("{}", e),
+/// }
+/// ```
+pub struct Scheduler {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    /// Takes the number of the workers, the maximum number of the queued searches,
+    /// the minimum interval between the requests and the options of the requests.
+    pub fn new(
+        workers: usize,
+        capacity: usize,
+        min_interval: Duration,
+        options: RequestOptions,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: BinaryHeap::new(),
+                seq: 0,
+                closed: false,
+                next_start: None,
+            }),
+            available: Condvar::new(),
+            space: Condvar::new(),
+            capacity: capacity.max(1),
+            min_interval,
+            options,
+        });
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || work(&shared))
+            })
+            .collect();
+
+        Scheduler { shared, workers }
+    }
+
+    /// Adds the search to the queue, waiting while the queue is full.
+    /// The result is sent to the returned channel.
+    pub fn enqueue<T, U>(&self, search: U, priority: u8) -> Receiver<Result<Option<T>>>
+    where
+        T: Send + 'static,
+        U: RzdClientInterface<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.push(priority, search_job(search, tx), true);
+        rx
+    }
+
+    /// Adds the search to the queue, returns `None` at once if the queue is full.
+    pub fn try_enqueue<T, U>(&self, search: U, priority: u8) -> Option<Receiver<Result<Option<T>>>>
+    where
+        T: Send + 'static,
+        U: RzdClientInterface<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        match self.push(priority, search_job(search, tx), false) {
+            true => Some(rx),
+            false => None,
+        }
+    }
+
+    /// Returns the number of the searches waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    /// Returns true if no searches are waiting in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Queues the job, returns false if the queue is full and waiting is not allowed.
+    fn push(&self, priority: u8, job: Job, wait: bool) -> bool {
+        let mut state = self.shared.lock();
+        while state.queue.len() >= self.shared.capacity {
+            if !wait {
+                return false;
+            }
+            state = self
+                .shared
+                .space
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+
+        state.seq += 1;
+        let seq = state.seq;
+        state.queue.push(Queued { priority, seq, job });
+        self.shared.available.notify_one();
+        true
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn search_job<T, U>(search: U, tx: mpsc::Sender<Result<Option<T>>>) -> Job
+where
+    T: Send + 'static,
+    U: RzdClientInterface<T> + Send + 'static,
+{
+    Box::new(move |options: &RequestOptions| {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            RzdClient::get_with(&search, options.clone())
+        }))
+        .unwrap_or_else(|_| Err(Error::SearchAborted(request_target::<T, U>(&search))));
+        // The receiver may be dropped if the result is not needed anymore.
+        let _ = tx.send(result);
+    })
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let (job, delay) = {
+            let mut state = shared.lock();
+            while state.queue.is_empty() && !state.closed {
+                state = shared
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+            let job = match state.queue.pop() {
+                Some(q) => q.job,
+                None => return,
+            };
+            shared.space.notify_one();
+
+            let now = shared.options.clock().now();
+            let start = state.next_start.map_or(now, |s| s.max(now));
+            state.next_start =
+                Some(start + chrono::Duration::from_std(shared.min_interval).unwrap_or_default());
+            (job, (start - now).to_std().unwrap_or_default())
+        };
+
+        if !delay.is_zero() {
+            shared.options.sleeper().sleep(delay);
+        }
+        // The worker goes on with the next jobs if the job panicked.
+        if panic::catch_unwind(AssertUnwindSafe(|| job(&shared.options))).is_err() {
+            error!("the job of the scheduler panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scheduler;
+    use crate::error::Error;
+    use crate::{RequestOptions, ResultList, StationCodeSearch, StationItem, Url};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn priority_test() {
+        let scheduler = Scheduler::new(1, 2, Duration::ZERO, RequestOptions::new());
+        let order = Arc::new(Mutex::new(vec![]));
+
+        // The worker is blocked until the other jobs are queued.
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started, wait_started) = mpsc::channel::<()>();
+        scheduler.push(
+            0,
+            Box::new(move |_| {
+                started.send(()).unwrap();
+                blocked.recv().unwrap();
+            }),
+            true,
+        );
+        wait_started.recv().unwrap();
+
+        for priority in [1, 5] {
+            let order = order.clone();
+            assert!(scheduler.push(
+                priority,
+                Box::new(move |_| order.lock().unwrap().push(priority)),
+                false
+            ));
+        }
+        assert_eq!(scheduler.len(), 2);
+        assert!(!scheduler.push(9, Box::new(|_| {}), false));
+
+        release.send(()).unwrap();
+        drop(scheduler);
+        assert_eq!(*order.lock().unwrap(), vec![5, 1]);
+    }
+
+    #[test]
+    fn panic_test() {
+        let scheduler = Scheduler::new(1, 2, Duration::ZERO, RequestOptions::new());
+        let (tx, rx) = mpsc::channel();
+
+        scheduler.push(0, Box::new(|_| panic!("broken job")), true);
+        scheduler.push(0, Box::new(move |_| tx.send(()).unwrap()), true);
+
+        // The only worker survives the panic.
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        let options = RequestOptions::new()
+            .with_url_rewriter(Arc::new(|_: Url| -> Url { panic!("broken rewriter") }));
+        let scheduler = Scheduler::new(1, 1, Duration::ZERO, options);
+        let q = StationCodeSearch::new("москва").unwrap();
+        let reply = scheduler.enqueue::<ResultList<StationItem>, _>(q, 0);
+        assert!(matches!(reply.recv(), Ok(Err(Error::SearchAborted(_)))));
+    }
+}
@@ -0,0 +1,265 @@
+//! Versioned JSON of the results, e.g. to keep the collected snapshots readable
+//! by the newer versions of the crate.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Debug, Display};
+
+use crate::trip_info::TripStations;
+use crate::{error::Error, Result, ResultList, SeatKind, TrainType};
+
+/// Version of the JSON schema written by `to_versioned_json`.
+///
+/// * `1` is the plain JSON of `to_json` without the version.
+/// * `2` wraps the data into `{"schema_version":2,"data":...}`,
+///   the trains have the full `duration` and the stops have the `distance`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct Versioned<'a, T: Serialize> {
+    schema_version: u32,
+    data: &'a T,
+}
+
+#[derive(Deserialize)]
+struct VersionedOwned {
+    schema_version: u32,
+    data: Value,
+}
+
+fn to_versioned_json<T: Serialize>(data: &T, empty: &str) -> String {
+    let versioned = Versioned {
+        schema_version: SCHEMA_VERSION,
+        data,
+    };
+    match serde_json::to_string(&versioned) {
+        Ok(v) => v,
+        Err(_) => format!(
+            "{{\"schema_version\":{},\"data\":{}}}",
+            SCHEMA_VERSION, empty
+        ),
+    }
+}
+
+fn from_versioned_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let error = |e: serde_json::Error| Error::DeserializeError(format!("{}", e));
+
+    let value: Value = serde_json::from_str(json).map_err(error)?;
+    let data = match value.get("schema_version") {
+        // The first version has no envelope.
+        None => {
+            let mut value = value;
+            migrate_v1(&mut value);
+            value
+        }
+        Some(_) => {
+            let versioned: VersionedOwned = serde_json::from_value(value).map_err(error)?;
+            if versioned.schema_version > SCHEMA_VERSION {
+                return Err(Error::DeserializeError(format!(
+                    "unsupported schema version {}, the latest known one is {}",
+                    versioned.schema_version, SCHEMA_VERSION
+                )));
+            }
+            versioned.data
+        }
+    };
+
+    serde_json::from_value(data).map_err(error)
+}
+
+// Brings the JSON of the first version to the current schema. The fields added since then
+// have the defaults, the ones below are derived from the data of the first version.
+fn migrate_v1(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(migrate_v1),
+        Value::Object(object) => {
+            // The carrier was written as the type of the train.
+            if let Some(Value::String(carrier)) = object.get("train_type") {
+                if serde_json::from_value::<TrainType>(Value::String(carrier.clone())).is_err() {
+                    let carrier = Value::String(carrier.clone());
+                    object.entry("carrier").or_insert(carrier);
+                    object.insert("train_type".to_string(), Value::Null);
+                }
+            }
+            // The seats had no kind.
+            if let Some(Value::String(name)) = object.get("seats_type") {
+                if !object.contains_key("kind") {
+                    let kind = serde_json::to_value(SeatKind::from(name.as_str()));
+                    object.insert("kind".to_string(), kind.unwrap_or(Value::Null));
+                }
+            }
+            object.values_mut().for_each(migrate_v1);
+        }
+        _ => {}
+    }
+}
+
+impl<T> ResultList<T>
+where
+    T: Debug + Display + Serialize,
+{
+    /// Performs the conversion into a JSON string with the version of the schema,
+    /// see `SCHEMA_VERSION`.
+    pub fn to_versioned_json(&self) -> String {
+        to_versioned_json(self, "[]")
+    }
+}
+
+impl<T> ResultList<T>
+where
+    T: Debug + Display + Serialize + DeserializeOwned,
+{
+    /// Reads the list written by `to_versioned_json` of this or an older version of the crate
+    /// or by `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the JSON is malformed or its schema is newer than the known one.
+    pub fn from_versioned_json(json: &str) -> Result<Self> {
+        from_versioned_json(json)
+    }
+}
+
+impl TripStations {
+    /// Performs the conversion into a JSON string with the version of the schema,
+    /// see `SCHEMA_VERSION`.
+    pub fn to_versioned_json(&self) -> String {
+        to_versioned_json(self, "{}")
+    }
+
+    /// Reads the stops written by `to_versioned_json` of this or an older version of the crate
+    /// or by `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the JSON is malformed or its schema is newer than the known one.
+    pub fn from_versioned_json(json: &str) -> Result<Self> {
+        from_versioned_json(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SCHEMA_VERSION;
+    use crate::error::{Error, GatewayInfo};
+    use crate::fixtures::load_fixture;
+    use crate::train_schedule::SaleState;
+    use crate::{parse_schedule_reply, parse_train_reply, parse_trip_reply, TripStations};
+    use crate::{Carrier, RouteList, RzdStationCode, SeatKind, TrainCategory, TrainInfoList};
+
+    #[test]
+    fn versioned_json_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let json = routes.to_versioned_json();
+        assert!(json.starts_with(&format!(
+            "{{\"schema_version\":{},\"data\":[",
+            SCHEMA_VERSION
+        )));
+        assert_eq!(RouteList::from_versioned_json(&json).unwrap(), routes);
+
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let json = trains.to_versioned_json();
+        assert_eq!(TrainInfoList::from_versioned_json(&json).unwrap(), trains);
+
+        let answer = load_fixture("trip_info").unwrap();
+        let trip = parse_trip_reply(&answer).unwrap().unwrap();
+        let json = trip.to_versioned_json();
+        assert_eq!(TripStations::from_versioned_json(&json).unwrap(), trip);
+    }
+
+    #[test]
+    fn previous_version_test() {
+        // Written by `to_json` of the first version from the same fixtures.
+        let json = load_fixture("v1_train_schedule").unwrap();
+        let routes = RouteList::from_versioned_json(&json).unwrap();
+        let answer = load_fixture("train_schedule").unwrap();
+        let current = parse_schedule_reply(&answer).unwrap().unwrap();
+        let route = &routes.as_ref()[0];
+        assert_eq!(
+            route.leaving_station_code(),
+            current.as_ref()[0].leaving_station_code()
+        );
+        assert!(!route.no_seats());
+        assert!(route.warnings().is_empty());
+        assert_eq!(
+            route.trains().as_ref().len(),
+            current.as_ref()[0].trains().as_ref().len()
+        );
+        let train = &route.trains().as_ref()[0];
+        let expected = &current.as_ref()[0].trains().as_ref()[0];
+        assert_eq!(train.train_number(), "119А");
+        assert_eq!(train.carrier(), &Carrier::Fpk);
+        assert_eq!(train.kind(), None);
+        assert_eq!(train.category(), TrainCategory::default());
+        assert!(!train.sold_out());
+        assert_eq!(train.sale_state(), SaleState::Unknown);
+        // The codes of the stations of the train are unknown, the searched ones may be cities.
+        assert_eq!(train.leaving_station_code(), RzdStationCode::default());
+        assert_eq!(train.arriving_station_code(), RzdStationCode::default());
+        assert_eq!(train.leaving_time(), expected.leaving_time());
+        assert_eq!(train.trip_duration(), expected.trip_duration());
+        assert_eq!(train.seats().as_ref()[0].kind(), &SeatKind::OpenBerth);
+        assert_eq!(train.total_free_seats(), expected.total_free_seats());
+
+        let json = load_fixture("v1_train_schedule_electric").unwrap();
+        let routes = RouteList::from_versioned_json(&json).unwrap();
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+        assert_eq!(train.carrier(), &Carrier::Szppk);
+
+        let json = load_fixture("v1_train_info").unwrap();
+        let trains = TrainInfoList::from_versioned_json(&json).unwrap();
+        let answer = load_fixture("train_info").unwrap();
+        let current = parse_train_reply(&answer).unwrap().unwrap();
+        let (train, expected) = (&trains.as_ref()[0], &current.as_ref()[0]);
+        assert_eq!(train.train_number(), expected.train_number());
+        assert!(train.warnings().is_empty());
+        assert_eq!(train.cars().as_ref().len(), expected.cars().as_ref().len());
+        let car = &train.cars().as_ref()[0];
+        assert_eq!(car.seats().as_ref()[0].kind(), &SeatKind::Lower);
+        assert_eq!(
+            car.total_free_seats(),
+            expected.cars().as_ref()[0].total_free_seats()
+        );
+
+        let json = load_fixture("v1_trip_info").unwrap();
+        let trip = TripStations::from_versioned_json(&json).unwrap();
+        let answer = load_fixture("trip_info").unwrap();
+        let current = parse_trip_reply(&answer).unwrap().unwrap();
+        assert_eq!(trip.train_number(), current.train_number());
+        assert_eq!(trip.gateway(), &GatewayInfo::default());
+        assert_eq!(
+            trip.stations().as_ref().len(),
+            current.stations().as_ref().len()
+        );
+        let stop = &trip.stations().as_ref()[1];
+        assert_eq!(stop.code(), current.stations().as_ref()[1].code());
+        assert_eq!(stop.distance_km(), None);
+
+        // The plain JSON of the current version is read as is.
+        assert_eq!(
+            RouteList::from_versioned_json(&current_routes().to_json()).unwrap(),
+            current_routes()
+        );
+    }
+
+    fn current_routes() -> RouteList {
+        let answer = load_fixture("train_schedule").unwrap();
+        parse_schedule_reply(&answer).unwrap().unwrap()
+    }
+
+    #[test]
+    fn newer_version_test() {
+        let json = r#"{"schema_version":99,"data":[]}"#;
+        assert!(matches!(
+            RouteList::from_versioned_json(json),
+            Err(Error::DeserializeError(_))
+        ));
+        assert!(matches!(
+            RouteList::from_versioned_json("not a json"),
+            Err(Error::DeserializeError(_))
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+//! The two kinds of the free seats, both named `SeatsInfo` in their modules:
+//! `TrainSeats` of the train in the schedule and `CarSeats` of the car of the train.
+//!
+//! The seats of the schedule have the lowest price as a number of rubles,
+//! the seats of the car have the price as the server writes it.
+//! The conversions between them keep the number, the type and the kind of the seats.
+
+use crate::des::parse_price;
+use crate::error::Error;
+
+/// Free seats of the train in the schedule, see `Route::trains`.
+pub use crate::train_schedule::SeatsInfo as TrainSeats;
+
+/// Free seats of the car of the train, see `TrainItem::cars`.
+pub use crate::train_info::SeatsInfo as CarSeats;
+
+impl From<&TrainSeats> for CarSeats {
+    /// The price is written as a whole number of rubles, empty if it is unknown.
+    fn from(seats: &TrainSeats) -> Self {
+        CarSeats::new(
+            seats.free_seats_number(),
+            seats.seats_type().to_string(),
+            seats.kind().clone(),
+            seats.price().map(|p| p.to_string()).unwrap_or_default(),
+        )
+    }
+}
+
+impl From<TrainSeats> for CarSeats {
+    fn from(seats: TrainSeats) -> Self {
+        CarSeats::from(&seats)
+    }
+}
+
+impl TryFrom<&CarSeats> for TrainSeats {
+    type Error = Error;
+
+    /// The price is rounded to whole rubles, the empty one is unknown.
+    ///
+    /// # Errors
+    ///
+    /// The conversion fails with `Error::ParsePriceError` if the price is not a number.
+    fn try_from(seats: &CarSeats) -> Result<Self, Self::Error> {
+        let price = match seats.price_opt() {
+            Some(p) => Some(parse_price(p).ok_or_else(|| Error::ParsePriceError(p.to_string()))?),
+            None => None,
+        };
+
+        Ok(TrainSeats::new(
+            seats.free_seats_number(),
+            seats.seats_type().to_string(),
+            seats.kind().clone(),
+            price,
+        ))
+    }
+}
+
+impl TryFrom<CarSeats> for TrainSeats {
+    type Error = Error;
+
+    fn try_from(seats: CarSeats) -> Result<Self, Self::Error> {
+        TrainSeats::try_from(&seats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CarSeats, TrainSeats};
+    use crate::error::Error;
+    use crate::fixtures::load_fixture;
+    use crate::{parse_schedule_reply, parse_train_reply, SeatKind};
+
+    #[test]
+    fn conversion_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        for car_seats in trains
+            .iter()
+            .flat_map(|t| t.cars().iter())
+            .flat_map(|c| c.seats().iter())
+        {
+            let seats = TrainSeats::try_from(car_seats).unwrap();
+            assert_eq!(seats.free_seats_number(), car_seats.free_seats_number());
+            assert_eq!(seats.seats_type(), car_seats.seats_type());
+            assert_eq!(seats.kind(), car_seats.kind());
+            assert_eq!(seats.price().is_some(), car_seats.price_opt().is_some());
+        }
+
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let seats = &routes.as_ref()[0].trains().as_ref()[0].seats().as_ref()[0];
+        let car_seats = CarSeats::from(seats);
+        assert_eq!(car_seats.price(), seats.price().unwrap().to_string());
+        assert_eq!(&TrainSeats::try_from(car_seats).unwrap(), seats);
+
+        let malformed = CarSeats::new(2, "Купе".to_string(), SeatKind::Lower, "договорная".into());
+        assert!(matches!(
+            TrainSeats::try_from(malformed),
+            Err(Error::ParsePriceError(ref p)) if p == "договорная"
+        ));
+        let unknown = CarSeats::new(2, "Купе".to_string(), SeatKind::Lower, String::new());
+        assert_eq!(TrainSeats::try_from(unknown).unwrap().price(), None);
+    }
+}
@@ -2,7 +2,7 @@
 
 use serde::ser::{Serialize, Serializer};
 
-use crate::{TrainDate, TrainTime};
+use crate::{Carrier, ServerTimestamp, TrainDate, TrainTime, TripDuration};
 
 impl Serialize for TrainDate {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -13,7 +13,38 @@ impl Serialize for TrainDate {
     }
 }
 
+impl Serialize for Carrier {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
 impl Serialize for TrainTime {
+    // The seconds are written only if there are any, so the time is read back the same.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.seconds() {
+            0 => serializer.serialize_str(&format!("{}", self)),
+            _ => serializer.serialize_str(&format!("{}", self.with_seconds())),
+        }
+    }
+}
+
+impl Serialize for ServerTimestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{}", self))
+    }
+}
+
+impl Serialize for TripDuration {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -24,7 +55,7 @@ impl Serialize for TrainTime {
 
 #[cfg(test)]
 mod tests {
-    use super::{TrainDate, TrainTime};
+    use super::{TrainDate, TrainTime, TripDuration};
 
     #[test]
     fn train_date_test() {
@@ -36,9 +67,22 @@ mod tests {
 
     #[test]
     fn train_time_test() {
-        let t = TrainTime::new(5, 7);
+        let t = TrainTime::try_new(5, 7).unwrap();
         let json = serde_json::to_string(&t).unwrap();
 
         assert_eq!(json, r#""05:07""#);
+
+        let t = TrainTime::try_with_seconds(5, 7, 30).unwrap();
+        let json = serde_json::to_string(&t).unwrap();
+        assert_eq!(json, r#""05:07:30""#);
+        assert_eq!(serde_json::from_str::<TrainTime>(&json).unwrap(), t);
+    }
+
+    #[test]
+    fn trip_duration_test() {
+        let d = TripDuration::new(33, 5).unwrap();
+        let json = serde_json::to_string(&d).unwrap();
+
+        assert_eq!(json, r#""33:05""#);
     }
 }
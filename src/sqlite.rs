@@ -0,0 +1,294 @@
+//! Export of the results to SQLite tables, enabled by the `sqlite` feature.
+
+use rusqlite::{params, Connection};
+
+use crate::des::parse_price;
+use crate::train_info::TrainItem;
+use crate::train_schedule::{Route, TrainInfo};
+use crate::trip_info::TripStations;
+use crate::{Result, ResultList, TrainDate};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS routes (
+        id INTEGER PRIMARY KEY,
+        leaving_code INTEGER NOT NULL,
+        leaving_name TEXT NOT NULL,
+        arriving_code INTEGER NOT NULL,
+        arriving_name TEXT NOT NULL,
+        date TEXT
+    );
+    CREATE TABLE IF NOT EXISTS trains (
+        id INTEGER PRIMARY KEY,
+        route_id INTEGER REFERENCES routes (id),
+        number TEXT NOT NULL,
+        brand TEXT,
+        carrier TEXT,
+        category TEXT NOT NULL,
+        leaving_code INTEGER NOT NULL,
+        leaving_station TEXT NOT NULL,
+        leaving_date TEXT,
+        leaving_time TEXT,
+        arriving_code INTEGER NOT NULL,
+        arriving_station TEXT NOT NULL,
+        arriving_date TEXT,
+        arriving_time TEXT,
+        duration TEXT
+    );
+    CREATE TABLE IF NOT EXISTS cars (
+        id INTEGER PRIMARY KEY,
+        train_id INTEGER NOT NULL REFERENCES trains (id),
+        number TEXT NOT NULL,
+        type_loc TEXT NOT NULL,
+        service_class TEXT NOT NULL,
+        carrier TEXT NOT NULL,
+        min_price INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS seats (
+        id INTEGER PRIMARY KEY,
+        train_id INTEGER NOT NULL REFERENCES trains (id),
+        car_id INTEGER REFERENCES cars (id),
+        seats_type TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        free_seats INTEGER NOT NULL,
+        price INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS stops (
+        id INTEGER PRIMARY KEY,
+        train_number TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        station TEXT NOT NULL,
+        code INTEGER NOT NULL,
+        trip_days INTEGER NOT NULL,
+        arriving_time TEXT,
+        leaving_time TEXT
+    );
+";
+
+/// Results which may be exported to SQLite, see `export_to_sqlite`.
+pub trait SqliteExport {
+    /// Inserts the rows of the result into the tables created already.
+    fn insert_rows(&self, conn: &Connection) -> rusqlite::Result<()>;
+}
+
+/// Writes the result to the normalized tables `routes`, `trains`, `cars`, `seats` and `stops`,
+/// the missing tables are created. The rows are added in one transaction,
+/// the dates are written as `YYYY-MM-DD` and the times as `HH:MM`.
+///
+/// ```rust,no_run
+/// use rusqlite::Connection;
+/// use rzd_trains::{export_to_sqlite, RouteList, RzdClient, TrainScheduleSearch, TrainType};
+///
+/// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+/// let routes = RzdClient::<RouteList>::get(&q).unwrap().unwrap();
+///
+/// let conn = Connection::open("trains.db").unwrap();
+/// export_to_sqlite(&conn, &routes).unwrap();
+/// ```
+///
+/// # Errors
+///
+/// The method fails if the database couldn't be written, nothing is added then.
+pub fn export_to_sqlite<T: SqliteExport>(conn: &Connection, data: &T) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(SCHEMA)?;
+    data.insert_rows(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+impl SqliteExport for ResultList<Route> {
+    fn insert_rows(&self, conn: &Connection) -> rusqlite::Result<()> {
+        for route in self.iter() {
+            conn.execute(
+                "INSERT INTO routes (leaving_code, leaving_name, arriving_code, arriving_name, date)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    route.leaving_station_code().to_uint(),
+                    route.leaving_station_name(),
+                    route.arriving_station_code().to_uint(),
+                    route.arriving_station_name(),
+                    iso_date(route.requested_date()),
+                ],
+            )?;
+            let route_id = conn.last_insert_rowid();
+
+            for train in route.trains().iter() {
+                insert_schedule_train(conn, route_id, train)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn insert_schedule_train(
+    conn: &Connection,
+    route_id: i64,
+    train: &TrainInfo,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO trains (route_id, number, brand, carrier, category,
+            leaving_code, leaving_station, leaving_date, leaving_time,
+            arriving_code, arriving_station, arriving_date, arriving_time, duration)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            route_id,
+            train.train_number(),
+            train.brand(),
+            train.carrier().name(),
+            train.category().to_string(),
+            train.leaving_station_code().to_uint(),
+            train.leaving_station(),
+            iso_date(train.leaving_date()),
+            train.leaving_time().map(|t| t.to_string()),
+            train.arriving_station_code().to_uint(),
+            train.arriving_station(),
+            iso_date(train.arriving_date()),
+            train.arriving_time().map(|t| t.to_string()),
+            train.duration().map(|t| t.to_string()),
+        ],
+    )?;
+    let train_id = conn.last_insert_rowid();
+
+    for seats in train.seats().iter() {
+        conn.execute(
+            "INSERT INTO seats (train_id, seats_type, kind, free_seats, price)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                train_id,
+                seats.seats_type(),
+                seats.kind().to_string(),
+                seats.free_seats_number(),
+                seats.price(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+impl SqliteExport for ResultList<TrainItem> {
+    fn insert_rows(&self, conn: &Connection) -> rusqlite::Result<()> {
+        for train in self.iter() {
+            conn.execute(
+                "INSERT INTO trains (number, category,
+                    leaving_code, leaving_station, leaving_date, leaving_time,
+                    arriving_code, arriving_station, arriving_date, arriving_time)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    train.train_number(),
+                    train.category().to_string(),
+                    train.leaving_station_code().to_uint(),
+                    train.leaving_station_name(),
+                    iso_date(train.leaving_date()),
+                    train.leaving_time().map(|t| t.to_string()),
+                    train.arriving_station_code().to_uint(),
+                    train.arriving_station_name(),
+                    iso_date(train.arriving_date()),
+                    train.arriving_time().map(|t| t.to_string()),
+                ],
+            )?;
+            let train_id = conn.last_insert_rowid();
+
+            for car in train.cars().iter() {
+                conn.execute(
+                    "INSERT INTO cars (train_id, number, type_loc, service_class, carrier, min_price)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        train_id,
+                        car.number(),
+                        car.type_loc(),
+                        car.service_class_code().as_str(),
+                        car.carrier(),
+                        car.min_price(),
+                    ],
+                )?;
+                let car_id = conn.last_insert_rowid();
+
+                for seats in car.seats().iter() {
+                    conn.execute(
+                        "INSERT INTO seats (train_id, car_id, seats_type, kind, free_seats, price)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            train_id,
+                            car_id,
+                            seats.seats_type(),
+                            seats.kind().to_string(),
+                            seats.free_seats_number(),
+                            parse_price(seats.price()),
+                        ],
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SqliteExport for TripStations {
+    fn insert_rows(&self, conn: &Connection) -> rusqlite::Result<()> {
+        for (i, stop) in self.stations().iter().enumerate() {
+            conn.execute(
+                "INSERT INTO stops (train_number, position, station, code, trip_days,
+                    arriving_time, leaving_time)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    self.train_number(),
+                    i as u32,
+                    stop.station(),
+                    stop.code().to_uint(),
+                    stop.trip_days(),
+                    stop.arriving_time().map(|t| t.to_string()),
+                    stop.leaving_time().map(|t| t.to_string()),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn iso_date(date: Option<TrainDate>) -> Option<String> {
+    date.map(|d| d.0.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export_to_sqlite;
+    use crate::fixtures::load_fixture;
+    use crate::{parse_schedule_reply, parse_train_reply, parse_trip_reply};
+    use rusqlite::Connection;
+
+    fn count(conn: &Connection, table: &str) -> u32 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn export_to_sqlite_test() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let routes = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&routes).unwrap().unwrap();
+        export_to_sqlite(&conn, &routes).unwrap();
+        assert_eq!(count(&conn, "routes"), 1);
+        assert_eq!(count(&conn, "trains"), 3);
+
+        let (number, date): (String, String) = conn
+            .query_row(
+                "SELECT number, leaving_date FROM trains ORDER BY id LIMIT 1",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((number.as_str(), date.as_str()), ("119А", "2022-04-01"));
+
+        let trains = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&trains).unwrap().unwrap();
+        export_to_sqlite(&conn, &trains).unwrap();
+        assert_eq!(count(&conn, "trains"), 4);
+        assert_eq!(count(&conn, "cars"), 5);
+
+        let stops = load_fixture("trip_info").unwrap();
+        let stops = parse_trip_reply(&stops).unwrap().unwrap();
+        export_to_sqlite(&conn, &stops).unwrap();
+        assert_eq!(count(&conn, "stops"), 2);
+    }
+}
@@ -1,17 +1,160 @@
-use reqwest::blocking::Response;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
-use url::form_urlencoded::byte_serialize;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::client::{RzdClientInterface, RzdQueryType, RzdRequestId};
-use crate::{error::Error, Result};
-use crate::{ResultList, RzdStationCode};
+use crate::client::{
+    request_url, RequestOptions, RzdClientInterface, RzdLayer, RzdQuery, RzdQueryType, RzdRequestId,
+};
+use crate::scheduler::Scheduler;
+use crate::{error::Error, Result, Url};
+use crate::{Lang, ResultList, RzdStationCode, StationCodeDirectory, StationName};
 
 const MIN_QUERY_LENGTH: usize = 2;
 
+// Limits of the batch resolution not to overload the server.
+const RESOLVE_THREADS: usize = 4;
+const RESOLVE_DELAY: Duration = Duration::from_millis(300);
+
+// The function taking the station name and the query.
+type MatchFn = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+/// How the station names returned by the server are matched with the query.
+/// Letter case, "Ё" and punctuation are ignored.
+#[derive(Clone, Default)]
+pub enum Matcher {
+    /// Any word of the name starts with the query, e.g. "ПЕТ" for "САНКТ-ПЕТЕРБУРГ".
+    #[default]
+    PrefixWords,
+    /// The name contains the query.
+    Substring,
+    /// The name equals the query.
+    Exact,
+    /// The function takes the name and the query.
+    Custom(Arc<MatchFn>),
+}
+
+impl Matcher {
+    /// Creates a matcher from the function taking the name and the query.
+    pub fn custom(f: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        Matcher::Custom(Arc::new(f))
+    }
+
+    /// Returns true if the station name matches the query.
+    pub fn matches(&self, name: &str, query: &str) -> bool {
+        let found = match self {
+            Matcher::PrefixWords => normalize_pair(name, query)
+                .is_some_and(|(n, q)| n.starts_with(&q) || n.contains(&format!(" {}", q))),
+            Matcher::Substring => normalize_pair(name, query).is_some_and(|(n, q)| n.contains(&q)),
+            Matcher::Exact => normalize_pair(name, query).is_some_and(|(n, q)| n == q),
+            Matcher::Custom(f) => f(name, query),
+        };
+        trace!("searching {} in {}...{}", query, name, found);
+        found
+    }
+}
+
+// Normalizes the name and the query, `None` if any of them is empty.
+fn normalize_pair(name: &str, query: &str) -> Option<(String, String)> {
+    let name = normalize_name(name);
+    let query = normalize_name(query);
+
+    match query.is_empty() || name.is_empty() {
+        true => None,
+        false => Some((name, query)),
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Matcher::PrefixWords => write!(f, "PrefixWords"),
+            Matcher::Substring => write!(f, "Substring"),
+            Matcher::Exact => write!(f, "Exact"),
+            Matcher::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Matcher::Custom(a), Matcher::Custom(b)) => Arc::ptr_eq(a, b),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+// Makes the name upper case, replaces "Ё" and turns punctuation into single spaces.
+fn normalize_name(name: &str) -> String {
+    let name: String = name
+        .to_uppercase()
+        .chars()
+        .map(|c| match c {
+            'Ё' => 'Е',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "StationCodeSearchDef")]
 /// Station code search by part of the name.
 pub struct StationCodeSearch {
     query: String,
+    #[serde(skip_serializing_if = "is_default_min_length")]
+    min_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    #[serde(skip_serializing_if = "is_zero")]
+    offset: usize,
+    #[serde(skip_serializing_if = "crate::is_default_lang")]
+    lang: Lang,
+    #[serde(skip)]
+    matcher: Matcher,
+}
+
+// Raw fields of the search checked by `StationCodeSearch::new` on deserialization.
+#[derive(Deserialize)]
+struct StationCodeSearchDef {
+    query: String,
+    #[serde(default = "default_min_length")]
+    min_length: usize,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default)]
+    lang: Lang,
+}
+
+impl TryFrom<StationCodeSearchDef> for StationCodeSearch {
+    type Error = Error;
+
+    fn try_from(d: StationCodeSearchDef) -> Result<Self> {
+        let mut search = StationCodeSearch::with_min_length(&d.query, d.min_length)?;
+        search.lang = d.lang;
+        search.offset = d.offset;
+        Ok(match d.limit {
+            Some(limit) => search.with_limit(limit),
+            None => search,
+        })
+    }
+}
+
+fn default_min_length() -> usize {
+    MIN_QUERY_LENGTH
+}
+
+fn is_default_min_length(min_length: &usize) -> bool {
+    *min_length == MIN_QUERY_LENGTH
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
 impl StationCodeSearch {
@@ -21,67 +164,290 @@ impl StationCodeSearch {
     ///
     /// The method fails if the query has less than 2 characters.
     pub fn new(query: &str) -> Result<Self> {
+        StationCodeSearch::with_min_length(query, MIN_QUERY_LENGTH)
+    }
+
+    /// Takes part of the station name in the language the names are returned in,
+    /// e.g. "MOS" for Moscow with `Lang::En`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the query has less than 2 characters.
+    pub fn new_with_lang(query: &str, lang: Lang) -> Result<Self> {
+        let mut search = StationCodeSearch::new(query)?;
+        search.lang = lang;
+        Ok(search)
+    }
+
+    /// Takes part of the station name and the minimum number of its characters,
+    /// e.g. 1 for the autocompletion from the first letter.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the query is empty or shorter than the minimum length.
+    pub fn with_min_length(query: &str, min_length: usize) -> Result<Self> {
         let query = query.trim().to_uppercase();
 
         if query.is_empty() {
             return Err(Error::TooShortQuery);
         }
-        if query.chars().count() < MIN_QUERY_LENGTH {
+        if query.chars().count() < min_length {
             return Err(Error::TooShortQuery);
         }
         debug!("query: {}", query);
 
-        Ok(StationCodeSearch { query })
+        Ok(StationCodeSearch {
+            query,
+            min_length,
+            limit: None,
+            offset: 0,
+            lang: Lang::default(),
+            matcher: Matcher::default(),
+        })
+    }
+
+    /// Sets the maximum number of the stations returned, it is passed to the suggester
+    /// to make the reply smaller. If the suggester returns more stations anyway,
+    /// the list is cut on the client side after matching,
+    /// then `StationSearchResult::has_more` tells if the list is cut.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the number of the stations skipped, e.g. to get the next page.
+    /// Like the limit, it is passed to the suggester and applied on the client side
+    /// if the suggester returns more stations than the limit,
+    /// the matched stations go first then.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets how the found station names are matched with the query.
+    pub fn with_matcher(mut self, matcher: Matcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    /// Returns the part of the station name being searched.
+    #[inline]
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Returns how the found station names are matched with the query.
+    #[inline]
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    /// Returns the language of the station names.
+    #[inline]
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    /// Returns the minimum number of characters of the query.
+    #[inline]
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    /// Returns the maximum number of the stations returned, see `with_limit`.
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Returns the number of the stations skipped.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the URL of the first request made for the search to inspect the encoded parameters.
+    /// Fails with `Error::ParseUrlError` if the search makes an invalid URL.
+    pub fn request_url(&self) -> Result<Url> {
+        request_url::<ResultList<StationItem>, _>(self)
     }
 }
 
+// The page of the matched stations only, the truncation is reported by `StationSearchResult`.
 impl RzdClientInterface<ResultList<StationItem>> for StationCodeSearch {
     fn query_type(&self) -> RzdQueryType {
         RzdQueryType::Simple
     }
 
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::Suggester
+    }
+
     fn request_id(&self) -> String {
         String::new()
     }
 
     fn request_data(&self, _id: RzdRequestId) -> String {
-        let query_encoded: String = byte_serialize(self.query.as_bytes()).collect();
-
-        format!(
-            "https://pass.rzd.ru/suggester\
-                ?stationNamePart={}\
-                &lang=ru\
-                &compactMode=y",
-            query_encoded
-        )
+        let mut query = RzdQuery::new(RzdLayer::Suggester)
+            .param("stationNamePart", &self.query)
+            .param("lang", self.lang)
+            .param("compactMode", "y");
+        if let Some(limit) = self.limit {
+            query = query.param("limit", limit);
+        }
+        if self.offset > 0 {
+            query = query.param("offset", self.offset);
+        }
+        query.to_url()
     }
 
-    fn deserialize_reply_id(&self, _response: Response) -> Result<Option<RzdRequestId>> {
+    fn deserialize_reply_id(&self, _body: &str) -> Result<Option<RzdRequestId>> {
         Err(Error::UnsupportedOperation)
     }
 
-    fn deserialize_reply_data(
-        &self,
-        response: Response,
-    ) -> Result<Option<ResultList<StationItem>>> {
-        let answer: AnswerList = match response.json() {
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<ResultList<StationItem>>> {
+        let stations = self.split_reply(body)?.matched;
+
+        if stations.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(stations))
+    }
+}
+
+impl RzdClientInterface<StationSearchResult> for StationCodeSearch {
+    fn query_type(&self) -> RzdQueryType {
+        RzdQueryType::Simple
+    }
+
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::Suggester
+    }
+
+    fn request_id(&self) -> String {
+        String::new()
+    }
+
+    fn request_data(&self, id: RzdRequestId) -> String {
+        RzdClientInterface::<ResultList<StationItem>>::request_data(self, id)
+    }
+
+    fn deserialize_reply_id(&self, _body: &str) -> Result<Option<RzdRequestId>> {
+        Err(Error::UnsupportedOperation)
+    }
+
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<StationSearchResult>> {
+        let result = self.split_reply(body)?;
+
+        if result.matched.is_empty() && result.others.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(result))
+    }
+}
+
+impl StationCodeSearch {
+    // Splits the stations of the reply into matched with the query and others.
+    fn split_reply(&self, body: &str) -> Result<StationSearchResult> {
+        let answer: AnswerList = match serde_json::from_str(body) {
             Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
             Ok(r) => r,
         };
-        debug!("answer: {}", answer);
+        trace!("answer: {}", answer);
 
-        let stations: Vec<StationItem> = answer
+        let (mut matched, mut others): (Vec<StationItem>, Vec<StationItem>) = answer
             .0
             .into_iter()
-            .filter(|s| is_first_letters_found(&s.name, &self.query))
-            .collect();
-        info!("{} stations found", stations.len());
+            .partition(|s| self.matcher.matches(&s.name, &self.query));
+        info!("{} stations found", matched.len());
 
-        if stations.len() == 0 {
-            return Ok(None);
+        let total = matched.len() + others.len();
+        // The reply within the limit is the page made by the suggester.
+        let paged = match self.limit {
+            Some(limit) => total <= limit,
+            None => true,
+        };
+        if paged {
+            return Ok(StationSearchResult {
+                matched: ResultList(matched),
+                others: ResultList(others),
+                offset: self.offset,
+                total: self.offset + total,
+            });
         }
 
-        Ok(Some(ResultList(stations)))
+        let skipped = self.offset.min(matched.len());
+        matched.drain(..skipped);
+        others.drain(..(self.offset - skipped).min(others.len()));
+
+        if let Some(limit) = self.limit {
+            matched.truncate(limit);
+            others.truncate(limit.saturating_sub(matched.len()));
+        }
+
+        Ok(StationSearchResult {
+            matched: ResultList(matched),
+            others: ResultList(others),
+            offset: self.offset,
+            total,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Stations returned by the server, matched with the query and others.
+pub struct StationSearchResult {
+    matched: ResultList<StationItem>,
+    others: ResultList<StationItem>,
+    offset: usize,
+    total: usize,
+}
+
+impl StationSearchResult {
+    /// Returns the stations matched with the query.
+    #[inline]
+    pub fn matched(&self) -> &ResultList<StationItem> {
+        &self.matched
+    }
+
+    /// Returns the stations returned by the server but not matched with the query.
+    #[inline]
+    pub fn others(&self) -> &ResultList<StationItem> {
+        &self.others
+    }
+
+    /// Returns the number of all stations returned by the server
+    /// together with the ones skipped by the offset.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the number of the stations skipped by the offset of the search.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns true if the list is cut by the limit on the client side
+    /// and there are more stations.
+    pub fn has_more(&self) -> bool {
+        self.offset + self.matched.as_ref().len() + self.others.as_ref().len() < self.total
+    }
+}
+
+impl fmt::Display for StationSearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.matched)?;
+        if !self.others.is_empty() {
+            write!(f, "Другие станции:\n{}", self.others)?;
+        }
+        if self.has_more() {
+            writeln!(f, "Показаны не все станции, всего {}", self.total)?;
+        }
+        Ok(())
     }
 }
 
@@ -90,12 +456,22 @@ impl RzdClientInterface<ResultList<StationItem>> for StationCodeSearch {
 pub struct StationItem {
     name: String,
     code: RzdStationCode,
+    kind: Option<u32>,
+    level: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) coordinates: Option<Coordinates>,
 }
 
 impl StationItem {
     /// Takes a name and a code of the station and creates a new item.
-    fn new(name: String, code: RzdStationCode) -> Self {
-        StationItem { name, code }
+    pub(crate) fn new(name: String, code: RzdStationCode) -> Self {
+        StationItem {
+            name,
+            code,
+            kind: None,
+            level: None,
+            coordinates: None,
+        }
     }
 
     /// Returns the name of the station.
@@ -104,11 +480,99 @@ impl StationItem {
         &self.name
     }
 
+    /// Returns the title-cased name of the station with the terminal split off.
+    pub fn display_name(&self) -> StationName {
+        StationName::parse(&self.name)
+    }
+
     /// Returns the code of the station.
     #[inline]
     pub fn code(&self) -> RzdStationCode {
         self.code
     }
+
+    /// Returns the kind of the station as the suggester reports it, the `S` attribute.
+    #[inline]
+    pub fn kind(&self) -> Option<u32> {
+        self.kind
+    }
+
+    /// Returns the level of the station as the suggester reports it, the `L` attribute.
+    #[inline]
+    pub fn level(&self) -> Option<u32> {
+        self.level
+    }
+
+    /// Returns the location of the station, if it is set by `with_coordinates`
+    /// or by `enrich_coordinates` of the `geo` feature.
+    #[inline]
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        self.coordinates
+    }
+
+    /// Sets the location of the station.
+    pub fn with_coordinates(mut self, coordinates: Coordinates) -> Self {
+        self.coordinates = Some(coordinates);
+        self
+    }
+
+    /// Returns the distance from the station to the point in kilometres,
+    /// if the location of the station is known.
+    pub fn distance_km(&self, point: Coordinates) -> Option<f64> {
+        self.coordinates.map(|c| c.distance_km(point))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+/// Latitude and longitude of a point in degrees.
+pub struct Coordinates {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl Coordinates {
+    /// Creates `Coordinates` from the latitude and the longitude in degrees.
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Coordinates {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Returns the latitude in degrees.
+    #[inline]
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Returns the longitude in degrees.
+    #[inline]
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Returns the great-circle distance to the point in kilometres.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::Coordinates;
+    /// #
+    /// let moscow = Coordinates::new(55.7558, 37.6173);
+    /// let spb = Coordinates::new(59.9386, 30.3141);
+    ///
+    /// assert_eq!(moscow.distance_km(spb).round(), 634.0);
+    /// ```
+    pub fn distance_km(&self, other: Coordinates) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
 }
 
 impl fmt::Display for StationItem {
@@ -117,6 +581,176 @@ impl fmt::Display for StationItem {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Stations of one city, e.g. the terminals of Moscow, found by the station directory.
+pub struct StationGroup {
+    city: StationItem,
+    stations: Vec<StationItem>,
+}
+
+impl StationGroup {
+    /// Returns the city, its first terminal if the city is not in the list,
+    /// or the station if it does not belong to any city of the directory.
+    #[inline]
+    pub fn city(&self) -> &StationItem {
+        &self.city
+    }
+
+    /// Returns the stations of the city except the city itself.
+    #[inline]
+    pub fn stations(&self) -> &[StationItem] {
+        &self.stations
+    }
+}
+
+impl fmt::Display for StationGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.city)?;
+        for s in &self.stations {
+            write!(f, "\n\t{}", s)?;
+        }
+        Ok(())
+    }
+}
+
+impl ResultList<StationItem> {
+    /// Groups the stations by the cities of the directory, e.g. the terminals of Moscow.
+    /// The city of the group is the city station if it is in the list,
+    /// otherwise its first terminal found.
+    /// The stations unknown to the directory make groups of their own.
+    pub fn group_by_city(&self, directory: &StationCodeDirectory) -> ResultList<StationGroup> {
+        let mut groups: Vec<(RzdStationCode, StationGroup)> = vec![];
+
+        for item in self.iter() {
+            let city = directory.city(item.code).unwrap_or(item.code);
+            match groups.iter_mut().find(|(c, _)| *c == city) {
+                Some((_, g)) if item.code == city => {
+                    let terminal = std::mem::replace(&mut g.city, item.clone());
+                    g.stations.insert(0, terminal);
+                }
+                Some((_, g)) => g.stations.push(item.clone()),
+                None => groups.push((
+                    city,
+                    StationGroup {
+                        city: item.clone(),
+                        stations: vec![],
+                    },
+                )),
+            }
+        }
+
+        ResultList(groups.into_iter().map(|(_, g)| g).collect())
+    }
+
+    /// Returns the cities and the stations not belonging to any city of the directory,
+    /// leaving one item per city.
+    pub fn dedupe_cities(&self, directory: &StationCodeDirectory) -> ResultList<StationItem> {
+        ResultList(
+            self.group_by_city(directory)
+                .into_iter()
+                .map(|g| g.city)
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The station chosen by the name and other stations found.
+pub struct ResolvedStation {
+    best: StationItem,
+    alternatives: Vec<StationItem>,
+    exact: bool,
+}
+
+impl ResolvedStation {
+    // Prefers the station with the same name, then the first city code found.
+    fn from_list(name: &str, list: &ResultList<StationItem>) -> Option<Self> {
+        let name = name.trim().to_uppercase();
+
+        let (best, exact) = match list.iter().find(|s| s.name == name) {
+            Some(s) => (s.clone(), true),
+            None => match list.iter().find(|s| s.code.is_city_aggregate()) {
+                Some(s) => (s.clone(), false),
+                None => (list.iter().next()?.clone(), false),
+            },
+        };
+
+        let alternatives = list.iter().filter(|s| **s != best).cloned().collect();
+
+        Some(ResolvedStation {
+            best,
+            alternatives,
+            exact,
+        })
+    }
+
+    /// Returns the station best matching the name.
+    #[inline]
+    pub fn best(&self) -> &StationItem {
+        &self.best
+    }
+
+    /// Returns the other stations found by the name.
+    #[inline]
+    pub fn alternatives(&self) -> &[StationItem] {
+        &self.alternatives
+    }
+
+    /// Returns true if there is no station with the same name
+    /// and the best one is chosen among several.
+    #[inline]
+    pub fn is_ambiguous(&self) -> bool {
+        !self.exact && !self.alternatives.is_empty()
+    }
+}
+
+impl fmt::Display for ResolvedStation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.best)?;
+        if self.is_ambiguous() {
+            write!(f, " (найдено ещё {})", self.alternatives.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the stations by the names, e.g. of the itinerary, making several requests at once.
+/// Returns the result of the search for every name.
+pub fn resolve_stations(names: &[&str]) -> HashMap<String, Result<Option<ResolvedStation>>> {
+    resolve_stations_with(names, &RequestOptions::default())
+}
+
+/// Does the same as `resolve_stations` making the requests with the options,
+/// the pauses between the requests of all the threads are made by the sleeper of the options.
+pub fn resolve_stations_with(
+    names: &[&str],
+    options: &RequestOptions,
+) -> HashMap<String, Result<Option<ResolvedStation>>> {
+    let scheduler = Scheduler::new(RESOLVE_THREADS, names.len(), RESOLVE_DELAY, options.clone());
+    let replies: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let reply = StationCodeSearch::new(name)
+                .map(|q| scheduler.enqueue::<ResultList<StationItem>, _>(q, 0));
+            (*name, reply)
+        })
+        .collect();
+
+    replies
+        .into_iter()
+        .map(|(name, reply)| {
+            let result = reply
+                .and_then(|rx| {
+                    // The reply is lost if the worker panicked.
+                    rx.recv()
+                        .unwrap_or_else(|_| Err(Error::SearchAborted(name.to_string())))
+                })
+                .map(|list| list.and_then(|l| ResolvedStation::from_list(name, &l)));
+            (name.to_string(), result)
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 struct AnswerList(Vec<StationItem>);
 
@@ -148,13 +782,25 @@ mod de {
                 #[serde(alias = "c")]
                 #[serde(default)]
                 code: RzdStationCode,
+
+                #[serde(alias = "S")]
+                #[serde(default)]
+                kind: Option<u32>,
+
+                #[serde(alias = "L")]
+                #[serde(default)]
+                level: Option<u32>,
             }
 
             let input = Vec::<RzdStation>::deserialize(deserializer)?;
 
             let stations: Vec<StationItem> = input
                 .into_iter()
-                .map(|s| StationItem::new(s.name, s.code))
+                .map(|s| StationItem {
+                    kind: s.kind,
+                    level: s.level,
+                    ..StationItem::new(s.name, s.code)
+                })
                 .collect();
 
             Ok(AnswerList(stations))
@@ -162,39 +808,17 @@ mod de {
     }
 }
 
-// The RZD server searches only by two letters of the query,
-// so it needs to analyze the whole request.
-fn is_first_letters_found(text: &str, letters: &str) -> bool {
-    let text = text.trim().to_uppercase();
-    let letters = letters.trim().to_uppercase();
-
-    if letters.is_empty() | text.is_empty() {
-        return false;
-    }
-
-    for word in text.split_whitespace() {
-        if word.starts_with(&letters) {
-            trace!("searching {} in {}...ok", letters, text);
-            return true;
-        }
-    }
-
-    for word in text.split_terminator('-') {
-        if word.starts_with(&letters) {
-            trace!("searching {} in {}...ok", letters, text);
-            return true;
-        }
-    }
-
-    trace!("searching {} in {}...nok", letters, text);
-    false
-}
-
 #[cfg(test)]
 mod tests {
-    use super::is_first_letters_found;
-    use super::{AnswerList, StationCodeSearch, StationItem};
-    use crate::RzdStationCode;
+    use super::{
+        resolve_stations_with, AnswerList, Matcher, ResolvedStation, StationCodeSearch,
+        StationItem, StationSearchResult, RESOLVE_DELAY,
+    };
+    use crate::client::{RzdClientInterface, RzdRequestId};
+    use crate::clock::MockClock;
+    use crate::fixtures::serve;
+    use crate::{Lang, ResultList, RzdStationCode, StationCodeDirectory};
+    use std::sync::Arc;
 
     #[test]
     fn search_test() {
@@ -202,17 +826,63 @@ mod tests {
         assert!(StationCodeSearch::new(" ").is_err());
         assert!(StationCodeSearch::new("м").is_err());
         assert!(!StationCodeSearch::new("мОс").is_err());
+
+        let q = StationCodeSearch::new(" мОс ").unwrap();
+        assert_eq!(q.query(), "МОС");
+        assert_eq!(q.clone(), q);
     }
 
     #[test]
-    fn is_first_letters_found_test() {
-        assert!(!is_first_letters_found("", ""));
-        assert!(!is_first_letters_found("", "МОС"));
-        assert!(!is_first_letters_found("МОСКОВСКАЯ", ""));
-        assert!(!is_first_letters_found("МОСКОВСКАЯ", " "));
-        assert!(is_first_letters_found("МОСКОВСКАЯ", "мОс"));
-        assert!(is_first_letters_found("ВОЕННЫЙ ГОРОДОК", "гоР"));
-        assert!(is_first_letters_found("САНКТ-ПЕТЕРБУРГ-ГЛАВН", "пет"));
+    fn request_url_test() {
+        let q = StationCodeSearch::new("санкт-петербург главн.").unwrap();
+        let url = q.request_url().unwrap();
+
+        assert_eq!(url.path(), "/suggester");
+        assert!(url
+            .query_pairs()
+            .any(|(n, v)| n == "stationNamePart" && v == "САНКТ-ПЕТЕРБУРГ ГЛАВН."));
+        assert!(!url.as_str().contains(' '));
+    }
+
+    #[test]
+    fn search_serde_test() {
+        let q = StationCodeSearch::new("мос").unwrap();
+        let json = serde_json::to_string(&q).unwrap();
+
+        assert_eq!(json, r#"{"query":"МОС"}"#);
+        assert_eq!(serde_json::from_str::<StationCodeSearch>(&json).unwrap(), q);
+        assert!(serde_json::from_str::<StationCodeSearch>(r#"{"query":"м"}"#).is_err());
+    }
+
+    #[test]
+    fn matcher_test() {
+        let m = Matcher::PrefixWords;
+        assert!(!m.matches("", ""));
+        assert!(!m.matches("", "МОС"));
+        assert!(!m.matches("МОСКОВСКАЯ", ""));
+        assert!(!m.matches("МОСКОВСКАЯ", " "));
+        assert!(m.matches("МОСКОВСКАЯ", "мОс"));
+        assert!(m.matches("ВОЕННЫЙ ГОРОДОК", "гоР"));
+        assert!(m.matches("САНКТ-ПЕТЕРБУРГ-ГЛАВН", "пет"));
+        assert!(m.matches("МОСКВА (ВСЕ ВОКЗАЛЫ)", "все"));
+        assert!(m.matches("С.ПЕТЕРБУРГ", "пет"));
+        assert!(m.matches("ОРЁЛ", "орел"));
+        assert!(m.matches("МОСКВА ОКТЯБРЬСКАЯ", "москва окт"));
+        assert!(!m.matches("МОСКВА ОКТЯБРЬСКАЯ", "ТЯБР"));
+
+        assert!(Matcher::Substring.matches("МОСКВА ОКТЯБРЬСКАЯ", "ТЯБР"));
+        assert!(Matcher::Exact.matches("Тверь", "ТВЕРЬ"));
+        assert!(!Matcher::Exact.matches("ТВЕРЬ", "ТВЕР"));
+
+        let m = Matcher::custom(|name, _| name.ends_with("АЯ"));
+        assert!(m.matches("МОСКОВСКАЯ", "Х"));
+        assert_eq!(m.clone(), m);
+        assert_ne!(m, Matcher::PrefixWords);
+
+        let q = StationCodeSearch::new("тяб")
+            .unwrap()
+            .with_matcher(Matcher::Substring);
+        assert_eq!(q.matcher(), &Matcher::Substring);
     }
 
     #[test]
@@ -225,12 +895,27 @@ mod tests {
         assert_eq!(&answer.0, &data.0);
 
         let data = AnswerList(vec![
-            StationItem::new(String::from("ВОЕННОЕ ШОССЕ"), RzdStationCode::new(2034058)),
-            StationItem::new(
-                String::from("БУРЛИТ-ВОЛОЧАЕВСКИЙ"),
-                RzdStationCode::new(2034458),
-            ),
-            StationItem::new(String::from("ВОРОПАЕВО"), RzdStationCode::new(2100047)),
+            StationItem {
+                name: String::from("ВОЕННОЕ ШОССЕ"),
+                code: RzdStationCode::new(2034058),
+                kind: Some(4),
+                level: Some(0),
+                coordinates: None,
+            },
+            StationItem {
+                name: String::from("БУРЛИТ-ВОЛОЧАЕВСКИЙ"),
+                code: RzdStationCode::new(2034458),
+                kind: Some(0),
+                level: Some(2),
+                coordinates: None,
+            },
+            StationItem {
+                name: String::from("ВОРОПАЕВО"),
+                code: RzdStationCode::new(2100047),
+                kind: Some(0),
+                level: Some(4),
+                coordinates: None,
+            },
         ]);
 
         let answer = r#"[{"n":"ВОЕННОЕ ШОССЕ","c":2034058,"S":4,"L":0},{"n":"БУРЛИТ-ВОЛОЧАЕВСКИЙ","c":2034458,"S":0,"L":2},{"n":"ВОРОПАЕВО","c":2100047,"S":0,"L":4}]"#;
@@ -238,4 +923,180 @@ mod tests {
 
         assert_eq!(&answer.0, &data.0);
     }
+
+    #[test]
+    fn group_by_city_test() {
+        let mut directory = StationCodeDirectory::new();
+        directory.insert_terminal(2000000, 2006004);
+        directory.insert_terminal(2000000, 2000003);
+
+        let list = ResultList::new(vec![
+            StationItem::new(
+                String::from("МОСКВА ОКТЯБРЬСКАЯ"),
+                RzdStationCode::new(2006004),
+            ),
+            StationItem::new(String::from("МОСКВА"), RzdStationCode::new(2000000)),
+            StationItem::new(
+                String::from("МОСКВА (ВСЕ ВОКЗАЛЫ)"),
+                RzdStationCode::new(2000003),
+            ),
+            StationItem::new(String::from("МОСКОВКА"), RzdStationCode::new(2040350)),
+        ]);
+
+        let groups = list.group_by_city(&directory);
+        let groups = groups.as_ref();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].city().name(), "МОСКВА");
+        assert_eq!(groups[0].stations().len(), 2);
+        assert_eq!(groups[0].stations()[0].name(), "МОСКВА ОКТЯБРЬСКАЯ");
+        assert_eq!(groups[1].city().name(), "МОСКОВКА");
+        assert!(groups[1].stations().is_empty());
+
+        let cities = list.dedupe_cities(&directory);
+        let names: Vec<&str> = cities.iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["МОСКВА", "МОСКОВКА"]);
+
+        // The terminal stands for the city missing in the list.
+        let terminals = ResultList::new(list.iter().skip(2).cloned().collect());
+        let groups = terminals.group_by_city(&directory);
+        assert_eq!(groups.as_ref()[0].city().name(), "МОСКВА (ВСЕ ВОКЗАЛЫ)");
+        assert!(groups.as_ref()[0].stations().is_empty());
+
+        // Without the directory every station is a group of its own.
+        assert_eq!(
+            list.dedupe_cities(&StationCodeDirectory::new())
+                .as_ref()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn resolved_station_test() {
+        let list = ResultList::new(vec![
+            StationItem::new(String::from("ТВЕРЬ"), RzdStationCode::new(2004600)),
+            StationItem::new(String::from("ТВЕРСКАЯ"), RzdStationCode::new(2004601)),
+        ]);
+
+        let r = ResolvedStation::from_list(" тверь", &list).unwrap();
+        assert_eq!(r.best().code(), RzdStationCode::new(2004600));
+        assert_eq!(r.alternatives().len(), 1);
+        assert!(!r.is_ambiguous());
+
+        let r = ResolvedStation::from_list("ТВЕР", &list).unwrap();
+        assert_eq!(r.best().name(), "ТВЕРЬ");
+        assert!(r.is_ambiguous());
+
+        assert!(ResolvedStation::from_list("ТВЕР", &ResultList::new(vec![])).is_none());
+    }
+
+    #[test]
+    fn resolve_stations_test() {
+        let clock = Arc::new(MockClock::default());
+        let options = serve(|_| r#"[{"n":"ТВЕРЬ","c":2004600,"S":0,"L":0}]"#.to_string())
+            .with_clock(clock.clone())
+            .with_sleeper(clock.clone());
+
+        let resolved = resolve_stations_with(&["тверь", "тверь ", "м"], &options);
+        assert_eq!(resolved.len(), 3);
+        let r = resolved["тверь"].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(r.best().code(), RzdStationCode::new(2004600));
+        assert!(resolved["м"].is_err());
+        assert_eq!(clock.pauses(), vec![RESOLVE_DELAY]);
+    }
+
+    #[test]
+    fn search_result_test() {
+        let answer = r#"[{"n":"МОСКВА","c":2000000},{"n":"МОСКВА ОКТЯБРЬСКАЯ","c":2006004}]"#;
+        let q = StationCodeSearch::new("октябр").unwrap();
+
+        let list: Option<ResultList<StationItem>> = q.deserialize_reply_data(answer).unwrap();
+        assert_eq!(list.unwrap().as_ref().len(), 1);
+
+        let q = StationCodeSearch::new("тябр").unwrap();
+        let list: Option<ResultList<StationItem>> = q.deserialize_reply_data(answer).unwrap();
+        assert!(list.is_none());
+
+        let result: Option<StationSearchResult> = q.deserialize_reply_data(answer).unwrap();
+        let result = result.unwrap();
+        assert!(result.matched().is_empty());
+        assert_eq!(result.others().as_ref().len(), 2);
+    }
+
+    #[test]
+    fn min_length_and_limit_test() {
+        assert!(StationCodeSearch::with_min_length("м", 1).is_ok());
+        assert!(StationCodeSearch::with_min_length(" ", 0).is_err());
+        assert!(StationCodeSearch::with_min_length("мос", 4).is_err());
+
+        let q = StationCodeSearch::with_min_length("м", 1)
+            .unwrap()
+            .with_limit(1);
+        assert_eq!(q.min_length(), 1);
+        assert_eq!(q.limit(), Some(1));
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(json, r#"{"query":"М","min_length":1,"limit":1}"#);
+        assert_eq!(serde_json::from_str::<StationCodeSearch>(&json).unwrap(), q);
+
+        let answer = r#"[{"n":"МОСКВА","c":2000000},{"n":"МОСКВА ОКТЯБРЬСКАЯ","c":2006004}]"#;
+        let list: Option<ResultList<StationItem>> = q.deserialize_reply_data(answer).unwrap();
+        assert_eq!(list.unwrap().as_ref().len(), 1);
+    }
+
+    #[test]
+    fn offset_test() {
+        let answer = r#"[{"n":"МОСКВА","c":2000000},{"n":"МОСКВА ОКТЯБРЬСКАЯ","c":2006004},{"n":"ДОМОДЕДОВО","c":2000060}]"#;
+        let q = StationCodeSearch::new("моск").unwrap().with_limit(1);
+
+        let result: StationSearchResult = q.deserialize_reply_data(answer).unwrap().unwrap();
+        assert_eq!(result.matched().as_ref()[0].code().to_uint(), 2000000);
+        assert_eq!(result.total(), 3);
+        assert_eq!(result.offset(), 0);
+        assert!(result.has_more());
+        assert!(result
+            .to_string()
+            .ends_with("Показаны не все станции, всего 3\n"));
+
+        let q = q.with_offset(2).with_limit(2);
+        let result: StationSearchResult = q.deserialize_reply_data(answer).unwrap().unwrap();
+        assert!(result.matched().is_empty());
+        assert_eq!(result.others().as_ref()[0].code().to_uint(), 2000060);
+        assert_eq!(result.offset(), 2);
+        assert!(!result.has_more());
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(json, r#"{"query":"МОСК","limit":2,"offset":2}"#);
+        assert_eq!(serde_json::from_str::<StationCodeSearch>(&json).unwrap(), q);
+
+        // The suggester returned the page itself.
+        let url = q.request_url().unwrap();
+        assert!(url.as_str().ends_with("&compactMode=y&limit=2&offset=2"));
+        let answer = r#"[{"n":"МОСКВА КУРСКАЯ","c":2000001},{"n":"ДОМОДЕДОВО","c":2000060}]"#;
+        let result: StationSearchResult = q.deserialize_reply_data(answer).unwrap().unwrap();
+        assert_eq!(result.matched().as_ref()[0].code().to_uint(), 2000001);
+        assert_eq!(result.others().as_ref()[0].code().to_uint(), 2000060);
+        assert_eq!(result.total(), 4);
+        assert!(!result.has_more());
+    }
+
+    #[test]
+    fn lang_test() {
+        let q = StationCodeSearch::new_with_lang("mos", Lang::En).unwrap();
+        assert_eq!(q.lang(), Lang::En);
+        assert!(RzdClientInterface::<ResultList<StationItem>>::request_data(
+            &q,
+            RzdRequestId::default()
+        )
+        .contains("lang=en"));
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(json, r#"{"query":"MOS","lang":"En"}"#);
+        assert_eq!(serde_json::from_str::<StationCodeSearch>(&json).unwrap(), q);
+
+        let answer = r#"[{"n":"MOSKVA","c":2000000},{"n":"MOSKVA OKTYABRSKAYA","c":2006004},{"n":"SMOSK","c":2000001}]"#;
+        let list: Option<ResultList<StationItem>> = q.deserialize_reply_data(answer).unwrap();
+        assert_eq!(list.unwrap().as_ref().len(), 2);
+    }
 }
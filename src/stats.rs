@@ -0,0 +1,163 @@
+//! Performance snapshots of the reply parsers.
+
+#[cfg(feature = "allocations")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "allocations")]
+use std::cell::Cell;
+#[cfg(feature = "allocations")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::client::RzdLayer;
+use crate::error::Error;
+use crate::{parse_schedule_reply, parse_train_reply, parse_trip_reply, Result};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Time and allocations spent on parsing of one reply.
+pub struct ParseStats {
+    layer: RzdLayer,
+    bytes: usize,
+    elapsed: Duration,
+    allocations: Option<u64>,
+}
+
+impl ParseStats {
+    /// Returns the layer the reply is of.
+    #[inline]
+    pub fn layer(&self) -> RzdLayer {
+        self.layer
+    }
+
+    /// Returns the size of the reply in bytes.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Returns the time spent on parsing.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the number of the memory blocks allocated while parsing,
+    /// `None` unless `CountingAllocator` of the `allocations` feature is the global allocator.
+    #[inline]
+    pub fn allocations(&self) -> Option<u64> {
+        self.allocations
+    }
+}
+
+#[cfg(feature = "allocations")]
+thread_local! {
+    // Allocations of the current thread, so the other threads are not counted.
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Set by the first allocation of `CountingAllocator`, i.e. if it is installed.
+#[cfg(feature = "allocations")]
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+/// Global allocator of the system counting the allocated blocks for `parse_stats`,
+/// the grown ones are not new allocations. It is installed by the binary:
+///
+/// ```rust,no_run
+/// use rzd_trains::{parse_stats, CountingAllocator, RzdLayer};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+///
+/// let body = std::fs::read_to_string("trip_info.json").unwrap();
+/// let stats = parse_stats(RzdLayer::TripStops, &body).unwrap();
+/// println!("allocations: {:?}", stats.allocations());
+/// ```
+#[cfg(feature = "allocations")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CountingAllocator;
+
+#[cfg(feature = "allocations")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        COUNTING.store(true, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+// Returns the number of the allocations made on the current thread so far,
+// `None` if they are not counted.
+#[cfg(feature = "allocations")]
+fn allocations() -> Option<u64> {
+    match COUNTING.load(Ordering::Relaxed) {
+        true => ALLOCATIONS.try_with(Cell::get).ok(),
+        false => None,
+    }
+}
+
+#[cfg(not(feature = "allocations"))]
+fn allocations() -> Option<u64> {
+    None
+}
+
+/// Parses the raw reply of the layer, e.g. a fixture, and measures the time
+/// and the allocations, if they are counted.
+///
+/// # Errors
+///
+/// The method fails if the reply couldn't be parsed
+/// or the layer is not a timetable one, e.g. the suggester.
+pub fn parse_stats(layer: RzdLayer, body: &str) -> Result<ParseStats> {
+    let allocated = allocations();
+    let started = Instant::now();
+
+    match layer {
+        RzdLayer::TrainSchedule => parse_schedule_reply(body).map(drop),
+        RzdLayer::TrainInfo => parse_train_reply(body).map(drop),
+        RzdLayer::TripStops => parse_trip_reply(body).map(drop),
+        _ => Err(Error::UnsupportedOperation),
+    }?;
+
+    let elapsed = started.elapsed();
+    let allocations = allocated.zip(allocations()).map(|(a, b)| b - a);
+    debug!(
+        "{:?} parsed in {:?}, allocations: {:?}",
+        layer, elapsed, allocations
+    );
+
+    Ok(ParseStats {
+        layer,
+        bytes: body.len(),
+        elapsed,
+        allocations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_stats;
+    use crate::fixtures::load_fixture;
+    use crate::RzdLayer;
+
+    #[cfg(feature = "allocations")]
+    #[global_allocator]
+    static ALLOCATOR: super::CountingAllocator = super::CountingAllocator;
+
+    #[test]
+    fn parse_stats_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let stats = parse_stats(RzdLayer::TrainSchedule, &answer).unwrap();
+        assert_eq!(stats.layer(), RzdLayer::TrainSchedule);
+        assert_eq!(stats.bytes(), answer.len());
+        match cfg!(feature = "allocations") {
+            true => assert!(stats.allocations().unwrap() > 0),
+            false => assert_eq!(stats.allocations(), None),
+        }
+
+        assert!(parse_stats(RzdLayer::TrainInfo, "not a json").is_err());
+        assert!(parse_stats(RzdLayer::Suggester, "[]").is_err());
+    }
+}
@@ -1,12 +1,21 @@
-use reqwest::blocking::Response;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::fmt;
-use url::form_urlencoded::byte_serialize;
-
-use crate::client::{RzdClientInterface, RzdQueryType, RzdRequestId};
-use crate::{error::Error, Result};
-use crate::{ReplyResult, ResultList, RouteDirection, RzdStationCode, TrainDate, TrainTime};
-
+use std::sync::Arc;
+
+use crate::client::{
+    booking_url, request_url, RzdClient, RzdClientInterface, RzdLayer, RzdQuery, RzdQueryType,
+    RzdRequestId, SessionToken,
+};
+use crate::des::parse_price;
+use crate::{error::Error, non_empty, Result, Url};
+use crate::{
+    plural, Lang, ReplyResult, ResultList, RouteDirection, RzdStationCode, SeatKind, ServiceClass,
+    TrainCategory, TrainDate, TrainNumber, TrainTime, Warnings,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TrainSearchDef")]
 /// Train info search.
 pub struct TrainSearch {
     leaving_code: RzdStationCode,
@@ -14,37 +23,131 @@ pub struct TrainSearch {
     leaving_time: TrainTime,
     arriving_code: RzdStationCode,
     train_number: String,
+    #[serde(skip_serializing_if = "crate::is_default_lang")]
+    lang: Lang,
+}
+
+// Raw fields of the search checked by `TrainSearch::new` on deserialization.
+#[derive(Deserialize)]
+struct TrainSearchDef {
+    leaving_code: RzdStationCode,
+    leaving_date: TrainDate,
+    leaving_time: TrainTime,
+    arriving_code: RzdStationCode,
+    train_number: String,
+    #[serde(default)]
+    lang: Lang,
+}
+
+impl TryFrom<TrainSearchDef> for TrainSearch {
+    type Error = Error;
+
+    fn try_from(d: TrainSearchDef) -> Result<Self> {
+        let search = TrainSearch::new(
+            d.leaving_code,
+            d.arriving_code,
+            d.leaving_date,
+            d.leaving_time,
+            &d.train_number,
+        )?;
+        Ok(search.with_lang(d.lang))
+    }
 }
 
 impl TrainSearch {
     /// Takes departure and arrival station codes, departure and arrival date,
-    /// departure time and train number.
+    /// departure time and train number. The number is kept as it is given, the letters
+    /// are changed to upper case Cyrillic ones. If the numbers of both directions are known,
+    /// e.g. "105Ж/106Я", pass the one of the direction, see [`TrainNumber::variants`].
+    ///
+    /// The codes of the stations are expected, not of the cities,
+    /// see [`RzdStationCode::is_city_aggregate`] and [`RzdStationCode::expand`].
     ///
     /// # Errors
     ///
-    /// The method fails if the train number is empty.
+    /// The method fails if the train number is empty or the stations are the same or not set.
     pub fn new(
-        leaving_code: RzdStationCode,
-        arriving_code: RzdStationCode,
-        leaving_date: TrainDate,
-        leaving_time: TrainTime,
+        leaving_code: impl Into<RzdStationCode>,
+        arriving_code: impl Into<RzdStationCode>,
+        leaving_date: impl Into<TrainDate>,
+        leaving_time: impl Into<TrainTime>,
         train_number: &str,
     ) -> Result<Self> {
-        let train_number = train_number.trim().to_uppercase();
+        let leaving_code = leaving_code.into();
+        let arriving_code = arriving_code.into();
+        RzdStationCode::check_route(leaving_code, arriving_code)?;
 
-        if train_number.is_empty() {
-            return Err(Error::EmptyTrainNumber);
-        }
+        let train_number = TrainNumber::parse(train_number)?.to_string();
         debug!("query: {}", train_number);
 
+        for code in [leaving_code, arriving_code] {
+            if code.is_city_aggregate() {
+                warn!("the city code {} is used instead of the station one", code);
+            }
+        }
+
         Ok(TrainSearch {
             leaving_code,
-            leaving_date,
-            leaving_time,
+            leaving_date: leaving_date.into(),
+            leaving_time: leaving_time.into(),
             arriving_code,
             train_number,
+            lang: Lang::default(),
         })
     }
+
+    /// Returns the builder of the search, e.g. when the departure time is unknown.
+    pub fn builder() -> TrainSearchBuilder {
+        TrainSearchBuilder::default()
+    }
+
+    /// Sets the language of the names returned by the server, e.g. `Lang::En` for Latin names.
+    pub fn with_lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Returns the language of the names returned by the server.
+    #[inline]
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    /// Returns the RZD code of the departure station.
+    #[inline]
+    pub fn leaving_code(&self) -> RzdStationCode {
+        self.leaving_code
+    }
+
+    /// Returns the RZD code of the arrival station.
+    #[inline]
+    pub fn arriving_code(&self) -> RzdStationCode {
+        self.arriving_code
+    }
+
+    /// Returns the departure date.
+    #[inline]
+    pub fn leaving_date(&self) -> TrainDate {
+        self.leaving_date
+    }
+
+    /// Returns the departure time.
+    #[inline]
+    pub fn leaving_time(&self) -> TrainTime {
+        self.leaving_time
+    }
+
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the URL of the first request made for the search to inspect the encoded parameters.
+    /// Fails with `Error::ParseUrlError` if the search makes an invalid URL.
+    pub fn request_url(&self) -> Result<Url> {
+        request_url::<ResultList<TrainItem>, _>(self)
+    }
 }
 
 impl RzdClientInterface<ResultList<TrainItem>> for TrainSearch {
@@ -52,38 +155,31 @@ impl RzdClientInterface<ResultList<TrainItem>> for TrainSearch {
         RzdQueryType::WithId
     }
 
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::TrainInfo
+    }
+
     fn request_id(&self) -> String {
-        let train_encoded: String = byte_serialize(self.train_number.as_bytes()).collect();
-
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5764\
-            &dir={}\
-            &code0={}\
-            &dt0={}\
-            &time0={}\
-            &code1={}\
-            &tnum0={}",
-            RouteDirection::OneWay as u8,
-            self.leaving_code,
-            self.leaving_date,
-            self.leaving_time,
-            self.arriving_code,
-            train_encoded
-        )
+        RzdQuery::new(RzdLayer::TrainInfo)
+            .lang(self.lang)
+            .param("dir", RouteDirection::OneWay as u8)
+            .param("code0", self.leaving_code)
+            .param("dt0", self.leaving_date)
+            .param("time0", self.leaving_time)
+            .param("code1", self.arriving_code)
+            .param("tnum0", &self.train_number)
+            .to_url()
     }
 
     fn request_data(&self, id: RzdRequestId) -> String {
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5764\
-            &rid={}",
-            id
-        )
+        RzdQuery::new(RzdLayer::TrainInfo)
+            .lang(self.lang)
+            .param("rid", id)
+            .to_url()
     }
 
-    fn deserialize_reply_id(&self, response: Response) -> Result<Option<RzdRequestId>> {
-        let reply: RidReply = match response.json() {
+    fn deserialize_reply_id(&self, body: &str) -> Result<Option<RzdRequestId>> {
+        let reply: RidReply = match serde_json::from_str(body) {
             Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
             Ok(r) => r,
         };
@@ -97,38 +193,250 @@ impl RzdClientInterface<ResultList<TrainItem>> for TrainSearch {
         Ok(Some(reply.value))
     }
 
-    fn deserialize_reply_data(&self, response: Response) -> Result<Option<ResultList<TrainItem>>> {
-        let reply: TrainReply = match response.json() {
-            Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
-            Ok(r) => r,
-        };
-        let reply = reply.0;
-        trace!("reply: {:?}", reply);
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<ResultList<TrainItem>>> {
+        parse_train_reply(body)
+    }
 
-        if !reply.success {
-            return Err(reply.error);
-        }
+    fn validate(&self) -> Result<()> {
+        RzdStationCode::check_route(self.leaving_code, self.arriving_code)
+    }
+}
 
-        if reply.value.is_empty() {
-            return Ok(None);
-        }
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Builder of `TrainSearch` with the optional departure time.
+///
+/// ```rust
+/// use rzd_trains::TrainSearch;
+///
+/// let q = TrainSearch::builder()
+///     .with_route(2004000, 2000000)
+///     .with_date((2022, 4, 1))
+///     .with_train_number("119А")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(q.leaving_time().to_string(), "00:00");
+/// ```
+pub struct TrainSearchBuilder {
+    leaving_code: RzdStationCode,
+    arriving_code: RzdStationCode,
+    leaving_date: Option<TrainDate>,
+    leaving_time: Option<TrainTime>,
+    train_number: String,
+    lang: Lang,
+}
+
+impl TrainSearchBuilder {
+    /// Sets the departure and the arrival stations.
+    pub fn with_route(
+        mut self,
+        leaving_code: impl Into<RzdStationCode>,
+        arriving_code: impl Into<RzdStationCode>,
+    ) -> Self {
+        self.leaving_code = leaving_code.into();
+        self.arriving_code = arriving_code.into();
+        self
+    }
+
+    /// Sets the departure date.
+    pub fn with_date(mut self, leaving_date: impl Into<TrainDate>) -> Self {
+        self.leaving_date = Some(leaving_date.into());
+        self
+    }
+
+    /// Sets the departure time. If it is not set, the trains are searched
+    /// from the start of the day, i.e. 00:00.
+    pub fn with_time(mut self, leaving_time: impl Into<TrainTime>) -> Self {
+        self.leaving_time = Some(leaving_time.into());
+        self
+    }
+
+    /// Sets the train number, see `TrainSearch::new`.
+    pub fn with_train_number(mut self, train_number: &str) -> Self {
+        self.train_number = train_number.to_string();
+        self
+    }
 
-        Ok(Some(ResultList(reply.value)))
+    /// Sets the language of the names returned by the server, e.g. `Lang::En` for Latin names.
+    pub fn with_lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Checks the parameters and creates the search.
+    ///
+    /// # Errors
+    ///
+    /// The method fails with `Error::MissingParameter` if the date is not set
+    /// and as `TrainSearch::new` if the train number or the stations are wrong.
+    pub fn build(self) -> Result<TrainSearch> {
+        let leaving_date = self.leaving_date.ok_or(Error::MissingParameter("date"))?;
+        let leaving_time = self.leaving_time.unwrap_or(TrainTime::lossy(0, 0));
+
+        let search = TrainSearch::new(
+            self.leaving_code,
+            self.arriving_code,
+            leaving_date,
+            leaving_time,
+            &self.train_number,
+        )?;
+        Ok(search.with_lang(self.lang))
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+/// Search of the train composition, the types of the cars and their number.
+///
+/// The server has no separate layer for it, so the train info layer is requested,
+/// but the prices and the seats are dropped from the result.
+pub struct CarListSearch(TrainSearch);
+
+impl CarListSearch {
+    /// Takes the same parameters as `TrainSearch::new`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the train number is empty or the stations are the same or not set.
+    pub fn new(
+        leaving_code: impl Into<RzdStationCode>,
+        arriving_code: impl Into<RzdStationCode>,
+        leaving_date: impl Into<TrainDate>,
+        leaving_time: impl Into<TrainTime>,
+        train_number: &str,
+    ) -> Result<Self> {
+        TrainSearch::new(
+            leaving_code,
+            arriving_code,
+            leaving_date,
+            leaving_time,
+            train_number,
+        )
+        .map(CarListSearch)
+    }
+
+    /// Sets the language of the names returned by the server, e.g. `Lang::En` for Latin names.
+    pub fn with_lang(self, lang: Lang) -> Self {
+        CarListSearch(self.0.with_lang(lang))
+    }
+
+    /// Returns the underlying train info search.
+    #[inline]
+    pub fn search(&self) -> &TrainSearch {
+        &self.0
+    }
+
+    /// Returns the URL of the first request made for the search.
+    pub fn request_url(&self) -> Result<Url> {
+        self.0.request_url()
+    }
+}
+
+impl From<TrainSearch> for CarListSearch {
+    fn from(search: TrainSearch) -> Self {
+        CarListSearch(search)
+    }
+}
+
+impl RzdClientInterface<ResultList<TrainComposition>> for CarListSearch {
+    fn query_type(&self) -> RzdQueryType {
+        RzdQueryType::WithId
+    }
+
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::TrainInfo
+    }
+
+    fn request_id(&self) -> String {
+        RzdClientInterface::<ResultList<TrainItem>>::request_id(&self.0)
+    }
+
+    fn request_data(&self, id: RzdRequestId) -> String {
+        RzdClientInterface::<ResultList<TrainItem>>::request_data(&self.0, id)
+    }
+
+    fn deserialize_reply_id(&self, body: &str) -> Result<Option<RzdRequestId>> {
+        RzdClientInterface::<ResultList<TrainItem>>::deserialize_reply_id(&self.0, body)
+    }
+
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<ResultList<TrainComposition>>> {
+        let trains = parse_train_reply(body)?;
+        Ok(trains.map(|t| ResultList::new(t.iter().map(|t| t.composition()).collect())))
+    }
+
+    fn validate(&self) -> Result<()> {
+        RzdClientInterface::<ResultList<TrainItem>>::validate(&self.0)
+    }
+}
+
+/// Parses a raw reply of the train info layer, e.g. a captured or logged payload.
+/// Returns a typed error for any malformed or failed reply, `None` if nothing is found.
+pub fn parse_train_reply(body: &str) -> Result<Option<ResultList<TrainItem>>> {
+    let reply: TrainReply = match serde_json::from_str(body) {
+        Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
+        Ok(r) => r,
+    };
+    let reply = reply.0;
+    trace!("reply: {:?}", reply);
+
+    if !reply.success {
+        return Err(reply.error);
+    }
+
+    if reply.value.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ResultList(reply.value)))
+}
+
+impl ResultList<TrainItem> {
+    /// Parses a raw reply of the train info, e.g. a logged one.
+    pub fn from_rzd_json(body: &str) -> Result<Option<Self>> {
+        parse_train_reply(body)
+    }
+
+    /// Sorts the cars of every train in the order.
+    pub fn sort_cars(&mut self, order: CarOrder) {
+        for train in self.0.iter_mut() {
+            train.sort_cars(order);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Order of the train cars.
+pub enum CarOrder {
+    /// By the car number.
+    Number,
+    /// By the lowest ticket price, the cars without the price go last.
+    Price,
+    /// By the number of free seats, the emptiest cars go first.
+    FreeSeats,
+}
+
 #[derive(Debug)]
 struct RidReply(ReplyResult<RzdRequestId>);
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Number of free seats on the train car, seat type and price info.
 pub struct SeatsInfo {
     free_seats: u32,
     seats_type: String,
+    kind: SeatKind,
     price: String,
 }
 
 impl SeatsInfo {
+    pub(crate) fn new(free_seats: u32, seats_type: String, kind: SeatKind, price: String) -> Self {
+        SeatsInfo {
+            free_seats,
+            seats_type,
+            kind,
+            price,
+        }
+    }
+
     /// Returns the number of free seats.
     #[inline]
     pub fn free_seats_number(&self) -> u32 {
@@ -141,11 +449,22 @@ impl SeatsInfo {
         &self.seats_type
     }
 
+    /// Returns the kind of the seats.
+    #[inline]
+    pub fn kind(&self) -> &SeatKind {
+        &self.kind
+    }
+
     /// Returns the seat price.
     #[inline]
     pub fn price(&self) -> &str {
         &self.price
     }
+
+    /// Returns the seat price, `None` if the server omitted it.
+    pub fn price_opt(&self) -> Option<&str> {
+        non_empty(&self.price)
+    }
 }
 
 impl fmt::Display for SeatsInfo {
@@ -158,12 +477,13 @@ impl fmt::Display for SeatsInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Name, URL and cost of insurance.
+/// The offer is shared by the cars, so the clones of it share the data.
 pub struct InsuranceInfo {
-    name: String,
-    url: String,
-    price: String,
+    name: Arc<str>,
+    url: Arc<str>,
+    price: Arc<str>,
 }
 
 impl InsuranceInfo {
@@ -179,11 +499,21 @@ impl InsuranceInfo {
         &self.url
     }
 
+    /// Returns URL with insurance info, `None` if the server omitted it.
+    pub fn url_opt(&self) -> Option<&str> {
+        non_empty(&self.url)
+    }
+
     /// Returns the insurance price.
     #[inline]
     pub fn price(&self) -> &str {
         &self.price
     }
+
+    /// Returns the insurance price, `None` if the server omitted it.
+    pub fn price_opt(&self) -> Option<&str> {
+        non_empty(&self.price)
+    }
 }
 
 impl fmt::Display for InsuranceInfo {
@@ -192,7 +522,7 @@ impl fmt::Display for InsuranceInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Train car info.
 pub struct TrainCar {
     number: String,
@@ -227,6 +557,16 @@ impl TrainCar {
         &self.service_class
     }
 
+    /// Returns the class of service, `None` if the server omitted it.
+    pub fn service_class_opt(&self) -> Option<&str> {
+        non_empty(&self.service_class)
+    }
+
+    /// Returns the normalized code of the class of service.
+    pub fn service_class_code(&self) -> ServiceClass {
+        ServiceClass::new(&self.service_class)
+    }
+
     /// Returns the immutable list of available services.
     #[inline]
     pub fn services(&self) -> &ResultList<String> {
@@ -244,24 +584,45 @@ impl TrainCar {
         &self.tariff1
     }
 
+    /// Returns the price of a seat, `None` if the server omitted it.
+    pub fn price1_opt(&self) -> Option<&str> {
+        non_empty(&self.tariff1)
+    }
+
     /// Returns the price of a seat.
     #[inline]
     pub fn price2(&self) -> &str {
         &self.tariff2
     }
 
+    /// Returns the price of a seat, `None` if the server omitted it,
+    /// e.g. the car has one tariff only.
+    pub fn price2_opt(&self) -> Option<&str> {
+        non_empty(&self.tariff2)
+    }
+
     /// Returns the price of services.
     #[inline]
     pub fn price_service(&self) -> &str {
         &self.tariff_service
     }
 
+    /// Returns the price of services, `None` if the server omitted it.
+    pub fn price_service_opt(&self) -> Option<&str> {
+        non_empty(&self.tariff_service)
+    }
+
     /// Returns the carrier.
     #[inline]
     pub fn carrier(&self) -> &str {
         &self.carrier
     }
 
+    /// Returns the carrier, `None` if the server omitted it.
+    pub fn carrier_opt(&self) -> Option<&str> {
+        non_empty(&self.carrier)
+    }
+
     /// Returns insurance info of a seat.
     #[inline]
     pub fn insurance(&self) -> &Option<InsuranceInfo> {
@@ -284,6 +645,39 @@ impl TrainCar {
     pub fn places(&self) -> &str {
         &self.places
     }
+
+    /// Returns the available places, `None` if the server omitted them.
+    pub fn places_opt(&self) -> Option<&str> {
+        non_empty(&self.places)
+    }
+
+    /// Returns the number of free seats of all types in the train car.
+    pub fn total_free_seats(&self) -> u32 {
+        self.seats.iter().map(|s| s.free_seats).sum()
+    }
+
+    /// Returns the lowest ticket price in whole rubles, `None` if it isn't a number.
+    pub fn min_price(&self) -> Option<u32> {
+        parse_price(&self.tariff1)
+    }
+
+    /// Returns the number of free lower berths, including the side ones.
+    pub fn lower_berths_free(&self) -> u32 {
+        self.free_seats_of(|k| matches!(k, SeatKind::Lower | SeatKind::LowerSide))
+    }
+
+    /// Returns the number of free upper berths, including the side ones.
+    pub fn upper_berths_free(&self) -> u32 {
+        self.free_seats_of(|k| matches!(k, SeatKind::Upper | SeatKind::UpperSide))
+    }
+
+    fn free_seats_of(&self, is_kind: impl Fn(&SeatKind) -> bool) -> u32 {
+        self.seats
+            .iter()
+            .filter(|s| is_kind(&s.kind))
+            .map(|s| s.free_seats)
+            .sum()
+    }
 }
 
 impl fmt::Display for TrainCar {
@@ -297,17 +691,17 @@ impl fmt::Display for TrainCar {
             write!(f, "\tуслуги: {}\n", self.services)?;
         }
         write!(f, "\tтарифы:")?;
-        if !self.tariff1.is_empty() {
-            write!(f, "\t\t{} (билет)\n", self.tariff1)?;
+        if let Some(price) = self.price1_opt() {
+            write!(f, "\t\t{} (билет)\n", price)?;
         }
-        if !self.tariff2.is_empty() {
-            write!(f, "\t\t{} (плацкарта)\n", self.tariff2)?;
+        if let Some(price) = self.price2_opt() {
+            write!(f, "\t\t{} (плацкарта)\n", price)?;
         }
-        if !self.tariff_service.is_empty() {
-            write!(f, "\t\t{} (сервис)\n", self.tariff_service)?;
+        if let Some(price) = self.price_service_opt() {
+            write!(f, "\t\t{} (сервис)\n", price)?;
         }
-        if !self.insurance.is_none() {
-            write!(f, "\tстраховка: {}\n", self.insurance.as_ref().unwrap())?;
+        if let Some(ref insurance) = self.insurance {
+            writeln!(f, "\tстраховка: {}", insurance)?;
         }
         write!(f, "\tместа: {}\n", self.places)?;
         write!(f, "\tвсего мест:")?;
@@ -318,10 +712,12 @@ impl fmt::Display for TrainCar {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 /// Info about the train.
 pub struct TrainItem {
     train_number: String,
+    #[serde(default)]
+    category: TrainCategory,
     leaving_date: Option<TrainDate>,
     leaving_time: Option<TrainTime>,
     arriving_date: Option<TrainDate>,
@@ -331,6 +727,8 @@ pub struct TrainItem {
     leaving_station_code: RzdStationCode,
     arriving_station_code: RzdStationCode,
     cars: ResultList<TrainCar>,
+    #[serde(default)]
+    warnings: Warnings,
 }
 
 impl TrainItem {
@@ -340,6 +738,12 @@ impl TrainItem {
         &self.train_number
     }
 
+    /// Returns the category of the train.
+    #[inline]
+    pub fn category(&self) -> TrainCategory {
+        self.category
+    }
+
     /// Returns the departure date of the train.
     #[inline]
     pub fn leaving_date(&self) -> Option<TrainDate> {
@@ -388,6 +792,17 @@ impl TrainItem {
         self.arriving_station_code
     }
 
+    /// Returns the link to buy a ticket for the train on rzd.ru,
+    /// `None` if the stations or the departure date are unknown.
+    pub fn booking_url(&self) -> Option<Url> {
+        booking_url(
+            self.leaving_station_code,
+            self.arriving_station_code,
+            self.leaving_date?,
+            &self.train_number,
+        )
+    }
+
     /// Returns the immutable list of train cars.
     #[inline]
     pub fn cars(&self) -> &ResultList<TrainCar> {
@@ -398,6 +813,120 @@ impl TrainItem {
     pub fn cars_mut(&mut self) -> &mut ResultList<TrainCar> {
         &mut self.cars
     }
+
+    /// Returns the distinct insurance offers for the cars of the train.
+    pub fn insurances(&self) -> Vec<&InsuranceInfo> {
+        let mut list: Vec<&InsuranceInfo> = vec![];
+        for ins in self.cars.iter().filter_map(|c| c.insurance.as_ref()) {
+            if !list.contains(&ins) {
+                list.push(ins);
+            }
+        }
+        list
+    }
+
+    /// Returns the messages about the malformed cars and seats skipped in the reply.
+    #[inline]
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+
+    /// Returns the train cars with one of the classes of service, e.g. `&["2Э", "2Т"]`.
+    pub fn cars_with_class(&self, classes: &[&str]) -> Vec<&TrainCar> {
+        let classes: Vec<ServiceClass> = classes.iter().map(|c| ServiceClass::new(c)).collect();
+        self.cars
+            .iter()
+            .filter(|c| classes.contains(&c.service_class_code()))
+            .collect()
+    }
+
+    /// Returns true if there is a free lower berth in any train car.
+    pub fn has_lower_berth(&self) -> bool {
+        self.cars.iter().any(|c| c.lower_berths_free() > 0)
+    }
+
+    /// Returns the number of free seats of all types in all train cars.
+    pub fn total_free_seats(&self) -> u32 {
+        self.cars.iter().map(|c| c.total_free_seats()).sum()
+    }
+
+    /// Sorts the train cars in the order.
+    pub fn sort_cars(&mut self, order: CarOrder) {
+        let cars = &mut self.cars.0;
+        match order {
+            CarOrder::Number => {
+                cars.sort_by(
+                    |a, b| match (a.number.parse::<u32>(), b.number.parse::<u32>()) {
+                        (Ok(a), Ok(b)) => a.cmp(&b),
+                        _ => a.number.cmp(&b.number),
+                    },
+                )
+            }
+            CarOrder::Price => cars.sort_by_key(|c| (c.min_price().is_none(), c.min_price())),
+            CarOrder::FreeSeats => cars.sort_by_key(|c| Reverse(c.total_free_seats())),
+        }
+    }
+
+    /// Returns the cars collapsed into one line per type and class of service,
+    /// e.g. `Купе 2Э ×3 вагона, 180 мест от 3966`.
+    pub fn grouped_cars(&self) -> GroupedCars<'_> {
+        GroupedCars { train: self }
+    }
+
+    /// Returns one offer per type and class of service of the cars
+    /// with the total free seats and the lowest price, in the order of the cars.
+    pub fn offers(&self) -> ResultList<CarOffer> {
+        let mut offers: Vec<CarOffer> = vec![];
+        for car in self.cars.iter() {
+            let service_class = car.service_class_code();
+            let price = car.min_price();
+            match offers
+                .iter_mut()
+                .find(|o| o.type_loc == car.type_loc && o.service_class == service_class)
+            {
+                Some(o) => {
+                    o.cars += 1;
+                    o.free_seats += car.total_free_seats();
+                    o.min_price = match (o.min_price, price) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                }
+                None => offers.push(CarOffer {
+                    type_loc: car.type_loc.clone(),
+                    service_class,
+                    cars: 1,
+                    free_seats: car.total_free_seats(),
+                    min_price: price,
+                }),
+            }
+        }
+        ResultList::new(offers)
+    }
+
+    /// Returns the composition of the train, the types of the cars and their number.
+    pub fn composition(&self) -> TrainComposition {
+        let mut cars: Vec<CarCount> = vec![];
+        for car in self.cars.iter() {
+            let service_class = car.service_class_code();
+            match cars
+                .iter_mut()
+                .find(|c| c.type_loc == car.type_loc && c.service_class == service_class)
+            {
+                Some(c) => c.count += 1,
+                None => cars.push(CarCount {
+                    type_loc: car.type_loc.clone(),
+                    service_class,
+                    count: 1,
+                }),
+            }
+        }
+
+        TrainComposition {
+            train_number: self.train_number.clone(),
+            cars: ResultList::new(cars),
+        }
+    }
 }
 
 impl fmt::Display for TrainItem {
@@ -420,20 +949,314 @@ impl fmt::Display for TrainItem {
     }
 }
 
+/// The cars of the train displayed by groups, see `TrainItem::grouped_cars`.
+pub struct GroupedCars<'a> {
+    train: &'a TrainItem,
+}
+
+impl fmt::Display for GroupedCars<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for offer in self.train.offers().iter() {
+            writeln!(f, "{}", offer)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Cars of one type and class of service with their free seats and the lowest price.
+pub struct CarOffer {
+    type_loc: String,
+    service_class: ServiceClass,
+    cars: u32,
+    free_seats: u32,
+    min_price: Option<u32>,
+}
+
+impl CarOffer {
+    /// Returns the seat type of the cars.
+    #[inline]
+    pub fn type_loc(&self) -> &str {
+        &self.type_loc
+    }
+
+    /// Returns the normalized code of the class of service.
+    #[inline]
+    pub fn service_class(&self) -> &ServiceClass {
+        &self.service_class
+    }
+
+    /// Returns the number of the cars.
+    #[inline]
+    pub fn cars(&self) -> u32 {
+        self.cars
+    }
+
+    /// Returns the number of free seats in all the cars.
+    #[inline]
+    pub fn free_seats(&self) -> u32 {
+        self.free_seats
+    }
+
+    /// Returns the lowest ticket price among the cars, `None` if no car has the price.
+    #[inline]
+    pub fn min_price(&self) -> Option<u32> {
+        self.min_price
+    }
+}
+
+impl fmt::Display for CarOffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ×{} {}, {} {}",
+            self.type_loc,
+            self.service_class,
+            self.cars,
+            plural(self.cars, "вагон", "вагона", "вагонов"),
+            self.free_seats,
+            plural(self.free_seats, "место", "места", "мест")
+        )?;
+        if let Some(price) = self.min_price {
+            write!(f, " от {}", price)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 struct TrainReply(ReplyResult<Vec<TrainItem>>);
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Number of the train cars of one type and class of service.
+pub struct CarCount {
+    type_loc: String,
+    service_class: ServiceClass,
+    count: u32,
+}
+
+impl CarCount {
+    /// Returns the seat type of the cars.
+    #[inline]
+    pub fn type_loc(&self) -> &str {
+        &self.type_loc
+    }
+
+    /// Returns the normalized code of the class of service.
+    #[inline]
+    pub fn service_class(&self) -> &ServiceClass {
+        &self.service_class
+    }
+
+    /// Returns the number of the cars.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+impl fmt::Display for CarCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.type_loc, self.service_class, self.count
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Composition of the train without prices and seats.
+pub struct TrainComposition {
+    train_number: String,
+    cars: ResultList<CarCount>,
+}
+
+impl TrainComposition {
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the types of the cars and their number.
+    #[inline]
+    pub fn cars(&self) -> &ResultList<CarCount> {
+        &self.cars
+    }
+
+    /// Returns the number of all train cars.
+    pub fn total_cars(&self) -> u32 {
+        self.cars.iter().map(|c| c.count).sum()
+    }
+}
+
+impl fmt::Display for TrainComposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Поезд № \"{}\", вагонов: {}",
+            self.train_number,
+            self.total_cars()
+        )?;
+        for c in self.cars.iter() {
+            writeln!(f, "\t{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+/// Difference between the cars of the train on two days, the cars are matched by their numbers.
+/// Only the cars with free seats are returned by the server, so a sold out car looks removed.
+pub struct CompositionDiff {
+    train_number: String,
+    added: Vec<TrainCar>,
+    removed: Vec<TrainCar>,
+    changed: Vec<(TrainCar, TrainCar)>,
+}
+
+impl CompositionDiff {
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the cars appeared on the other day.
+    #[inline]
+    pub fn added(&self) -> &[TrainCar] {
+        &self.added
+    }
+
+    /// Returns the cars missing on the other day.
+    #[inline]
+    pub fn removed(&self) -> &[TrainCar] {
+        &self.removed
+    }
+
+    /// Returns the cars with the changed type or class of service, before and after.
+    #[inline]
+    pub fn changed(&self) -> &[(TrainCar, TrainCar)] {
+        &self.changed
+    }
+
+    /// Returns true if the composition is the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for CompositionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Поезд № \"{}\", изменения состава:", self.train_number)?;
+        for c in self.added.iter() {
+            writeln!(
+                f,
+                "\t+ вагон {}, {}, класс {}",
+                c.number, c.type_loc, c.service_class
+            )?;
+        }
+        for c in self.removed.iter() {
+            writeln!(
+                f,
+                "\t- вагон {}, {}, класс {}",
+                c.number, c.type_loc, c.service_class
+            )?;
+        }
+        for (a, b) in self.changed.iter() {
+            writeln!(
+                f,
+                "\t* вагон {}: {}, класс {} -> {}, класс {}",
+                a.number, a.type_loc, a.service_class, b.type_loc, b.service_class
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TrainItem {
+    /// Compares the cars of the train with the cars of the same train on the other day.
+    pub fn composition_diff(&self, other: &TrainItem) -> CompositionDiff {
+        // The car may be listed once per tariff, the first entry is taken.
+        let unique = |cars: &ResultList<TrainCar>| {
+            let mut list: Vec<TrainCar> = vec![];
+            for car in cars.iter() {
+                if !list.iter().any(|c| c.number == car.number) {
+                    list.push(car.clone());
+                }
+            }
+            list
+        };
+        let (before, after) = (unique(&self.cars), unique(&other.cars));
+
+        let mut diff = CompositionDiff {
+            train_number: self.train_number.clone(),
+            ..CompositionDiff::default()
+        };
+        for car in before.iter() {
+            match after.iter().find(|c| c.number == car.number) {
+                None => diff.removed.push(car.clone()),
+                Some(c) if c.type_loc != car.type_loc || c.service_class != car.service_class => {
+                    diff.changed.push((car.clone(), c.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for car in after.into_iter() {
+            if !before.iter().any(|c| c.number == car.number) {
+                diff.added.push(car);
+            }
+        }
+        diff
+    }
+}
+
+impl RzdClient<CompositionDiff> {
+    /// Gets the cars of the train on the date of the search and on the other date
+    /// within one session and compares them.
+    ///
+    /// Returns `None` if the train is not found on one of the dates.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if there was an error while processing request
+    /// or received data couldn't be deserialized.
+    pub fn composition_diff(
+        search: &TrainSearch,
+        other_date: impl Into<TrainDate>,
+    ) -> Result<Option<CompositionDiff>> {
+        let mut session = SessionToken::default();
+        let mut other = search.clone();
+        other.leaving_date = other_date.into();
+
+        let mut trains = vec![];
+        for q in [search, &other] {
+            let list = RzdClient::<ResultList<TrainItem>>::get_with_session(q, &mut session)?;
+            match list.and_then(|l| l.0.into_iter().next()) {
+                Some(t) => trains.push(t),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(trains[0].composition_diff(&trains[1])))
+    }
+}
+
 mod de {
     use super::{InsuranceInfo, RidReply, SeatsInfo, TrainCar, TrainItem, TrainReply};
     use crate::client::RzdRequestId;
-    use crate::des::des_null_to_default;
+    use crate::des::{des_null_to_default, Lenient};
     use crate::error::Error as GError;
     use crate::error::RzdErrors;
-    use crate::{ReplyResult, ResultList, RzdStationCode, TrainDate, TrainTime};
+    use crate::{
+        ReplyResult, ResultList, RzdStationCode, SeatKind, TrainCategory, TrainDate, Warnings,
+    };
     use serde::Deserialize;
+    use std::collections::HashMap;
     type ReplyResultId = ReplyResult<RzdRequestId>;
     type ReplyResultTrains = ReplyResult<Vec<TrainItem>>;
-    use crate::{parse_station_code, parse_train_date, parse_train_time};
+    use crate::{parse_reply_time, parse_station_code, parse_train_date};
 
     impl<'de> serde::Deserialize<'de> for RidReply {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -478,6 +1301,11 @@ mod de {
                 #[serde(default)]
                 type_seats: String,
 
+                // The code of the seats, e.g. "dn", or the short name of the car type.
+                #[serde(rename = "type")]
+                #[serde(default)]
+                type_code: String,
+
                 #[serde(default)]
                 tariff: String,
             }
@@ -526,7 +1354,7 @@ mod de {
                 insurance_id: u32,
 
                 #[serde(default)]
-                seats: Vec<Seats>,
+                seats: Lenient<Seats>,
 
                 #[serde(default)]
                 places: String,
@@ -537,9 +1365,16 @@ mod de {
                 #[serde(default)]
                 result: String,
 
+                #[serde(default)]
+                timestamp: String,
+
                 #[serde(default)]
                 number: String,
 
+                #[serde(alias = "type")]
+                #[serde(default)]
+                category: String,
+
                 #[serde(default)]
                 date0: String,
 
@@ -565,7 +1400,7 @@ mod de {
                 code1: String,
 
                 #[serde(default)]
-                cars: Vec<TrainCars>,
+                cars: Lenient<TrainCars>,
 
                 #[serde(default)]
                 error: String,
@@ -579,6 +1414,9 @@ mod de {
                 #[serde(default)]
                 lst: Vec<RzdTrain>,
 
+                #[serde(default)]
+                timestamp: String,
+
                 #[serde(alias = "insuranceCompany")]
                 #[serde(default)]
                 insurance: Vec<RzdInsurance>,
@@ -620,6 +1458,16 @@ mod de {
                 }
             };
 
+            // The offers are shared by all cars, the first one with the id is taken.
+            let mut insurances: HashMap<u32, InsuranceInfo> = HashMap::new();
+            for ins in input.insurance {
+                insurances.entry(ins.id).or_insert_with(|| InsuranceInfo {
+                    name: ins.short_name.into(),
+                    url: ins.offer_url.into(),
+                    price: ins.insurance_cost.to_string().into(),
+                });
+            }
+
             let mut trains: Vec<TrainItem> = vec![];
             for train in input.lst {
                 let res_error: &str = &(train.result);
@@ -629,17 +1477,36 @@ mod de {
                         Some(r) => err = r.to_string(),
                         None => {}
                     }
-                    let err = GError::RzdError(RzdErrors::new(vec![err]));
+                    // The train has its own timestamp, the one of the reply is taken otherwise.
+                    let timestamp = match train.timestamp.is_empty() {
+                        true => &input.timestamp,
+                        false => &train.timestamp,
+                    };
+                    let err = RzdErrors::new(vec![err]).with_reply_timestamp(timestamp);
+                    let err = GError::from(err);
                     return Ok(TrainReply(ReplyResultTrains::fail(err)));
                 }
 
+                let mut warnings: Vec<String> = train
+                    .cars
+                    .errors
+                    .iter()
+                    .map(|e| format!("пропущен некорректный вагон: {}", e))
+                    .collect();
+
                 let mut cars: Vec<TrainCar> = vec![];
-                for car in train.cars {
+                for car in train.cars.items {
+                    warnings.extend(car.seats.errors.iter().map(|e| {
+                        format!("вагон {}: пропущены некорректные места: {}", car.cnumber, e)
+                    }));
+
                     let seats: Vec<SeatsInfo> = car
                         .seats
+                        .items
                         .into_iter()
                         .map(|s| SeatsInfo {
                             free_seats: s.free_seats,
+                            kind: SeatKind::from_code_or_name(&s.type_code, &s.type_seats),
                             seats_type: s.type_seats,
                             price: s.tariff,
                         })
@@ -648,17 +1515,7 @@ mod de {
                     let services: Vec<String> =
                         car.services.into_iter().map(|s| s.description).collect();
 
-                    let mut insurance = None;
-                    for ins in input.insurance.iter() {
-                        if ins.id == car.insurance_id {
-                            insurance = Some(InsuranceInfo {
-                                name: ins.short_name.clone(),
-                                url: ins.offer_url.clone(),
-                                price: ins.insurance_cost.to_string(),
-                            });
-                            break;
-                        }
-                    }
+                    let insurance = insurances.get(&car.insurance_id).cloned();
 
                     cars.push(TrainCar {
                         number: car.cnumber,
@@ -675,17 +1532,25 @@ mod de {
                     });
                 }
 
+                let what = |value| format!("поезд {}, {}", train.number, value);
+                let leaving_time =
+                    parse_reply_time(&train.time0, &what("отправление"), &mut warnings);
+                let arriving_time =
+                    parse_reply_time(&train.time1, &what("прибытие"), &mut warnings);
+
                 trains.push(TrainItem {
                     train_number: train.number,
+                    category: TrainCategory::from(train.category.as_str()),
                     leaving_date: parse_train_date!(train.date0),
-                    leaving_time: parse_train_time!(train.time0),
+                    leaving_time,
                     arriving_date: parse_train_date!(train.date1),
-                    arriving_time: parse_train_time!(train.time1),
+                    arriving_time,
                     leaving_station_name: train.station0,
                     arriving_station_name: train.station1,
                     leaving_station_code: parse_station_code!(train.code0),
                     arriving_station_code: parse_station_code!(train.code1),
                     cars: ResultList(cars),
+                    warnings: Warnings::new(warnings),
                 });
             }
 
@@ -696,11 +1561,19 @@ mod de {
 
 #[cfg(test)]
 mod tests {
-    use super::{InsuranceInfo, RidReply, SeatsInfo, TrainCar, TrainItem, TrainReply};
-    use crate::client::RzdRequestId;
+    use super::{
+        parse_train_reply, CarListSearch, CarOrder, InsuranceInfo, RidReply, SeatsInfo, TrainCar,
+        TrainComposition, TrainItem, TrainReply,
+    };
+    use crate::client::{RzdClientInterface, RzdRequestId};
+    use crate::fixtures::load_fixture;
     use crate::{error::Error, RzdErrors};
     use crate::{parse_train_date, parse_train_time};
-    use crate::{ResultList, RzdStationCode, TrainDate, TrainTime};
+    use crate::{
+        Lang, ResultList, RzdStationCode, SeatKind, TrainCategory, TrainDate, TrainTime, Warnings,
+    };
+    use crate::{RouteList, RzdClient, TrainInfoList, TrainScheduleSearch, TrainSearch, TrainType};
+    use std::sync::Arc;
 
     #[test]
     fn rid_reply_deserialize_test() {
@@ -753,13 +1626,21 @@ mod tests {
 
         assert!(!answer.success);
         assert_eq!(answer.error.to_string(), data.to_string());
+        match answer.error {
+            Error::RzdError(ref e) => assert_eq!(
+                e.server_timestamp().map(|t| t.to_string()),
+                Some("30.03.2022 13:54:43.191".to_string())
+            ),
+            ref e => panic!("unexpected error: {:?}", e),
+        }
 
-        let answer = r#"{"result":"OK","lst":[{"result":"OK","number":"001А","number2":"001А","defShowTime":"local","date0":"01.04.2022","time0":"23:55","date1":"02.04.2022","time1":"07:55","type":"СК ФИРМ","virtual":false,"bus":false,"boat":false,"station0":"САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)","code0":"2004001","station1":"МОСКВА ОКТЯБРЬСКАЯ (ЛЕНИНГРАДСКИЙ ВОКЗАЛ)","code1":"2006004","timeSt0":"","timeSt1":"","route0":"С-ПЕТЕР-ГЛ","route1":"МОСКВА ОКТ","cars":[{"cnumber":"01","type":"Купе","catLabelLoc":"Купе","typeLoc":"Купе","catCode":"Купе","ctypei":4,"ctype":4,"letter":"А","clsType":"2Э","subType":"66К","clsName":"4-местные купе.<p>Вагон повышенной комфортности (рацион питания, санитарно-гигиенический набор*, пресса*, белье).</p><p><strong>Кондиционер, биотуалет в вагоне.</strong></p><p>Вагон с услугой перевозки животных. </p><p>*кроме двухэтажных вагонов</p>","services":[{"id":2,"name":"[иконка сайта] Биотуалет","description":"Биотуалет","hasImage":true},{"id":3,"name":"[иконка сайта] Кондиционер","description":"Кондиционер работает в летний период","hasImage":true},{"id":73,"name":"[иконка сайта] Попутчик","description":"Мультимедийный портал \"Попутчик\"","hasImage":true},{"id":80,"name":"[иконка сайта] Животные 2Э,2Б,2Ф,2Ц","description":"Для провоза мелких животных необходим выкуп всего купе.<br>Провоз мелких животных бесплатный.<br>Для провоза крупной собаки необходим выкуп всего купе.<br>Можно провести только одну крупную собаку.<br>Провоз крупной собаки бесплатный","hasImage":true},{"id":135,"name":"[иконка сайта] Гигиена 1Э 2Э","description":"Гигиенический набор","hasImage":true},{"id":136,"name":"[иконка сайта] Пресса 1Э 2Э","description":"Пресса","hasImage":true},{"id":30,"name":"[иконка сайта] Постель","description":"Постельное белье","hasImage":true}],"tariff":"3966","tariff2":"5090","tariffServ":"766","addSigns":"У1","carrier":"ФПК","carrierId":1,"insuranceFlag":true,"insuranceTypeId":1,"owner":"РЖД/ОКТ","elReg":true,"food":true,"selFood":false,"equippedSIOP":true,"addFood":true,"regularFoodService":false,"noSmok":false,"inetSaleOff":false,"bVip":false,"conferenceRoomFlag":false,"bDeck2":false,"intServiceClass":null,"specialSeatTypes":null,"deferredPayment":false,"varPrice":true,"ferry":false,"seniorTariff":0,"bedding":false,"nonRefundable":false,"addTour":false,"addGoods":true,"addHandLuggage":true,"youth":false,"unior":false,"seats":[{"type":"dn","free":9,"label":"Нижнее","tariff":"3966"},{"type":"up","free":15,"label":"Верхнее","tariff":"3966"}],"places":"002-004,006-010,012-014,016,020-028,030-032","schemeId":830,"schemeInfo":{"dir":"/dbmm/images/61/28209/14","dirVert":"/dbmm/images/61/28216/14","legend":""},"forcedBedding":true,"policyEnabled":true,"msr":true,"medic":true},{"cnumber":"02","type":"Купе","catLabelLoc":"Купе","typeLoc":"Купе","catCode":"Купе","ctypei":4,"ctype":4,"letter":"А","clsType":"2Т","subType":"66К","clsName":"4-местные купе.<p>Вагон повышенной комфортности (рацион питания, санитарно-гигиенический набор*, пресса*, белье).</p><p><strong>Кондиционер, биотуалет в вагоне.</strong></p><p>","services":[{"id":2,"name":"[иконка сайта] Биотуалет","description":"Биотуалет","hasImage":true},{"id":3,"name":"[иконка сайта] Кондиционер","description":"Кондиционер работает в летний период","hasImage":true},{"id":4,"name":"[иконка сайта] Гигиена","description":"Гигиенический набор","hasImage":true},{"id":30,"name":"[иконка сайта] Постель","description":"Постельное белье","hasImage":true},{"id":73,"name":"[иконка сайта] Попутчик","description":"Мультимедийный портал \"Попутчик\"","hasImage":true},{"id":6,"name":"[иконка сайта] Пресса","description":"Пресса","hasImage":true},{"id":14,"name":"[иконка сайта] Провоз животных запрещен","description":"Провоз животных запрещен","hasImage":true}],"tariff":"3966","tariff2":"5090","tariffServ":"766","addSigns":"У1","carrier":"ФПК","carrierId":1,"insuranceFlag":true,"insuranceTypeId":1,"owner":"РЖД/ОКТ","elReg":true,"food":true,"selFood":false,"equippedSIOP":true,"addFood":true,"regularFoodService":false,"noSmok":false,"inetSaleOff":false,"bVip":false,"conferenceRoomFlag":false,"bDeck2":false,"intServiceClass":null,"specialSeatTypes":null,"deferredPayment":false,"varPrice":true,"ferry":false,"seniorTariff":0,"bedding":false,"nonRefundable":false,"addTour":false,"addGoods":true,"addHandLuggage":true,"youth":false,"unior":false,"seats":[{"type":"dn","free":9,"label":"Нижнее","tariff":"3966"},{"type":"up","free":12,"label":"Верхнее","tariff":"3966"}],"places":"005,006,008-016,021,022,024-026,028-032","schemeId":830,"schemeInfo":{"dir":"/dbmm/images/61/28209/14","dirVert":"/dbmm/images/61/28216/14","legend":""},"forcedBedding":true,"policyEnabled":true,"msr":true,"medic":true},{"cnumber":"03","type":"Купе","catLabelLoc":"Купе","typeLoc":"Купе","catCode":"Купе","ctypei":4,"ctype":4,"letter":"А","clsType":"2Э","subType":"66К","clsName":"4-местные купе.<p>Вагон повышенной комфортности (рацион питания, санитарно-гигиенический набор*, пресса*, белье).</p><p><strong>Кондиционер, биотуалет в вагоне.</strong></p><p>Вагон с услугой перевозки животных. </p><p>*кроме двухэтажных вагонов</p>","services":[{"id":2,"name":"[иконка сайта] Биотуалет","description":"Биотуалет","hasImage":true},{"id":3,"name":"[иконка сайта] Кондиционер","description":"Кондиционер работает в летний период","hasImage":true},{"id":73,"name":"[иконка сайта] Попутчик","description":"Мультимедийный портал \"Попутчик\"","hasImage":true},{"id":80,"name":"[иконка сайта] Животные 2Э,2Б,2Ф,2Ц","description":"Для провоза мелких животных необходим выкуп всего купе.<br>Провоз мелких животных бесплатный.<br>Для провоза крупной собаки необходим выкуп всего купе.<br>Можно провести только одну крупную собаку.<br>Провоз крупной собаки бесплатный","hasImage":true},{"id":135,"name":"[иконка сайта] Гигиена 1Э 2Э","description":"Гигиенический набор","hasImage":true},{"id":136,"name":"[иконка сайта] Пресса 1Э 2Э","description":"Пресса","hasImage":true},{"id":30,"name":"[иконка сайта] Постель","description":"Постельное белье","hasImage":true}],"tariff":"3966","tariff2":"5090","tariffServ":"766","addSigns":"МЖ У1","carrier":"ФПК","carrierId":1,"insuranceFlag":true,"insuranceTypeId":1,"owner":"РЖД/ОКТ","elReg":true,"food":true,"selFood":false,"equippedSIOP":true,"addFood":true,"regularFoodService":false,"noSmok":false,"inetSaleOff":false,"bVip":false,"conferenceRoomFlag":false,"bDeck2":false,"intServiceClass":null,"specialSeatTypes":null,"deferredPayment":false,"varPrice":true,"ferry":false,"seniorTariff":0,"bedding":false,"nonRefundable":false,"addTour":false,"addGoods":true,"addHandLuggage":true,"youth":false,"unior":false,"seats":[{"type":"dn","free":5,"label":"Нижнее","tariff":"3966"},{"type":"up","free":13,"label":"Верхнее","tariff":"3966"}],"places":"002Ж,004Ж,006-008М,012-014Ж,018М,022-024Ж,026-028С,030-032М","schemeId":830,"schemeInfo":{"dir":"/dbmm/images/61/28209/14","dirVert":"/dbmm/images/61/28216/14","legend":""},"forcedBedding":true,"policyEnabled":true,"msr":true,"medic":true},{"cnumber":"08","type":"Люкс","catLabelLoc":"СВ","typeLoc":"СВ","catCode":"СВ","ctypei":6,"ctype":6,"letter":"А","clsType":"1Э","subType":"23Л","clsName":"2-местные купе. </p><strong>Биотуалет, кондиционер в вагоне.</strong><p></p> Перевозка домашних животных.</p> Вагон повышенной комфортности (санитарно-гигиенический набор, пресса, белье)","services":[{"id":2,"name":"[иконка сайта] Биотуалет","description":"Биотуалет","hasImage":true},{"id":3,"name":"[иконка сайта] Кондиционер","description":"Кондиционер работает в летний период","hasImage":true},{"id":73,"name":"[иконка сайта] Попутчик","description":"Мультимедийный портал \"Попутчик\"","hasImage":true},{"id":135,"name":"[иконка сайта] Гигиена 1Э 2Э","description":"Гигиенический набор","hasImage":true},{"id":136,"name":"[иконка сайта] Пресса 1Э 2Э","description":"Пресса","hasImage":true},{"id":9,"name":"[иконка сайта] Телевизор","description":"Телевизор","hasImage":true},{"id":18,"name":"[иконка сайта] Животные 1Э, 1У, 1Л, 2Э, 2Б, 1Б (ТКС)","description":"Возможен провоз мелких животных или одной крупной собаки. Для провоза необходим выкуп всего купе.","hasImage":true},{"id":30,"name":"[иконка сайта] Постель","description":"Постельное белье","hasImage":true}],"tariff":"7950","tariff2":null,"tariffServ":"1643","addSigns":"У1","carrier":"ФПК","carrierId":1,"insuranceFlag":true,"insuranceTypeId":1,"owner":"РЖД/ОКТ","elReg":true,"food":true,"selFood":false,"equippedSIOP":true,"addFood":true,"regularFoodService":false,"noSmok":false,"inetSaleOff":false,"bVip":false,"conferenceRoomFlag":false,"bDeck2":false,"intServiceClass":null,"specialSeatTypes":null,"deferredPayment":false,"varPrice":true,"ferry":false,"seniorTariff":0,"bedding":false,"nonRefundable":false,"addTour":false,"addGoods":true,"addHandLuggage":true,"youth":false,"unior":false,"seats":[{"type":"dn","free":6,"label":"Нижнее","tariff":"7950"}],"places":"001,002,012,013,015,016","schemeId":324,"schemeInfo":{"dir":"/dbmm/images/61/28209/44","dirVert":"/dbmm/images/61/28216/44","legend":""},"forcedBedding":true,"policyEnabled":true,"medic":true},{"cnumber":"16","type":"Мягкий","catLabelLoc":"Люкс","typeLoc":"Люкс","catCode":"Люкс","ctypei":5,"ctype":5,"letter":"А","clsType":"1А","subType":"19М","clsName":"<p>1/1-купе с 1-местным размещением, 1/2 -купе с 2-местным размещением. Салон-бар в вагоне. <p><strong>Душ, биотуалет, умывальник, кондиционер в купе.</strong><p>Продается только целое купе.<p>Особые условия провоза детей. Перевозка домашних животных.","services":[{"id":2,"name":"[иконка сайта] Биотуалет","description":"Биотуалет","hasImage":true},{"id":3,"name":"[иконка сайта] Кондиционер","description":"Кондиционер работает в летний период","hasImage":true},{"id":4,"name":"[иконка сайта] Гигиена","description":"Гигиенический набор","hasImage":true},{"id":6,"name":"[иконка сайта] Пресса","description":"Пресса","hasImage":true},{"id":73,"name":"[иконка сайта] Попутчик","description":"Мультимедийный портал \"Попутчик\"","hasImage":true},{"id":9,"name":"[иконка сайта] Телевизор","description":"Телевизор","hasImage":true},{"id":17,"name":"[иконка сайта] Животные 1А, 1И, 1М, 1Е, 1В","description":"Возможен провоз мелких животных. За провоз плата не взимается. Провоз крупных собак не предусмотрен.","hasImage":true},{"id":30,"name":"[иконка сайта] Постель","description":"Постельное белье","hasImage":true}],"tariff":"23587","tariff2":"26740","tariffServ":"3153","addSigns":"У1","carrier":"ФПК","carrierId":1,"insuranceFlag":true,"insuranceTypeId":1,"owner":"РЖД/ОКТ","elReg":true,"food":true,"selFood":false,"equippedSIOP":true,"addFood":true,"regularFoodService":false,"noSmok":false,"inetSaleOff":false,"bVip":true,"conferenceRoomFlag":false,"bDeck2":false,"intServiceClass":null,"specialSeatTypes":null,"deferredPayment":false,"varPrice":true,"ferry":false,"seniorTariff":0,"bedding":false,"nonRefundable":false,"addTour":false,"addGoods":true,"addHandLuggage":true,"youth":false,"unior":false,"seats":[{"type":"kupe","free":1,"label":"Купе","tariff":"23587"}],"places":"007,008","schemeId":320,"forcedBedding":true,"policyEnabled":true,"medic":true}],"addCompLuggage":false,"functionBlocks":[{"className":"s-type-lo","name":"Нижнее место"},{"className":"s-type-mid","name":"Среднее место"},{"className":"s-type-up","name":"Верхнее место"},{"className":"s-type-jumpseat","name":"Откидное место"},{"className":"s-type-seatrot","name":"Место с изменением направления по ходу движения"},{"className":"s-type-seat","name":"Сидячее место"},{"className":"s-prop-undef","name":"Мужское / Женское / Смешанное - признак не определен"},{"className":"s-prop-man","name":"Мужское купе"},{"className":"s-prop-woman","name":"Женское купе"},{"className":"s-prop-mixed","name":"Смешанное купе"},{"className":"s-type-bicycle","name":"Место для пассажира с велосипедом"},{"className":"s-type-pet","name":"Место для проезда с мелким домашним животным"},{"className":"s-type-infant","name":"Место матери и ребенка"},{"className":"s-type-kid","name":"Место пассажира с детьми"},{"className":"s-type-cripple","name":"Место для пассажиров с ограниченными физическими возможностями"},{"className":"s-type-table","name":"Стол"},{"className":"s-type-luggage","name":"Тумба/багаж"},{"className":"s-type-wardrobe","name":"Шкаф/гардероб"},{"className":"s-type-buffet","name":"Буфет"},{"className":"s-type-toilet","name":"Туалет"},{"className":"s-type-shower","name":"Душевая кабина"},{"className":"s-type-conf","name":"Переговорная"},{"className":"s-type-playroom","name":"Детская площадка"},{"className":"s-type-ac","name":"Электрическая розетка"},{"className":"s-type-stairs","name":"Лестничный пролет"},{"className":"s-type-exit","name":"Выход"},{"className":"s-type-cashbox","name":"Касса"},{"className":"s-type-water","name":"Вода"},{"className":"s-prop-kid","name":"Купе для пассажиров с детьми"},{"className":"s-type-kofe-bar","name":"Кафе-бар"},{"className":"s-type-babycarriage","name":"Детские коляски"}],"timestamp":"02.12.2021 18:40:15.064"}],"schemes":[{"id":320,"html":"{\"len\":15,\"cells\":[{\"type\":\"up\",\"number\":2,\"style\":\";border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"wc\",\"style\":\"border-right-color:#000\"},{\"type\":\"wc\",\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":4,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":6,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"wc\",\"style\":\"border-right-color:#000\"},{\"type\":\"wc\",\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":8,\"style\":\"border-right-color:#000\"},{\"type\":\"bu\"},{\"type\":\"bu\"},{\"type\":\"bu\"},{\"type\":\"dn\",\"number\":1,\"style\":\";border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"wc\",\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"wc\",\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":3,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":5,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"wc\",\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"wc\",\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":7,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"bu\"},{\"type\":\"bu\"},{\"type\":\"bu\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"bu\"},{\"type\":\"bu\"},{\"type\":\"bu\"}]}","image":null},{"id":324,"html":"{\"len\":24,\"cells\":[{\"type\":\"dn\",\"number\":1,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":2,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":3,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":4,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":5,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":6,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":7,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":8,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":9,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":10,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":11,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":12,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":13,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":14,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":15,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"st\"},{\"type\":\"dn\",\"number\":16,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"}]}","image":null},{"id":830,"html":"{\"len\":24,\"cells\":[{\"type\":\"up\",\"number\":2,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":4,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":6,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":8,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":10,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":12,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":14,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":16,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":18,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":20,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":22,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":24,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":26,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":28,\"style\":\"border-right-color:#000\"},{\"type\":\"up\",\"number\":30,\"style\":\"border-left-color:#000\"},{\"type\":\"st\"},{\"type\":\"up\",\"number\":32,\"style\":\"border-right-color:#000\"},{\"type\":\"dn\",\"number\":1,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":3,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":5,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":7,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":9,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":11,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":13,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":15,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":17,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":19,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":21,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":23,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":25,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":27,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"dn\",\"number\":29,\"style\":\"border-left-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"dn\",\"number\":31,\"style\":\"border-right-color:#000;border-bottom-color:#000\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"XX\"},{\"type\":\"prohod\"},{\"type\":\"XX\"},{\"type\":\"XX\"}]}","image":null}],"insuranceCompany":[{"id":10,"shortName":"ПАО СК «Росгосстрах»","offerUrl":"https://old.rgs.ru/upload/medialibrary/c96/pravila_strakhovaniya_passazhirov_215_ru_eng.pdf","insuranceCost":150,"insuranceBenefit":1500000,"sortOrder":1},{"id":1,"shortName":"АО «СОГАЗ»","offerUrl":"https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf","insuranceCost":150,"insuranceBenefit":1500000,"sortOrder":2}],"insuranceCompanyTypes":[{"typeId":1,"insuranceTariffs":[{"id":1,"name":"Базовый","insuranceCost":150,"insuranceBenefit":1500000,"default":false,"InsurancePrograms":[{"id":2,"offerUrl":"https://old.rgs.ru/upload/medialibrary/c96/pravila_strakhovaniya_passazhirov_215_ru_eng.pdf","sortOrder":1,"shortName":"ПАО СК «Росгосстрах»"},{"id":1,"offerUrl":"https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf","sortOrder":2,"shortName":"АО «СОГАЗ»"}]}]}],"psaction":null,"childrenAge":10,"motherAndChildAge":1,"partialPayment":false,"timestamp":"20.03.2022 18:40:15.065"}"#;
-        let answer: TrainReply = serde_json::from_str(answer).unwrap();
+        let answer = load_fixture("train_info").unwrap();
+        let answer: TrainReply = serde_json::from_str(&answer).unwrap();
         let answer = answer.0;
 
         let data = vec![TrainItem {
             train_number: String::from("001А"),
+            category: TrainCategory::Firm,
             leaving_date: parse_train_date!("01.04.2022"),
             leaving_time: parse_train_time!("23:55"),
             arriving_date: parse_train_date!("02.04.2022"),
@@ -787,20 +1668,22 @@ mod tests {
                     tariff_service: String::from("766"),
                     carrier: String::from("ФПК"),
                     insurance: Some(InsuranceInfo {
-                        name: String::from("АО «СОГАЗ»"),
-                        url: String::from("https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf"),
-                        price: String::from("150"),
+                        name: "АО «СОГАЗ»".into(),
+                        url: "https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf".into(),
+                        price: "150".into(),
                     }),
                     places: String::from("002-004,006-010,012-014,016,020-028,030-032"),
                     seats: ResultList(vec![
                         SeatsInfo {
                             free_seats: 9,
                             seats_type: String::from("Нижнее"),
+                            kind: SeatKind::Lower,
                             price: String::from("3966"),
                         },
                         SeatsInfo {
                             free_seats: 15,
                             seats_type: String::from("Верхнее"),
+                            kind: SeatKind::Upper,
                             price: String::from("3966"),
                         },
                     ]),
@@ -823,20 +1706,22 @@ mod tests {
                     tariff_service: String::from("766"),
                     carrier: String::from("ФПК"),
                     insurance: Some(InsuranceInfo {
-                        name: String::from("АО «СОГАЗ»"),
-                        url: String::from("https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf"),
-                        price: String::from("150"),
+                        name: "АО «СОГАЗ»".into(),
+                        url: "https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf".into(),
+                        price: "150".into(),
                     }),
                     places: String::from("005,006,008-016,021,022,024-026,028-032"),
                     seats: ResultList(vec![
                         SeatsInfo {
                             free_seats: 9,
                             seats_type: String::from("Нижнее"),
+                            kind: SeatKind::Lower,
                             price: String::from("3966"),
                         },
                         SeatsInfo {
                             free_seats: 12,
                             seats_type: String::from("Верхнее"),
+                            kind: SeatKind::Upper,
                             price: String::from("3966"),
                         },
                     ]),
@@ -859,20 +1744,22 @@ mod tests {
                     tariff_service: String::from("766"),
                     carrier: String::from("ФПК"),
                     insurance: Some(InsuranceInfo {
-                        name: String::from("АО «СОГАЗ»"),
-                        url: String::from("https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf"),
-                        price: String::from("150"),
+                        name: "АО «СОГАЗ»".into(),
+                        url: "https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf".into(),
+                        price: "150".into(),
                     }),
                     places: String::from("002Ж,004Ж,006-008М,012-014Ж,018М,022-024Ж,026-028С,030-032М"),
                     seats: ResultList(vec![
                         SeatsInfo {
                             free_seats: 5,
                             seats_type: String::from("Нижнее"),
+                            kind: SeatKind::Lower,
                             price: String::from("3966"),
                         },
                         SeatsInfo {
                             free_seats: 13,
                             seats_type: String::from("Верхнее"),
+                            kind: SeatKind::Upper,
                             price: String::from("3966"),
                         },
                     ]),
@@ -896,15 +1783,16 @@ mod tests {
                     tariff_service: String::from("1643"),
                     carrier: String::from("ФПК"),
                     insurance: Some(InsuranceInfo {
-                        name: String::from("АО «СОГАЗ»"),
-                        url: String::from("https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf"),
-                        price: String::from("150"),
+                        name: "АО «СОГАЗ»".into(),
+                        url: "https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf".into(),
+                        price: "150".into(),
                     }),
                     places: String::from("001,002,012,013,015,016"),
                     seats: ResultList(vec![
                         SeatsInfo {
                             free_seats: 6,
                             seats_type: String::from("Нижнее"),
+                            kind: SeatKind::Lower,
                             price: String::from("7950"),
                         },
                     ]),
@@ -928,23 +1816,281 @@ mod tests {
                     tariff_service: String::from("3153"),
                     carrier: String::from("ФПК"),
                     insurance: Some(InsuranceInfo {
-                        name: String::from("АО «СОГАЗ»"),
-                        url: String::from("https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf"),
-                        price: String::from("150"),
+                        name: "АО «СОГАЗ»".into(),
+                        url: "https://direct.sogaz.ru/products/persona/rail-passenger/rules.pdf".into(),
+                        price: "150".into(),
                     }),
                     places: String::from("007,008"),
                     seats: ResultList(vec![
                         SeatsInfo {
                             free_seats: 1,
                             seats_type: String::from("Купе"),
+                            kind: SeatKind::Compartment,
                             price: String::from("23587"),
                         },
                     ]),
                 },
             ]),
+            warnings: Warnings::default(),
         }];
 
         assert!(answer.success);
         assert_eq!(answer.value, data);
     }
+
+    #[test]
+    fn from_rzd_json_test() {
+        let answer = load_fixture("train_info").unwrap();
+        assert!(matches!(TrainInfoList::from_rzd_json(&answer), Ok(Some(_))));
+
+        assert!(matches!(
+            TrainInfoList::from_rzd_json("not a json"),
+            Err(Error::DeserializeError(_))
+        ));
+    }
+
+    #[test]
+    fn total_free_seats_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+
+        // 9 lower and 15 upper berths in the first car, 70 seats in the five cars.
+        assert_eq!(train.total_free_seats(), 70);
+        assert_eq!(train.cars().as_ref()[0].total_free_seats(), 24);
+        assert_eq!(train.cars().as_ref()[4].total_free_seats(), 1);
+    }
+
+    #[test]
+    fn cars_with_class_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+
+        let cars = train.cars_with_class(&["2э"]);
+        assert!(!cars.is_empty());
+        assert!(cars.iter().all(|c| c.service_class() == "2Э"));
+        assert!(train.cars_with_class(&["9Z"]).is_empty());
+        assert!(train.cars_with_class(&[]).is_empty());
+    }
+
+    #[test]
+    fn berths_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+        let car = &train.cars().as_ref()[0];
+
+        assert_eq!(car.lower_berths_free(), 9);
+        assert_eq!(car.upper_berths_free(), 15);
+        assert!(train.has_lower_berth());
+        assert!(!TrainItem::default().has_lower_berth());
+    }
+
+    #[test]
+    fn composition_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+
+        let composition = train.composition();
+        assert_eq!(composition.train_number(), train.train_number());
+        assert_eq!(
+            composition.total_cars() as usize,
+            train.cars().as_ref().len()
+        );
+
+        let q = CarListSearch::new(2004000, 2000000, (2022, 4, 1), (0, 11), "119А").unwrap();
+        let list =
+            RzdClientInterface::<ResultList<TrainComposition>>::deserialize_reply_data(&q, &answer)
+                .unwrap()
+                .unwrap();
+        assert_eq!(list.as_ref()[0], composition);
+    }
+
+    #[test]
+    fn composition_diff_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+        assert!(train.composition_diff(train).is_empty());
+
+        let mut other = train.clone();
+        let removed = other.cars_mut().0.remove(0);
+        other.cars_mut().0[0].service_class = String::from("2Ш");
+        let mut added = removed.clone();
+        added.number = String::from("99");
+        other.cars_mut().0.push(added);
+
+        let diff = train.composition_diff(&other);
+        assert_eq!(diff.train_number(), train.train_number());
+        assert_eq!(diff.added().len(), 1);
+        assert_eq!(diff.added()[0].number(), "99");
+        assert_eq!(diff.removed()[0].number(), removed.number());
+        assert_eq!(diff.changed().len(), 1);
+        assert_eq!(diff.changed()[0].1.service_class, "2Ш");
+    }
+
+    #[test]
+    fn invalid_route_test() {
+        assert!(matches!(
+            TrainSearch::new(2004000, 2004000, (2022, 4, 1), (0, 11), "119А"),
+            Err(Error::InvalidRoute { .. })
+        ));
+        assert!(matches!(
+            CarListSearch::new(0, 2000000, (2022, 4, 1), (0, 11), "119А"),
+            Err(Error::InvalidRoute { .. })
+        ));
+
+        let q = TrainScheduleSearch::new(2000000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        assert!(matches!(
+            RzdClient::<RouteList>::get(&q),
+            Err(Error::InvalidRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn train_search_builder_test() {
+        let q = TrainSearch::builder()
+            .with_route(2004000, 2000000)
+            .with_date((2022, 4, 1))
+            .with_train_number("119А")
+            .with_lang(Lang::En)
+            .build()
+            .unwrap();
+        assert_eq!(q.leaving_time(), TrainTime::try_new(0, 0).unwrap());
+        assert_eq!(q.lang(), Lang::En);
+
+        let builder = TrainSearch::builder()
+            .with_route(2004000, 2000000)
+            .with_train_number("119А")
+            .with_time((0, 11));
+        assert!(matches!(
+            builder.clone().build(),
+            Err(Error::MissingParameter("date"))
+        ));
+        assert_eq!(
+            builder.clone().with_date((2022, 4, 1)).build().unwrap(),
+            TrainSearch::new(2004000, 2000000, (2022, 4, 1), (0, 11), "119А").unwrap()
+        );
+        assert!(matches!(
+            builder
+                .with_train_number(" ")
+                .with_date((2022, 4, 1))
+                .build(),
+            Err(Error::EmptyTrainNumber)
+        ));
+        assert!(matches!(
+            TrainSearch::builder().with_date((2022, 4, 1)).build(),
+            Err(Error::InvalidRoute { .. })
+        ));
+    }
+
+    #[test]
+    fn car_order_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let mut trains = parse_train_reply(&answer).unwrap().unwrap();
+
+        trains.sort_cars(CarOrder::FreeSeats);
+        let train = &trains.as_ref()[0];
+        let seats: Vec<u32> = train.cars().iter().map(|c| c.total_free_seats()).collect();
+        assert!(seats.windows(2).all(|w| w[0] >= w[1]));
+
+        let mut train = train.clone();
+        train.sort_cars(CarOrder::Price);
+        let prices: Vec<Option<u32>> = train.cars().iter().map(|c| c.min_price()).collect();
+        assert!(prices.windows(2).all(|w| w[0] <= w[1]));
+
+        let offers = train.offers();
+        assert_eq!(offers.as_ref().len(), 4);
+        assert_eq!(offers.as_ref()[0].type_loc(), "Купе");
+        assert_eq!(offers.as_ref()[0].service_class().as_str(), "2Э");
+        assert_eq!(offers.as_ref()[0].cars(), 2);
+        assert_eq!(offers.as_ref()[0].free_seats(), 42);
+        assert_eq!(offers.as_ref()[0].min_price(), Some(3966));
+        assert_eq!(offers.as_ref()[3].min_price(), Some(23587));
+
+        train.sort_cars(CarOrder::Number);
+        let numbers: Vec<&str> = train.cars().iter().map(|c| c.number()).collect();
+        assert_eq!(numbers, ["01", "02", "03", "08", "16"]);
+
+        assert_eq!(
+            train.grouped_cars().to_string(),
+            "Купе 2Э ×2 вагона, 42 места от 3966\n\
+            Купе 2Т ×1 вагон, 21 место от 3966\n\
+            СВ 1Э ×1 вагон, 6 мест от 7950\n\
+            Люкс 1А ×1 вагон, 1 место от 23587\n"
+        );
+    }
+
+    #[test]
+    fn request_url_test() {
+        let q = TrainSearch::new(2004000, 2000000, (2022, 4, 1), (0, 11), " 020у ").unwrap();
+        let url = q.request_url().unwrap();
+
+        assert_eq!(url.host_str(), Some("pass.rzd.ru"));
+        assert!(url.query().unwrap().contains("tnum0=020%D0%A3"));
+        assert!(url
+            .query_pairs()
+            .any(|(n, v)| n == "tnum0" && v == q.train_number()));
+
+        assert!(url
+            .query_pairs()
+            .any(|(n, v)| n == "dt0" && v == "01.04.2022"));
+        assert!(url.query_pairs().any(|(n, v)| n == "time0" && v == "00:11"));
+        assert_eq!(CarListSearch::from(q.clone()).request_url().unwrap(), url);
+
+        // The number is not chosen by the direction, 119А runs to Moscow.
+        let q = TrainSearch::new(2004001, 2001025, (2022, 4, 1), (0, 11), "119А/120А").unwrap();
+        assert_eq!(q.train_number(), "119А/120А");
+    }
+
+    #[test]
+    fn insurances_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+
+        let insurances = train.insurances();
+        assert_eq!(insurances.len(), 1);
+        assert_eq!(insurances[0].name(), "АО «СОГАЗ»");
+
+        let cars = train.cars().as_ref();
+        let (a, b) = (cars[0].insurance(), cars[1].insurance());
+        assert!(Arc::ptr_eq(
+            &a.as_ref().unwrap().name,
+            &b.as_ref().unwrap().name
+        ));
+    }
+
+    #[test]
+    fn malformed_entries_test() {
+        let answer = r#"{"result":"OK","lst":[{"result":"OK","number":"119А","cars":[
+            {"cnumber":"01","tariff2":null,"tariffServ":null,"typeLoc":"Купе","seats":[{"free":1,"label":"Купе"},{"free":"x"}]},
+            {"cnumber":"02","tariff2":null,"tariffServ":null,"seats":{"free":1}},
+            {"cnumber":"03","tariff2":null,"tariffServ":null,"typeLoc":"Плацкартный","seats":[{"free":2,"label":"Плацкарт"}]}
+        ]}]}"#;
+        let trains = parse_train_reply(answer).unwrap().unwrap();
+        let train = &trains.as_ref()[0];
+
+        assert_eq!(train.cars().as_ref().len(), 2);
+        assert_eq!(train.total_free_seats(), 3);
+        assert_eq!(train.warnings().as_ref().len(), 2);
+        assert!(train.warnings().as_ref()[1].starts_with("вагон 01:"));
+    }
+
+    #[test]
+    fn optional_fields_test() {
+        let answer = load_fixture("train_info").unwrap();
+        let trains = parse_train_reply(&answer).unwrap().unwrap();
+        let cars: Vec<&TrainCar> = trains.iter().flat_map(|t| t.cars().iter()).collect();
+
+        let one_tariff = cars.iter().find(|c| c.price2().is_empty()).unwrap();
+        assert_eq!(one_tariff.price2_opt(), None);
+        assert_eq!(one_tariff.price1_opt(), Some(one_tariff.price1()));
+        assert_eq!(one_tariff.carrier_opt(), Some("ФПК"));
+        for car in cars {
+            assert_eq!(car.price2_opt().is_none(), car.price2().trim().is_empty());
+        }
+    }
 }
@@ -1,14 +1,27 @@
-use reqwest::blocking::Response;
-use serde::Serialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::Duration;
 
-use crate::client::{RzdClientInterface, RzdQueryType, RzdRequestId};
-use crate::{error::Error, Result};
+use crate::client::{
+    booking_url, request_url, RequestOptions, RzdClient, RzdClientInterface, RzdLayer, RzdQuery,
+    RzdQueryType, RzdRequestId,
+};
+use crate::TripStopsSearch;
+use crate::{error::Error, error::RzdErrors, non_empty, Result, Url};
 use crate::{
-    ReplyResult, ResultList, RouteDirection, RzdStationCode, ShowSeats, TrainDate, TrainTime,
-    TrainType,
+    Carrier, DayPart, Lang, ReplyResult, ResultList, RouteDirection, RzdStationCode, SeatKind,
+    ShowSeats, StationName, TrainCategory, TrainDate, TrainTime, TrainType, TripDuration, Warnings,
 };
 
+// Pause between the searches of the nearest date with free seats.
+const FIND_DELAY: Duration = Duration::from_millis(1000);
+
+// The shortest name matching the beginning of the name of the leaving or the arriving station.
+const MIN_STATION_PREFIX: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Schedule of trains search by departure and arrival station codes
 /// and departure date.
 pub struct TrainScheduleSearch {
@@ -17,32 +30,98 @@ pub struct TrainScheduleSearch {
     leaving_date: TrainDate,
     train_type: TrainType,
     check_seats: ShowSeats,
+    #[serde(default, skip_serializing_if = "crate::is_default_lang")]
+    lang: Lang,
 }
 
 impl TrainScheduleSearch {
     /// Takes departure and arrival station codes, departure date.
     /// It needs to specify a train type, especially, for suburban trains searching.
     /// Search not all but only free seats would be preferred.
+    /// If not only free seats are searched, the sold out trains are included too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::{TrainScheduleSearch, TrainType};
+    /// #
+    /// let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+    /// ```
     pub fn new(
-        leaving_code: RzdStationCode,
-        arriving_code: RzdStationCode,
-        leaving_date: TrainDate,
+        leaving_code: impl Into<RzdStationCode>,
+        arriving_code: impl Into<RzdStationCode>,
+        leaving_date: impl Into<TrainDate>,
         train_type: TrainType,
         free_seats_only: bool,
     ) -> Self {
         let check_seats = match free_seats_only {
             true => ShowSeats::FreeOnly,
-            false => ShowSeats::All,
+            false => ShowSeats::AllIncludingSoldOut,
         };
 
         TrainScheduleSearch {
-            leaving_code,
-            arriving_code,
-            leaving_date,
+            leaving_code: leaving_code.into(),
+            arriving_code: arriving_code.into(),
+            leaving_date: leaving_date.into(),
             train_type,
             check_seats,
+            lang: Lang::default(),
         }
     }
+
+    /// Returns the RZD code of the departure station.
+    #[inline]
+    pub fn leaving_code(&self) -> RzdStationCode {
+        self.leaving_code
+    }
+
+    /// Returns the RZD code of the arrival station.
+    #[inline]
+    pub fn arriving_code(&self) -> RzdStationCode {
+        self.arriving_code
+    }
+
+    /// Returns the departure date.
+    #[inline]
+    pub fn leaving_date(&self) -> TrainDate {
+        self.leaving_date
+    }
+
+    /// Returns the type of the trains.
+    #[inline]
+    pub fn train_type(&self) -> TrainType {
+        self.train_type
+    }
+
+    /// Returns what trains are searched depending on the seats availability.
+    #[inline]
+    pub fn show_seats(&self) -> ShowSeats {
+        self.check_seats
+    }
+
+    /// Sets what trains should be searched depending on the seats availability.
+    pub fn with_show_seats(mut self, check_seats: ShowSeats) -> Self {
+        self.check_seats = check_seats;
+        self
+    }
+
+    /// Returns the language of the names returned by the server.
+    #[inline]
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    /// Sets the language of the names returned by the server, e.g. `Lang::En` for Latin names.
+    pub fn with_lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Returns the URL of the first request made for the search to inspect the encoded parameters.
+    /// Fails with `Error::ParseUrlError` if the search makes an invalid URL.
+    pub fn request_url(&self) -> Result<Url> {
+        request_url::<ResultList<Route>, _>(self)
+    }
 }
 
 impl RzdClientInterface<ResultList<Route>> for TrainScheduleSearch {
@@ -53,60 +132,55 @@ impl RzdClientInterface<ResultList<Route>> for TrainScheduleSearch {
         }
     }
 
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::TrainSchedule
+    }
+
     fn request_id(&self) -> String {
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5827\
-            &dir={}\
-            &tfl={}\
-            &checkSeats={}\
-            &code0={}\
-            &dt0={}\
-            &code1={}",
-            RouteDirection::OneWay as u8,
-            self.train_type as u8,
-            match self.check_seats {
-                ShowSeats::All => "0&withoutSeats=y",
-                _ => "1",
-            },
-            self.leaving_code,
-            self.leaving_date,
-            self.arriving_code
-        )
+        let mut query = RzdQuery::new(RzdLayer::TrainSchedule)
+            .lang(self.lang)
+            .param("dir", RouteDirection::OneWay as u8)
+            .param("tfl", self.train_type as u8);
+
+        query = match self.check_seats {
+            ShowSeats::FreeOnly => query.param("checkSeats", 1),
+            ShowSeats::All => query.param("checkSeats", 0),
+            ShowSeats::AllIncludingSoldOut => {
+                query.param("checkSeats", 0).param("withoutSeats", "y")
+            }
+        };
+
+        query
+            .param("code0", self.leaving_code)
+            .param("dt0", self.leaving_date)
+            .param("code1", self.arriving_code)
+            .to_url()
     }
 
     fn request_data(&self, id: RzdRequestId) -> String {
         if self.query_type() == RzdQueryType::Simple {
-            return format!(
-                "https://pass.rzd.ru/timetable/public/ru\
-                ?layer_id=5827\
-                &dir={}\
-                &tfl={}\
-                &code0={}\
-                &dt0={}\
-                &code1={}",
-                RouteDirection::OneWay as u8,
-                self.train_type as u8,
-                self.leaving_code,
-                self.leaving_date,
-                self.arriving_code
-            );
+            return RzdQuery::new(RzdLayer::TrainSchedule)
+                .lang(self.lang)
+                .param("dir", RouteDirection::OneWay as u8)
+                .param("tfl", self.train_type as u8)
+                .param("code0", self.leaving_code)
+                .param("dt0", self.leaving_date)
+                .param("code1", self.arriving_code)
+                .to_url();
         }
 
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5827\
-            &rid={}",
-            id
-        )
+        RzdQuery::new(RzdLayer::TrainSchedule)
+            .lang(self.lang)
+            .param("rid", id)
+            .to_url()
     }
 
-    fn deserialize_reply_id(&self, response: Response) -> Result<Option<RzdRequestId>> {
+    fn deserialize_reply_id(&self, body: &str) -> Result<Option<RzdRequestId>> {
         if self.query_type() == RzdQueryType::Simple {
             return Err(Error::UnsupportedOperation);
         }
 
-        let reply: RidReply = match response.json() {
+        let reply: RidReply = match serde_json::from_str(body) {
             Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
             Ok(r) => r,
         };
@@ -120,37 +194,244 @@ impl RzdClientInterface<ResultList<Route>> for TrainScheduleSearch {
         Ok(Some(reply.value))
     }
 
-    fn deserialize_reply_data(&self, response: Response) -> Result<Option<ResultList<Route>>> {
-        let reply: ScheduleReply = match response.json() {
-            Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
-            Ok(r) => r,
-        };
-        let reply = reply.0;
-        trace!("reply: {:?}", reply);
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<ResultList<Route>>> {
+        parse_schedule_reply(body)
+    }
 
-        if !reply.success {
-            return Err(reply.error);
+    fn validate(&self) -> Result<()> {
+        RzdStationCode::check_route(self.leaving_code, self.arriving_code)
+    }
+}
+
+/// Parses a raw reply of the schedule of trains layer, e.g. a captured or logged payload.
+/// Returns a typed error for any malformed or failed reply, `None` if nothing is found.
+pub fn parse_schedule_reply(body: &str) -> Result<Option<ResultList<Route>>> {
+    let reply: ScheduleReply = match serde_json::from_str(body) {
+        Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
+        Ok(r) => r,
+    };
+    let reply = reply.0;
+    trace!("reply: {:?}", reply);
+
+    if !reply.success {
+        return Err(reply.error);
+    }
+
+    if reply.value.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ResultList(reply.value)))
+}
+
+impl ResultList<Route> {
+    /// Parses a raw reply of the schedule of trains, e.g. a logged one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::RouteList;
+    /// #
+    /// let body = r#"{"result":"RID","RID":17872768326}"#;
+    /// assert!(RouteList::from_rzd_json(body).is_err());
+    /// ```
+    pub fn from_rzd_json(body: &str) -> Result<Option<Self>> {
+        parse_schedule_reply(body)
+    }
+
+    /// Returns the errors of the routes with no trains found, if some routes are failed.
+    pub fn partial_errors(&self) -> Option<RzdErrors> {
+        let errors: Vec<String> = self
+            .0
+            .iter()
+            .flat_map(|r| r.errors.clone().to_vec())
+            .collect();
+
+        if errors.is_empty() {
+            return None;
         }
 
-        if reply.value.is_empty() {
-            return Ok(None);
+        Some(RzdErrors::new(errors))
+    }
+
+    /// Returns the advisory messages of all routes.
+    pub fn warnings(&self) -> Warnings {
+        Warnings::new(
+            self.0
+                .iter()
+                .flat_map(|r| r.warnings.as_ref().to_vec())
+                .collect(),
+        )
+    }
+
+    /// Returns the options of the schedule search, the same for all routes of the reply.
+    pub fn schedule_options(&self) -> Option<ScheduleOptions> {
+        self.0.first().map(|r| r.options)
+    }
+
+    /// Sorts the trains of every route by the duration of the trip, the shortest first.
+    ///
+    /// The duration is taken as `TrainInfo::duration()` returns it, i.e. computed from
+    /// the departure and the arrival when the server didn't send it. The trains with
+    /// the same duration are ordered by the departure, the trains with unknown duration
+    /// go last in the order they had.
+    pub fn sort_by_duration(&mut self) {
+        for route in self.0.iter_mut() {
+            route.trains.0.sort_by_key(|t| {
+                let leaving = (t.leaving_date.map(|d| d.0), t.leaving_time.map(|t| t.0));
+                match t.duration() {
+                    Some(d) => (false, Some(d), leaving),
+                    None => (true, None, (None, None)),
+                }
+            });
+        }
+    }
+
+    /// Returns the trains of the routes grouped by the part of the day they leave in,
+    /// the trains with unknown departure time are skipped.
+    pub fn bucket_by_daypart(&self) -> BTreeMap<DayPart, Vec<&TrainInfo>> {
+        let mut buckets: BTreeMap<DayPart, Vec<&TrainInfo>> = BTreeMap::new();
+        for train in self.iter().flat_map(|r| r.trains.iter()) {
+            if let Some(time) = train.leaving_time {
+                buckets.entry(time.day_part()).or_default().push(train);
+            }
         }
+        buckets
+    }
 
-        Ok(Some(ResultList(reply.value)))
+    /// Returns the free seats and the lowest prices of all trains of the routes
+    /// by the kinds of the seats.
+    pub fn availability_matrix(&self) -> AvailabilityMatrix {
+        let mut matrix = AvailabilityMatrix::default();
+        for train in self.iter().flat_map(|r| r.trains.iter()) {
+            let row = matrix.rows.entry(train.train_number.clone()).or_default();
+            for seats in train.seats.iter() {
+                let cell = row.entry(seats.kind.to_string()).or_default();
+                cell.free_seats += seats.free_seats;
+                cell.min_price = match (cell.min_price, seats.price) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        matrix
     }
+
+    // Returns the first train with free seats of the kind, any kind if it is not set.
+    fn first_with_seats(&self, kind: Option<&SeatKind>) -> Option<&TrainInfo> {
+        self.iter().flat_map(|r| r.trains.iter()).find(|t| {
+            t.seats.iter().any(|s| {
+                s.free_seats > 0
+                    && match kind {
+                        Some(k) => s.kind == *k,
+                        None => true,
+                    }
+            })
+        })
+    }
+}
+
+/// Walks forward day by day from the date until a train with free seats of the kind appears,
+/// e.g. when the route is nearly sold out. Any kind of seats is searched if it is not set.
+/// The searches are made with a pause not to overload the server.
+///
+/// Returns the date and the train, `None` if nothing is found within `horizon_days`.
+///
+/// # Errors
+///
+/// The method fails if there was an error while processing request
+/// or received data couldn't be deserialized.
+/// The errors of the server, e.g. no trains on the date, are skipped.
+/// The search stops at the first date beyond the period of the advance sale.
+pub fn find_first_available(
+    from: impl Into<RzdStationCode>,
+    to: impl Into<RzdStationCode>,
+    starting_date: impl Into<TrainDate>,
+    horizon_days: u32,
+    kind: Option<SeatKind>,
+) -> Result<Option<(TrainDate, TrainInfo)>> {
+    find_first_available_with(
+        from,
+        to,
+        starting_date,
+        horizon_days,
+        kind,
+        &RequestOptions::default(),
+    )
+}
+
+/// Does the same as `find_first_available` making the requests with the options,
+/// the pauses between the searches are made by the sleeper of the options.
+///
+/// # Errors
+///
+/// The method fails if there was an error while processing request
+/// or received data couldn't be deserialized.
+pub fn find_first_available_with(
+    from: impl Into<RzdStationCode>,
+    to: impl Into<RzdStationCode>,
+    starting_date: impl Into<TrainDate>,
+    horizon_days: u32,
+    kind: Option<SeatKind>,
+    options: &RequestOptions,
+) -> Result<Option<(TrainDate, TrainInfo)>> {
+    let (from, to) = (from.into(), to.into());
+    let mut date = starting_date.into();
+
+    for day in 0..horizon_days {
+        if day > 0 {
+            options.sleeper().sleep(FIND_DELAY);
+            date = TrainDate(date.0 + chrono::Duration::days(1));
+        }
+
+        let q = TrainScheduleSearch::new(from, to, date, TrainType::AllTrains, true);
+        let routes = match RzdClient::<ResultList<Route>>::get_with(&q, options.clone()) {
+            Ok(Some(r)) => r,
+            Ok(None) => continue,
+            Err(e) => match e.root() {
+                Error::RzdError(_) => continue,
+                // The following dates are not on sale either.
+                Error::OutsideSaleHorizon { .. } => break,
+                _ => return Err(e),
+            },
+        };
+
+        if let Some(train) = routes.first_with_seats(kind.as_ref()) {
+            info!("seats found on {}", date);
+            return Ok(Some((date, train.clone())));
+        }
+    }
+
+    Ok(None)
 }
 
 #[derive(Debug)]
 struct RidReply(ReplyResult<RzdRequestId>);
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Number of free seats on the train and seat type info.
 pub struct SeatsInfo {
     free_seats: u32,
     seats_type: String,
+    kind: SeatKind,
+    price: Option<u32>,
 }
 
 impl SeatsInfo {
+    pub(crate) fn new(
+        free_seats: u32,
+        seats_type: String,
+        kind: SeatKind,
+        price: Option<u32>,
+    ) -> Self {
+        SeatsInfo {
+            free_seats,
+            seats_type,
+            kind,
+            price,
+        }
+    }
+
     /// Returns the number of free seats.
     #[inline]
     pub fn free_seats_number(&self) -> u32 {
@@ -162,24 +443,161 @@ impl SeatsInfo {
     pub fn seats_type(&self) -> &str {
         &self.seats_type
     }
+
+    /// Returns the kind of the seats.
+    #[inline]
+    pub fn kind(&self) -> &SeatKind {
+        &self.kind
+    }
+
+    /// Returns the lowest price of the seats in rubles.
+    #[inline]
+    pub fn price(&self) -> Option<u32> {
+        self.price
+    }
 }
 
 impl fmt::Display for SeatsInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {}", self.free_seats, self.seats_type)
+        write!(f, "{} {}", self.free_seats, self.seats_type)?;
+        if let Some(p) = self.price {
+            write!(f, " от {} руб.", p)?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// State of the ticket sale for the train.
+pub enum SaleState {
+    /// There are free seats on the train.
+    OnSale,
+    /// There are no free seats on the long-distance train.
+    SoldOut,
+    /// The sale is not opened yet, the server reports when it opens.
+    NotYetOnSale,
+    /// The state is unknown, e.g. for the suburban trains with no seats info.
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Stops of the suburban train as the schedule describes them.
+pub enum StopPolicy {
+    /// The train stops at all stations, "Везде".
+    Everywhere,
+    /// The train stops at all stations except the listed ones, "Везде, кроме: ...".
+    Except(Vec<String>),
+    /// The train stops at the listed stations only.
+    Only(Vec<String>),
+    /// The train has no stops on the way, "Без остановок".
+    NonStop,
+    /// The stops are not described.
+    Unknown,
+}
+
+impl StopPolicy {
+    /// Parses the description of the stops of the schedule.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rzd_trains::StopPolicy;
+    /// #
+    /// let p = StopPolicy::parse("Везде, кроме: МГА, ПУПЫШЕВО");
+    ///
+    /// assert_eq!(p, StopPolicy::Except(vec!["МГА".to_string(), "ПУПЫШЕВО".to_string()]));
+    /// assert_eq!(StopPolicy::parse("МГА"), StopPolicy::Only(vec!["МГА".to_string()]));
+    /// ```
+    pub fn parse(stops: &str) -> Self {
+        let stops = stops.trim().trim_end_matches('.').trim();
+        let lower = stops.to_lowercase();
+
+        if lower.is_empty() {
+            return StopPolicy::Unknown;
+        }
+        if lower == "везде" || lower == "со всеми остановками" {
+            return StopPolicy::Everywhere;
+        }
+        if lower == "без остановок" {
+            return StopPolicy::NonStop;
+        }
+        if lower.starts_with("везде") {
+            return match stops.split_once(':') {
+                Some((_, names)) => StopPolicy::Except(split_stations(names)),
+                None => StopPolicy::Everywhere,
+            };
+        }
+
+        StopPolicy::Only(split_stations(stops))
+    }
+
+    /// Returns the stations listed in the description, the skipped or the only ones.
+    pub fn stations(&self) -> &[String] {
+        match self {
+            StopPolicy::Except(s) | StopPolicy::Only(s) => s,
+            _ => &[],
+        }
+    }
+}
+
+// Makes the name of the station comparable, in upper case
+// without the spaces and the punctuation, "Ё" is taken as "Е".
+fn station_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .map(|c| if c == 'Ё' { 'Е' } else { c })
+        .collect()
+}
+
+// Splits the name of the station into the words comparable like `station_key`.
+fn station_words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(station_key)
+        .collect()
+}
+
+// Splits the list of the stations separated by the commas.
+fn split_stations(names: &str) -> Vec<String> {
+    names
+        .split(',')
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+impl fmt::Display for StopPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopPolicy::Everywhere => write!(f, "Везде"),
+            StopPolicy::Except(s) => write!(f, "Везде, кроме: {}", s.join(", ")),
+            StopPolicy::Only(s) => write!(f, "{}", s.join(", ")),
+            StopPolicy::NonStop => write!(f, "Без остановок"),
+            StopPolicy::Unknown => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Train info.
 pub struct TrainInfo {
     train_number: String,
     train_brand: String,
-    train_type: String,
+    #[serde(default)]
+    carrier: Carrier,
+    train_type: Option<TrainType>,
+    #[serde(default)]
+    category: TrainCategory,
     leaving_route: String,
     leaving_route_code: RzdStationCode,
     arriving_route: String,
     arriving_route_code: RzdStationCode,
+    #[serde(default)]
+    leaving_code: RzdStationCode,
+    #[serde(default)]
+    arriving_code: RzdStationCode,
     leaving_station: String,
     leaving_date: Option<TrainDate>,
     leaving_time: Option<TrainTime>,
@@ -187,7 +605,17 @@ pub struct TrainInfo {
     arriving_date: Option<TrainDate>,
     arriving_time: Option<TrainTime>,
     trip_duration: Option<TrainTime>,
+    #[serde(default)]
+    duration: Option<TripDuration>,
     stops: String,
+    #[serde(default)]
+    stops_full: String,
+    sale_depth: Option<u32>,
+    #[serde(default)]
+    sold_out: bool,
+    #[serde(default)]
+    sale_state: SaleState,
+    train_id: Option<u64>,
     seats: ResultList<SeatsInfo>,
 }
 
@@ -204,10 +632,34 @@ impl TrainInfo {
         &self.train_brand
     }
 
-    /// Returns the train type.
+    /// Returns the brand name of the train, `None` if the train has no brand.
+    pub fn brand_opt(&self) -> Option<&str> {
+        non_empty(&self.train_brand)
+    }
+
+    /// Returns the name of the carrier.
+    #[deprecated(note = "the server returns the carrier here, use `carrier()` instead")]
     #[inline]
     pub fn train_type(&self) -> &str {
-        &self.train_type
+        self.carrier.name()
+    }
+
+    /// Returns the carrier of the train.
+    #[inline]
+    pub fn carrier(&self) -> &Carrier {
+        &self.carrier
+    }
+
+    /// Returns the type of the train, a long-distance or a suburban one.
+    #[inline]
+    pub fn kind(&self) -> Option<TrainType> {
+        self.train_type
+    }
+
+    /// Returns the category of the train.
+    #[inline]
+    pub fn category(&self) -> TrainCategory {
+        self.category
     }
 
     /// Returns the name of the departure station along the train route.
@@ -234,12 +686,40 @@ impl TrainInfo {
         self.arriving_route_code
     }
 
+    /// Returns the RZD code of the departure station.
+    #[inline]
+    pub fn leaving_station_code(&self) -> RzdStationCode {
+        self.leaving_code
+    }
+
+    /// Returns the RZD code of the arrival station.
+    #[inline]
+    pub fn arriving_station_code(&self) -> RzdStationCode {
+        self.arriving_code
+    }
+
+    /// Returns the link to buy a ticket for the train on rzd.ru,
+    /// `None` if the stations or the departure date are unknown.
+    pub fn booking_url(&self) -> Option<Url> {
+        booking_url(
+            self.leaving_code,
+            self.arriving_code,
+            self.leaving_date?,
+            &self.train_number,
+        )
+    }
+
     /// Returns the name of the departure station.
     #[inline]
     pub fn leaving_station(&self) -> &str {
         &self.leaving_station
     }
 
+    /// Returns the title-cased name of the departure station with the terminal split off.
+    pub fn leaving_station_short(&self) -> StationName {
+        StationName::parse(&self.leaving_station)
+    }
+
     /// Returns the departure date of the train.
     #[inline]
     pub fn leaving_date(&self) -> Option<TrainDate> {
@@ -258,6 +738,11 @@ impl TrainInfo {
         &self.arriving_station
     }
 
+    /// Returns the title-cased name of the arrival station with the terminal split off.
+    pub fn arriving_station_short(&self) -> StationName {
+        StationName::parse(&self.arriving_station)
+    }
+
     /// Returns the arrival date of the train.
     #[inline]
     pub fn arriving_date(&self) -> Option<TrainDate> {
@@ -270,18 +755,185 @@ impl TrainInfo {
         self.arriving_time
     }
 
-    /// Returns the duration of the trip.
+    /// Returns the duration of the trip as a time of day,
+    /// `None` is returned for the trips longer than a day, use `duration()` for them.
     #[inline]
     pub fn trip_duration(&self) -> Option<TrainTime> {
         self.trip_duration
     }
 
+    /// Returns the duration of the trip including the trips longer than a day.
+    /// If the server didn't send it, the duration is computed from the departure
+    /// and the arrival dates and times, which are given in the same (Moscow) time,
+    /// `None` is returned if any of them is unknown too.
+    pub fn duration(&self) -> Option<TripDuration> {
+        if self.duration.is_some() {
+            return self.duration;
+        }
+
+        let leaving = self.leaving_date?.0.and_time(self.leaving_time?.0);
+        let arriving = self.arriving_date?.0.and_time(self.arriving_time?.0);
+        let minutes = (arriving - leaving).num_minutes();
+        u32::try_from(minutes).ok().map(TripDuration::from_minutes)
+    }
+
     /// Returns the train stops.
     #[inline]
     pub fn stops(&self) -> &str {
         &self.stops
     }
 
+    /// Returns the train stops, `None` if they are not described.
+    pub fn stops_opt(&self) -> Option<&str> {
+        non_empty(&self.stops)
+    }
+
+    /// Returns the full list of the stops of the suburban train,
+    /// if the server shortens `stops`, or an empty string otherwise.
+    #[inline]
+    pub fn stops_full(&self) -> &str {
+        &self.stops_full
+    }
+
+    /// Returns the full list of the stops of the suburban train,
+    /// `None` if the server doesn't shorten `stops`.
+    pub fn stops_full_opt(&self) -> Option<&str> {
+        non_empty(&self.stops_full)
+    }
+
+    /// Returns the stops of the train parsed from the description, see `stops`.
+    /// The full list of the stops is taken if there is one.
+    pub fn stop_policy(&self) -> StopPolicy {
+        StopPolicy::parse(self.stops_full_opt().unwrap_or(&self.stops))
+    }
+
+    /// Returns true if the train stops at the station given by the name or the code,
+    /// the stations the train leaves and arrives at included.
+    /// The code is matched to the leaving and the arriving stations only.
+    /// The names are matched regardless of the case, the spaces and the punctuation,
+    /// e.g. "ост. пункт 95 км" matches "ОСТ.ПУНКТ 95КМ".
+    /// The leaving and the arriving stations are also matched by their first whole words,
+    /// e.g. "Санкт-Петербург" matches "САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)".
+    ///
+    /// Returns `None` if it is not known, e.g. the stops are not described
+    /// or the code is not of the leaving or the arriving station.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use rzd_trains::{RzdClient, RouteList, TrainScheduleSearch, TrainType};
+    /// #
+    /// let q = TrainScheduleSearch::new(2004001, 2005283, (2022, 4, 1), TrainType::ElectricTrain, false);
+    /// let routes = RzdClient::<RouteList>::get(&q).unwrap().unwrap();
+    ///
+    /// let mine = routes
+    ///     .iter()
+    ///     .flat_map(|r| r.trains().iter())
+    ///     .filter(|t| t.stops_at("Мга") == Some(true));
+    /// ```
+    pub fn stops_at(&self, name_or_code: &str) -> Option<bool> {
+        let station = name_or_code.trim();
+        if let Ok(code) = station.parse::<u32>() {
+            let endpoint = code == self.leaving_code.0 || code == self.arriving_code.0;
+            return endpoint.then_some(true);
+        }
+
+        let words = station_words(station);
+        let station = station_key(station);
+        if station.is_empty() {
+            return None;
+        }
+        let prefix = station.chars().count() >= MIN_STATION_PREFIX;
+        let endpoint = |name: &str| {
+            station_key(name) == station || (prefix && station_words(name).starts_with(&words))
+        };
+        if endpoint(&self.leaving_station) || endpoint(&self.arriving_station) {
+            return Some(true);
+        }
+
+        let listed = |names: &[String]| names.iter().any(|n| station_key(n) == station);
+        match self.stop_policy() {
+            StopPolicy::Everywhere => Some(true),
+            StopPolicy::Except(names) => Some(!listed(&names)),
+            StopPolicy::Only(names) => Some(listed(&names)),
+            StopPolicy::NonStop => Some(false),
+            StopPolicy::Unknown => None,
+        }
+    }
+
+    /// Returns how many days ahead tickets are sold for the train.
+    #[inline]
+    pub fn sale_depth_days(&self) -> Option<u32> {
+        self.sale_depth
+    }
+
+    /// Returns true if there are no seats on the long-distance train.
+    /// Such trains are found with `ShowSeats::AllIncludingSoldOut` only.
+    /// It is false if the server doesn't report the type of the train.
+    #[inline]
+    pub fn sold_out(&self) -> bool {
+        self.sold_out
+    }
+
+    /// Returns the state of the ticket sale for the train.
+    #[inline]
+    pub fn sale_state(&self) -> SaleState {
+        self.sale_state
+    }
+
+    /// Returns the state of the ticket sale for the train on the day,
+    /// the train without seats leaving later than the sale depth is not on sale yet.
+    pub fn sale_state_on(&self, today: impl Into<TrainDate>) -> SaleState {
+        if self.sale_state == SaleState::OnSale {
+            return SaleState::OnSale;
+        }
+
+        let today: NaiveDate = today.into().into();
+        match (self.leaving_date, self.sale_depth) {
+            (Some(d), Some(depth)) if d.0 > today + chrono::Duration::days(depth as i64) => {
+                SaleState::NotYetOnSale
+            }
+            _ => self.sale_state,
+        }
+    }
+
+    /// Returns the id of the suburban train.
+    #[inline]
+    pub fn train_id(&self) -> Option<u64> {
+        self.train_id
+    }
+
+    /// Returns the search of the full stops and times of the suburban train.
+    /// Long-distance trains have no train id, use `TripStopsSearch` for them.
+    pub fn suburban_details(&self) -> Option<TripStopsSearch> {
+        let train_id = self.train_id?;
+        let train_date = self.leaving_date?;
+        TripStopsSearch::new(&self.train_number, train_date)
+            .ok()
+            .map(|q| q.with_train_id(train_id))
+    }
+
+    /// Returns the number of free seats of all types on the train.
+    pub fn total_free_seats(&self) -> u32 {
+        self.seats.iter().map(|s| s.free_seats).sum()
+    }
+
+    /// Returns the main info about the train to show it in one line.
+    pub fn summary(&self) -> TrainSummary {
+        TrainSummary {
+            train_number: self.train_number.clone(),
+            leaving_station: self.leaving_station.clone(),
+            arriving_station: self.arriving_station.clone(),
+            leaving_date: self.leaving_date,
+            leaving_time: self.leaving_time,
+            arriving_date: self.arriving_date,
+            arriving_time: self.arriving_time,
+            duration: self.duration(),
+            min_price: self.seats.iter().filter_map(|s| s.price).min(),
+            free_seats: self.total_free_seats(),
+        }
+    }
+
     /// Returns the immutable list of seats on the train.
     #[inline]
     pub fn seats(&self) -> &ResultList<SeatsInfo> {
@@ -299,7 +951,7 @@ impl fmt::Display for TrainInfo {
         write!(
             f,
             "Поезд № \"{}\" {} {}\n",
-            self.train_number, self.train_brand, self.train_type
+            self.train_number, self.train_brand, self.carrier
         )?;
         write!(
             f,
@@ -335,7 +987,7 @@ impl fmt::Display for TrainInfo {
             "\tприбытие в \"{}\": {} {}\n",
             self.arriving_station, date, time
         )?;
-        let time = match self.trip_duration() {
+        let time = match self.duration() {
             Some(t) => format!("{}", t),
             None => String::new(),
         };
@@ -349,13 +1001,260 @@ impl fmt::Display for TrainInfo {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Main info about the train: where and when it goes, the lowest price and free seats.
+pub struct TrainSummary {
+    train_number: String,
+    leaving_station: String,
+    arriving_station: String,
+    leaving_date: Option<TrainDate>,
+    leaving_time: Option<TrainTime>,
+    arriving_date: Option<TrainDate>,
+    arriving_time: Option<TrainTime>,
+    duration: Option<TripDuration>,
+    min_price: Option<u32>,
+    free_seats: u32,
+}
+
+impl TrainSummary {
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the name of the departure station.
+    #[inline]
+    pub fn leaving_station(&self) -> &str {
+        &self.leaving_station
+    }
+
+    /// Returns the name of the arrival station.
+    #[inline]
+    pub fn arriving_station(&self) -> &str {
+        &self.arriving_station
+    }
+
+    /// Returns the departure date of the train.
+    #[inline]
+    pub fn leaving_date(&self) -> Option<TrainDate> {
+        self.leaving_date
+    }
+
+    /// Returns the departure time of the train.
+    #[inline]
+    pub fn leaving_time(&self) -> Option<TrainTime> {
+        self.leaving_time
+    }
+
+    /// Returns the arrival date of the train.
+    #[inline]
+    pub fn arriving_date(&self) -> Option<TrainDate> {
+        self.arriving_date
+    }
+
+    /// Returns the arrival time of the train.
+    #[inline]
+    pub fn arriving_time(&self) -> Option<TrainTime> {
+        self.arriving_time
+    }
+
+    /// Returns the duration of the trip including the trips longer than a day.
+    #[inline]
+    pub fn duration(&self) -> Option<TripDuration> {
+        self.duration
+    }
+
+    /// Returns the lowest price of the seats in rubles.
+    #[inline]
+    pub fn min_price(&self) -> Option<u32> {
+        self.min_price
+    }
+
+    /// Returns the number of free seats of all types.
+    #[inline]
+    pub fn free_seats(&self) -> u32 {
+        self.free_seats
+    }
+}
+
+impl fmt::Display for TrainSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let at = |date: Option<TrainDate>, time: Option<TrainTime>| {
+            let date = date.map(|d| d.to_string()).unwrap_or_default();
+            let time = time.map(|t| t.to_string()).unwrap_or_default();
+            format!("{} {}", date, time).trim().to_string()
+        };
+
+        write!(
+            f,
+            "№ {} \"{}\" {} - \"{}\" {}",
+            self.train_number,
+            self.leaving_station,
+            at(self.leaving_date, self.leaving_time),
+            self.arriving_station,
+            at(self.arriving_date, self.arriving_time)
+        )?;
+        if let Some(t) = self.duration {
+            write!(f, ", в пути {}", t)?;
+        }
+        if let Some(p) = self.min_price {
+            write!(f, ", от {} руб.", p)?;
+        }
+        if self.free_seats > 0 {
+            write!(f, ", мест: {}", self.free_seats)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize)]
+/// Free seats of one kind on the train and their lowest price.
+pub struct AvailabilityCell {
+    free_seats: u32,
+    min_price: Option<u32>,
+}
+
+impl AvailabilityCell {
+    /// Returns the number of free seats.
+    #[inline]
+    pub fn free_seats(&self) -> u32 {
+        self.free_seats
+    }
+
+    /// Returns the lowest price of the seats in rubles.
+    #[inline]
+    pub fn min_price(&self) -> Option<u32> {
+        self.min_price
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize)]
+/// Free seats of the trains by the kinds of the seats, the train number is the key
+/// of the rows and the name of the kind is the key of the cells.
+#[serde(transparent)]
+pub struct AvailabilityMatrix {
+    rows: BTreeMap<String, BTreeMap<String, AvailabilityCell>>,
+}
+
+impl AvailabilityMatrix {
+    /// Returns the seats of the train by the kinds.
+    pub fn train(&self, train_number: &str) -> Option<&BTreeMap<String, AvailabilityCell>> {
+        self.rows.get(train_number)
+    }
+
+    /// Returns the seats of the kind on the train.
+    pub fn get(&self, train_number: &str, kind: &SeatKind) -> Option<&AvailabilityCell> {
+        self.rows.get(train_number)?.get(&kind.to_string())
+    }
+
+    /// Returns the number of the trains.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if there are no trains.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the matrix as CSV with the `train,kind,free_seats,min_price` columns.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("train,kind,free_seats,min_price\n");
+        for (train, row) in self.rows.iter() {
+            for (kind, cell) in row.iter() {
+                let price = cell.min_price.map(|p| p.to_string()).unwrap_or_default();
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    csv_field(train),
+                    csv_field(kind),
+                    cell.free_seats,
+                    price
+                ));
+            }
+        }
+        csv
+    }
+}
+
+// Quotes the field if it contains a separator, a quote or a line break.
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Mode of the search for routes with transfers.
+pub enum TransferSearchMode {
+    /// Routes with transfers are searched automatically.
+    Auto,
+    /// Routes with transfers are offered if there are no direct trains.
+    SemiAuto,
+    /// Routes with transfers are not searched.
+    Manual,
+    /// The mode is unknown.
+    #[default]
+    Unknown,
+}
+
+impl From<&str> for TransferSearchMode {
+    // Takes the mode name used by RZD, e.g. "SEMI_AUTO".
+    fn from(s: &str) -> Self {
+        match s.trim().to_uppercase().as_str() {
+            "AUTO" => TransferSearchMode::Auto,
+            "SEMI_AUTO" => TransferSearchMode::SemiAuto,
+            "MANUAL" => TransferSearchMode::Manual,
+            _ => TransferSearchMode::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Options of the schedule search reported by the server.
+pub struct ScheduleOptions {
+    round_trip_bonus: bool,
+    transfer_search_mode: TransferSearchMode,
+    auto_transfer: bool,
+}
+
+impl ScheduleOptions {
+    /// Returns true if the round trip bonus applies.
+    #[inline]
+    pub fn round_trip_bonus(&self) -> bool {
+        self.round_trip_bonus
+    }
+
+    /// Returns the mode of the search for routes with transfers.
+    #[inline]
+    pub fn transfer_search_mode(&self) -> TransferSearchMode {
+        self.transfer_search_mode
+    }
+
+    /// Returns true if the server searched routes with transfers automatically.
+    #[inline]
+    pub fn auto_transfer(&self) -> bool {
+        self.auto_transfer
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 /// Info about the route and available trains.
 pub struct Route {
     leaving_name: String,
     leaving_code: RzdStationCode,
     arriving_name: String,
     arriving_code: RzdStationCode,
+    requested_date: Option<TrainDate>,
+    #[serde(default)]
+    no_seats: bool,
+    #[serde(default)]
+    options: ScheduleOptions,
+    #[serde(default)]
+    warnings: Warnings,
+    #[serde(default)]
+    errors: RzdErrors,
     trains: ResultList<TrainInfo>,
 }
 
@@ -384,6 +1283,36 @@ impl Route {
         self.arriving_code
     }
 
+    /// Returns the departure date the route is found for.
+    #[inline]
+    pub fn requested_date(&self) -> Option<TrainDate> {
+        self.requested_date
+    }
+
+    /// Returns true if the server reports no seats on the route.
+    #[inline]
+    pub fn no_seats(&self) -> bool {
+        self.no_seats
+    }
+
+    /// Returns the options of the schedule search the route is found with.
+    #[inline]
+    pub fn schedule_options(&self) -> ScheduleOptions {
+        self.options
+    }
+
+    /// Returns the advisory messages of the server about the found trains.
+    #[inline]
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+
+    /// Returns the errors of the server if no trains are found on the route.
+    #[inline]
+    pub fn errors(&self) -> &RzdErrors {
+        &self.errors
+    }
+
     /// Returns the immutable list of trains.
     #[inline]
     pub fn trains(&self) -> &ResultList<TrainInfo> {
@@ -404,7 +1333,7 @@ impl fmt::Display for Route {
             self.leaving_name, self.leaving_code, self.arriving_name, self.arriving_code
         )?;
         for t in self.trains.iter() {
-            write!(f, "{}\n", t)?;
+            write!(f, "{}\n", t.summary())?;
         }
         Ok(())
     }
@@ -414,15 +1343,23 @@ impl fmt::Display for Route {
 struct ScheduleReply(ReplyResult<Vec<Route>>);
 
 mod de {
-    use super::{RidReply, Route, ScheduleReply, SeatsInfo, TrainInfo};
+    use super::{
+        RidReply, Route, SaleState, ScheduleOptions, ScheduleReply, SeatsInfo, TrainInfo,
+        TransferSearchMode,
+    };
     use crate::client::RzdRequestId;
+    use crate::des::{des_null_to_default, des_price, Lenient};
     use crate::error::Error as GError;
     use crate::error::RzdErrors;
-    use crate::{ReplyResult, ResultList, RzdStationCode, TrainDate, TrainTime};
+    use crate::{parse_reply_time, TripDuration};
+    use crate::{
+        Carrier, ReplyResult, ResultList, RzdStationCode, SeatKind, TrainCategory, TrainDate,
+        TrainTime, TrainType, Warnings,
+    };
     use serde::Deserialize;
     type ReplyResultId = ReplyResult<RzdRequestId>;
     type ReplyResultRoutes = ReplyResult<Vec<Route>>;
-    use crate::{parse_train_date, parse_train_time};
+    use crate::parse_train_date;
 
     impl<'de> serde::Deserialize<'de> for RidReply {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -453,6 +1390,9 @@ mod de {
 
                 #[serde(default)]
                 tp: Vec<RzdMessList>,
+
+                #[serde(default)]
+                timestamp: String,
             }
 
             let input = RzdResult::deserialize(deserializer)?;
@@ -481,7 +1421,8 @@ mod de {
                         errors.append(&mut e);
                     }
 
-                    ReplyResultId::fail(GError::RzdError(RzdErrors::new(errors)))
+                    let errors = RzdErrors::new(errors).with_reply_timestamp(&input.timestamp);
+                    ReplyResultId::fail(GError::from(errors))
                 }
                 _ => ReplyResultId::fail(GError::FailRzdResponse),
             };
@@ -501,9 +1442,18 @@ mod de {
                 #[serde(default)]
                 car_type: String,
 
+                // The code of the seats, e.g. "dn", or the short name of the car type.
+                #[serde(rename = "type")]
+                #[serde(default)]
+                type_code: String,
+
                 #[serde(alias = "freeSeats")]
                 #[serde(default)]
                 free_seats: u32,
+
+                #[serde(default)]
+                #[serde(deserialize_with = "des_price")]
+                tariff: Option<u32>,
             }
 
             #[derive(Deserialize, Debug)]
@@ -511,12 +1461,40 @@ mod de {
                 #[serde(default)]
                 number: String,
 
+                #[serde(alias = "type")]
+                #[serde(default)]
+                train_kind: Option<u32>,
+
+                #[serde(alias = "typeEx")]
+                #[serde(default)]
+                type_ex: Option<u32>,
+
                 #[serde(default)]
                 brand: String,
 
                 #[serde(default)]
                 carrier: String,
 
+                // The category name of the suburban trains, e.g. "Экспресс".
+                #[serde(alias = "subtrainCatName")]
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                subtrain_category: String,
+
+                // The category name of the long-distance trains, e.g. "СК ФИРМ".
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                category: String,
+
+                #[serde(alias = "bFirm")]
+                #[serde(alias = "isFirm")]
+                #[serde(default)]
+                firm: bool,
+
+                #[serde(alias = "isExpress")]
+                #[serde(default)]
+                express: bool,
+
                 #[serde(default)]
                 route0: String,
 
@@ -531,6 +1509,12 @@ mod de {
                 #[serde(default)]
                 route_code1: RzdStationCode,
 
+                #[serde(default)]
+                code0: RzdStationCode,
+
+                #[serde(default)]
+                code1: RzdStationCode,
+
                 #[serde(default)]
                 station0: String,
 
@@ -557,8 +1541,18 @@ mod de {
                 #[serde(default)]
                 st_list: String,
 
+                #[serde(alias = "stListX")]
+                #[serde(default)]
+                st_list_x: String,
+
+                #[serde(default)]
+                depth: Option<u32>,
+
+                #[serde(default)]
+                train_id: Option<u64>,
+
                 #[serde(default)]
-                cars: Vec<Car>,
+                cars: Lenient<Car>,
             }
 
             #[derive(Deserialize, Debug)]
@@ -579,6 +1573,14 @@ mod de {
                 #[serde(default)]
                 to_code: RzdStationCode,
 
+                #[serde(default)]
+                date: String,
+
+                #[serde(alias = "noSeats")]
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                no_seats: bool,
+
                 #[serde(default)]
                 list: Vec<Train>,
 
@@ -600,6 +1602,24 @@ mod de {
 
                 #[serde(default)]
                 tp: Vec<RzdRoute>,
+
+                #[serde(default)]
+                timestamp: String,
+
+                #[serde(alias = "flFPKRoundBonus")]
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                round_bonus: bool,
+
+                #[serde(alias = "TransferSearchMode")]
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                transfer_search_mode: String,
+
+                #[serde(alias = "AutoTransferMode")]
+                #[serde(default)]
+                #[serde(deserialize_with = "des_null_to_default")]
+                auto_transfer: bool,
             }
 
             let input = RzdResult::deserialize(deserializer)?;
@@ -624,58 +1644,129 @@ mod de {
                 }
             };
 
+            let options = ScheduleOptions {
+                round_trip_bonus: input.round_bonus,
+                transfer_search_mode: TransferSearchMode::from(input.transfer_search_mode.as_str()),
+                auto_transfer: input.auto_transfer,
+            };
+
             let mut routes: Vec<Route> = vec![];
             let mut errors: Vec<String> = vec![];
-            let mut is_error = false;
 
             for route_or_err in input.tp {
-                if is_error | route_or_err.list.is_empty() {
-                    let mut err_list: Vec<String> = route_or_err
-                        .messages
-                        .into_iter()
-                        .map(|m| {
-                            let mut m = m.message.trim();
-                            match m.strip_suffix(".") {
-                                Some(r) => m = r,
-                                None => {}
-                            }
-                            m.to_lowercase()
-                        })
-                        .filter(|m| !m.is_empty())
-                        .collect();
-
-                    errors.append(&mut err_list);
-
-                    is_error = true;
-                    continue;
-                }
+                let messages: Vec<String> = route_or_err
+                    .messages
+                    .into_iter()
+                    .map(|m| {
+                        let mut m = m.message.trim();
+                        match m.strip_suffix(".") {
+                            Some(r) => m = r,
+                            None => {}
+                        }
+                        m.to_lowercase()
+                    })
+                    .filter(|m| !m.is_empty())
+                    .collect();
+
+                // Messages along with the found trains are advisory only.
+                // A failed route is kept with its errors, so that other routes are not lost.
+                let (mut warnings, route_errors) = if route_or_err.list.is_empty() {
+                    errors.extend(messages.iter().cloned());
+                    (Warnings::default(), RzdErrors::new(messages))
+                } else {
+                    (Warnings::new(messages), RzdErrors::default())
+                };
+
+                // The server explains in the messages why there are no seats,
+                // e.g. "продажа откроется через 2 дня".
+                let sale_not_open = warnings.as_ref().iter().any(|m| {
+                    (m.contains("продаж") && m.contains("откро"))
+                        || (m.contains("sale") && m.contains("open"))
+                });
 
                 let mut trains: Vec<TrainInfo> = vec![];
                 for train in route_or_err.list {
+                    // Suburban trains have no seats info at all, so only a train
+                    // the server marks as long-distance is sold out without the seats.
+                    let kind = train.train_kind.or(train.type_ex);
+                    let sold_out = kind == Some(0)
+                        && train.cars.items.is_empty()
+                        && train.cars.errors.is_empty();
+
+                    warnings.0.extend(train.cars.errors.iter().map(|e| {
+                        format!(
+                            "поезд {}: пропущены некорректные места: {}",
+                            train.number, e
+                        )
+                    }));
+
                     let seats: Vec<SeatsInfo> = train
                         .cars
+                        .items
                         .into_iter()
                         .map(|c| SeatsInfo {
                             free_seats: c.free_seats,
+                            kind: SeatKind::from_code_or_name(&c.type_code, &c.car_type),
                             seats_type: c.car_type,
+                            price: c.tariff,
                         })
                         .collect();
+                    let sale_state = if seats.iter().any(|s| s.free_seats > 0) {
+                        SaleState::OnSale
+                    } else if sale_not_open {
+                        SaleState::NotYetOnSale
+                    } else if sold_out {
+                        SaleState::SoldOut
+                    } else {
+                        SaleState::Unknown
+                    };
                     let seats = ResultList::<SeatsInfo>(seats);
 
+                    let what = |value| format!("поезд {}, {}", train.number, value);
                     let date1 = parse_train_date!(train.date0);
-                    let time1 = parse_train_time!(train.time0);
+                    let time1 =
+                        parse_reply_time(&train.time0, &what("отправление"), &mut warnings.0);
                     let date2 = parse_train_date!(train.date1);
-                    let time2 = parse_train_time!(train.time1);
-                    let duration = parse_train_time!(train.trip_duration);
+                    let time2 = parse_reply_time(&train.time1, &what("прибытие"), &mut warnings.0);
+                    let full_duration: Option<TripDuration> = parse_reply_time(
+                        &train.trip_duration,
+                        &what("время в пути"),
+                        &mut warnings.0,
+                    );
+                    // The trips longer than a day don't fit in the time of day.
+                    let duration = full_duration
+                        .filter(|d| d.hours() < 24)
+                        .and_then(|d| TrainTime::try_new(d.hours(), d.minutes()).ok());
+
+                    // The suburban trains are described by the name of the category,
+                    // the long-distance ones by the name or the flags.
+                    let category = match TrainCategory::from(train.subtrain_category.as_str()) {
+                        TrainCategory::Unknown => {
+                            match TrainCategory::from(train.category.as_str()) {
+                                TrainCategory::Unknown if train.firm => TrainCategory::Firm,
+                                TrainCategory::Unknown if train.express => TrainCategory::Express,
+                                c => c,
+                            }
+                        }
+                        c => c,
+                    };
 
                     trains.push(TrainInfo {
                         train_number: train.number,
                         train_brand: train.brand,
-                        train_type: train.carrier,
+                        carrier: Carrier::from(train.carrier.as_str()),
+                        train_type: match kind {
+                            Some(0) => Some(TrainType::Train),
+                            Some(1) => Some(TrainType::ElectricTrain),
+                            _ => None,
+                        },
+                        category,
                         leaving_route: train.route0,
                         leaving_route_code: train.route_code0,
                         arriving_route: train.route1,
                         arriving_route_code: train.route_code1,
+                        leaving_code: train.code0,
+                        arriving_code: train.code1,
                         leaving_station: train.station0,
                         leaving_date: date1,
                         leaving_time: time1,
@@ -683,7 +1774,13 @@ mod de {
                         arriving_date: date2,
                         arriving_time: time2,
                         trip_duration: duration,
+                        duration: full_duration,
                         stops: train.st_list,
+                        stops_full: train.st_list_x,
+                        sale_depth: train.depth,
+                        sold_out,
+                        sale_state,
+                        train_id: train.train_id.filter(|id| *id != 0),
                         seats,
                     });
                 }
@@ -694,12 +1791,18 @@ mod de {
                     leaving_code: route_or_err.from_code,
                     arriving_name: route_or_err.to_name,
                     arriving_code: route_or_err.to_code,
+                    requested_date: parse_train_date!(route_or_err.date),
+                    no_seats: route_or_err.no_seats,
+                    options,
+                    warnings,
+                    errors: route_errors,
                     trains,
                 });
             }
 
-            let reply = if is_error {
-                ReplyResultRoutes::fail(GError::RzdError(RzdErrors::new(errors)))
+            let reply = if routes.iter().all(|r| r.trains.is_empty()) {
+                let errors = RzdErrors::new(errors).with_reply_timestamp(&input.timestamp);
+                ReplyResultRoutes::fail(GError::from(errors))
             } else {
                 ReplyResultRoutes::success(routes)
             };
@@ -711,11 +1814,225 @@ mod de {
 
 #[cfg(test)]
 mod tests {
-    use super::{RidReply, Route, ScheduleReply, SeatsInfo, TrainInfo};
-    use crate::client::RzdRequestId;
+    use super::{
+        csv_field, parse_schedule_reply, RidReply, Route, SaleState, ScheduleOptions,
+        ScheduleReply, SeatsInfo, StopPolicy, TrainInfo, TrainScheduleSearch, TransferSearchMode,
+    };
+    use super::{find_first_available_with, FIND_DELAY};
+    use crate::client::{RzdClientInterface, RzdRequestId};
+    use crate::clock::MockClock;
+    use crate::fixtures::{load_fixture, serve};
+    use crate::RouteList;
     use crate::{error::Error, RzdErrors};
     use crate::{parse_train_date, parse_train_time};
-    use crate::{ResultList, RzdStationCode, TrainDate, TrainTime};
+    use crate::{
+        Carrier, DayPart, Lang, ResultList, RzdStationCode, SeatKind, ShowSeats, TrainCategory,
+        TrainDate, TrainTime, TrainType, TripDuration, Warnings,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn find_first_available_test() {
+        // There are no trains on the dates, the third one is beyond the period of the sale.
+        let requests = Arc::new(Mutex::new(vec![]));
+        let log = requests.clone();
+        let clock = Arc::new(MockClock::default());
+        let options = serve(move |request| {
+            log.lock().unwrap().push(request.to_string());
+            let message = match request.contains("dt0=03.04.2022") {
+                true => "Дата отправления за пределами периода предварительной продажи",
+                false => "В указанную дату поезд не ходит",
+            };
+            format!(
+                r#"{{"result":"OK","tp":[{{"list":[],"msgList":[{{"message":"{}"}}]}}]}}"#,
+                message
+            )
+        })
+        .with_clock(clock.clone())
+        .with_sleeper(clock.clone());
+
+        let found = find_first_available_with(2004000, 2000000, (2022, 4, 1), 5, None, &options);
+        assert_eq!(found.unwrap(), None);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(clock.pauses(), vec![FIND_DELAY, FIND_DELAY]);
+    }
+
+    #[test]
+    fn show_seats_test() {
+        let q = TrainScheduleSearch::new(
+            RzdStationCode::new(2004000),
+            RzdStationCode::new(2000000),
+            TrainDate::new(2022, 4, 1),
+            TrainType::Train,
+            true,
+        );
+        assert!(q.request_id().contains("&checkSeats=1&"));
+
+        let q = q.with_show_seats(ShowSeats::All);
+        assert!(q.request_id().contains("&checkSeats=0&"));
+
+        let q = q.with_show_seats(ShowSeats::AllIncludingSoldOut);
+        assert!(q.request_id().contains("&checkSeats=0&withoutSeats=y&"));
+    }
+
+    #[test]
+    fn lang_test() {
+        let q = TrainScheduleSearch::new(2004000, 2000000, (2022, 4, 1), TrainType::Train, true);
+        assert!(q
+            .request_id()
+            .starts_with("https://pass.rzd.ru/timetable/public/ru?"));
+        assert!(!serde_json::to_string(&q).unwrap().contains("lang"));
+
+        let q = q.with_lang(Lang::En);
+        assert_eq!(q.lang(), Lang::En);
+        assert!(q
+            .request_id()
+            .starts_with("https://pass.rzd.ru/timetable/public/en?"));
+        assert!(q
+            .request_data(RzdRequestId::new(1))
+            .starts_with("https://pass.rzd.ru/timetable/public/en?"));
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TrainScheduleSearch>(&json).unwrap(),
+            q
+        );
+    }
+
+    #[test]
+    fn sort_by_duration_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let mut routes = parse_schedule_reply(&answer).unwrap().unwrap();
+
+        let train = &mut routes.0[0].trains.0[0];
+        assert_eq!(train.duration(), TripDuration::new(9, 57).ok());
+        // 01.04.2022 00:11 - 02.04.2022 10:08
+        train.duration = None;
+        train.arriving_date = Some(TrainDate::new(2022, 4, 2));
+        assert_eq!(train.duration(), TripDuration::new(33, 57).ok());
+        routes.0[0].trains.0[1].arriving_time = None;
+        routes.0[0].trains.0[1].duration = None;
+
+        routes.sort_by_duration();
+        let numbers: Vec<&str> = routes.0[0]
+            .trains()
+            .iter()
+            .map(|t| t.train_number())
+            .collect();
+        assert_eq!(numbers, ["725Ч", "119А", "713В"]);
+    }
+
+    #[test]
+    fn invalid_time_test() {
+        let answer = load_fixture("train_schedule")
+            .unwrap()
+            .replacen("\"time0\":\"00:11\"", "\"time0\":\"24:11\"", 1)
+            .replacen("\"timeInWay\":\"09:57\"", "\"timeInWay\":\"33:57\"", 1);
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+
+        assert_eq!(train.leaving_time(), None);
+        assert_eq!(train.trip_duration(), None);
+        assert_eq!(train.duration(), TripDuration::new(33, 57).ok());
+        assert!(train.to_string().contains("время в пути: 33:57"));
+        assert!(train.summary().to_string().contains("в пути 33:57"));
+        assert_eq!(
+            routes.as_ref()[0].warnings().as_ref(),
+            ["поезд 119А, отправление: некорректное время \"24:11\""]
+        );
+    }
+
+    #[test]
+    fn bucket_by_daypart_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let buckets = routes.bucket_by_daypart();
+
+        let numbers =
+            |part| -> Vec<&str> { buckets[&part].iter().map(|t| t.train_number()).collect() };
+        assert_eq!(
+            buckets.keys().copied().collect::<Vec<_>>(),
+            [DayPart::Night, DayPart::Afternoon]
+        );
+        assert_eq!(numbers(DayPart::Night), ["119А", "713В"]);
+        assert_eq!(numbers(DayPart::Afternoon), ["725Ч"]);
+    }
+
+    #[test]
+    fn booking_url_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+
+        assert_eq!(
+            train.booking_url().unwrap().as_str(),
+            "https://ticket.rzd.ru/searchresults/v/1/2004001/2001025/2022-04-01?train=119%D0%90"
+        );
+
+        let mut train = train.clone();
+        train.leaving_date = None;
+        assert_eq!(train.booking_url(), None);
+    }
+
+    #[test]
+    fn sold_out_deserialize_test() {
+        let answer = r#"{"result":"OK","tp":[{"from":"МОСКВА","fromCode":2000000,"where":"САНКТ-ПЕТЕРБУРГ","whereCode":2004000,"date":"01.04.2022","noSeats":false,"list":[{"number":"020У","type":0,"depth":89,"carrier":"ФПК","date0":"01.04.2022","time0":"00:43","date1":"01.04.2022","time1":"09:13","cars":[]}],"msgList":[]}]}"#;
+        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let answer = answer.0;
+
+        assert!(answer.success);
+        assert!(answer.value[0].trains().as_ref()[0].sold_out());
+        assert_eq!(
+            answer.value[0].trains().as_ref()[0].sale_state(),
+            SaleState::SoldOut
+        );
+
+        let answer = r#"{"result":"OK","tp":[{"from":"МОСКВА","fromCode":2000000,"where":"САНКТ-ПЕТЕРБУРГ","whereCode":2004000,"date":"01.07.2022","noSeats":false,"list":[{"number":"020У","type":0,"depth":89,"carrier":"ФПК","date0":"01.07.2022","time0":"00:43","date1":"01.07.2022","time1":"09:13","cars":[]}],"msgList":[{"message":"Продажа откроется через 2 дня."}]}]}"#;
+        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let train = &answer.0.value[0].trains().as_ref()[0];
+        assert_eq!(train.sale_state(), SaleState::NotYetOnSale);
+
+        let mut train = train.clone();
+        train.sale_state = SaleState::Unknown;
+        assert_eq!(train.sale_state_on((2022, 4, 3)), SaleState::Unknown);
+        assert_eq!(train.sale_state_on((2022, 4, 1)), SaleState::NotYetOnSale);
+
+        // The type of the train is unknown, so it isn't taken as sold out.
+        let answer = r#"{"result":"OK","tp":[{"from":"МОСКВА","fromCode":2000000,"where":"САНКТ-ПЕТЕРБУРГ","whereCode":2004000,"date":"01.04.2022","noSeats":false,"list":[{"number":"020У","depth":89,"carrier":"ФПК","date0":"01.04.2022","time0":"00:43","date1":"01.04.2022","time1":"09:13","cars":[]}],"msgList":[]}]}"#;
+        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let train = &answer.0.value[0].trains().as_ref()[0];
+        assert!(!train.sold_out());
+        assert_eq!(train.sale_state(), SaleState::Unknown);
+        assert_eq!(train.kind(), None);
+    }
+
+    #[test]
+    fn car_type_code_test() {
+        // The code of the car is not taken for the type.
+        let answer = r#"{"result":"OK","tp":[{"from":"МОСКВА","fromCode":2000000,"where":"САНКТ-ПЕТЕРБУРГ","whereCode":2004000,"date":"01.04.2022","noSeats":false,"list":[{"number":"020У","type":0,"date0":"01.04.2022","time0":"00:43","date1":"01.04.2022","time1":"09:13","cars":[{"code":"Люкс","type":"Плац","typeLoc":"Плацкартный","freeSeats":3,"tariff":2500}]}],"msgList":[]}]}"#;
+        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let seats = &answer.0.value[0].trains().as_ref()[0].seats().as_ref()[0];
+        assert_eq!(seats.kind(), &SeatKind::OpenBerth);
+        assert_eq!(seats.seats_type(), "Плацкартный");
+    }
+
+    #[test]
+    fn long_distance_category_test() {
+        let reply = |fields: &str| {
+            let answer = format!(
+                r#"{{"result":"OK","tp":[{{"from":"МОСКВА","fromCode":2000000,"where":"САНКТ-ПЕТЕРБУРГ","whereCode":2004000,"date":"01.04.2022","noSeats":false,"list":[{{"number":"020У","type":0,{}"date0":"01.04.2022","time0":"00:43","date1":"01.04.2022","time1":"09:13","cars":[]}}],"msgList":[]}}]}}"#,
+                fields
+            );
+            let answer: ScheduleReply = serde_json::from_str(&answer).unwrap();
+            answer.0.value[0].trains().as_ref()[0].category()
+        };
+
+        assert_eq!(reply(r#""category":"СК ФИРМ","#), TrainCategory::Firm);
+        assert_eq!(reply(r#""bFirm":true,"#), TrainCategory::Firm);
+        assert_eq!(reply(r#""isExpress":true,"#), TrainCategory::Express);
+        assert_eq!(reply(r#""category":"СК","#), TrainCategory::Fast);
+        assert_eq!(reply(""), TrainCategory::Unknown);
+    }
 
     #[test]
     fn rid_reply_deserialize_test() {
@@ -732,13 +2049,36 @@ mod tests {
         let answer: RidReply = serde_json::from_str(answer).unwrap();
         let answer = answer.0;
 
-        let data = RzdErrors::new(vec![
+        assert!(!answer.success);
+        assert_eq!(
+            answer.error.to_string(),
+            "дата отправления находится за пределами периода предварительной продажи; дата отправления находится за пределами периода 90 дней"
+        );
+        assert!(answer
+            .error
+            .message(Lang::En)
+            .starts_with("the date is beyond the 90-day advance sale period: дата"));
+        match answer.error {
+            Error::OutsideSaleHorizon {
+                max_days: Some(90),
+                ref source,
+            } => assert_eq!(source.clone().to_vec().len(), 2),
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+
+        let errors = RzdErrors::new(vec![
             "дата отправления находится за пределами периода предварительной продажи".to_string(),
-            "дата отправления находится за пределами периода 90 дней".to_string(),
         ]);
-
-        assert!(!answer.success);
-        assert_eq!(answer.error.to_string(), data.to_string());
+        assert!(matches!(
+            Error::from(errors),
+            Error::OutsideSaleHorizon { max_days: None, .. }
+        ));
+        let errors = RzdErrors::new(vec!["поезда не найдены".to_string()])
+            .with_reply_timestamp("02.04.2022 14:30:25.934");
+        match Error::from(errors) {
+            Error::RzdError(ref e) => assert_eq!(e.server_timestamp().unwrap().millis(), 934),
+            ref e => panic!("unexpected error: {:?}", e),
+        }
 
         let answer = r#"{"result":"RID","RID":17355769877,"timestamp":"02.04.2022 18:31:00.189"}"#;
         let answer: RidReply = serde_json::from_str(answer).unwrap();
@@ -752,8 +2092,8 @@ mod tests {
 
     #[test]
     fn train_list_deserialize_test() {
-        let answer = r#"{"result":"OK","tp":[{"from":"САНКТ-ПЕТЕРБУРГ","fromCode":2004000,"where":"МОСКВА","whereCode":2000000,"date":"01.04.2022","noSeats":false,"defShowTime":"local","state":"Trains","list":[{"number":"119А","number2":"119А","type":0,"typeEx":0,"depth":89,"new":false,"elReg":true,"deferredPayment":false,"varPrice":true,"code0":2004001,"code1":2001025,"bEntire":true,"trainName":"","brand":"","carrier":"ФПК","route0":"С-ПЕТЕР-ГЛ","route1":"БЕЛГОРОД","routeCode0":2004001,"routeCode1":2014370,"trDate0":"01.04.2022","trTime0":"00:11","station0":"САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)","station1":"МОСКВА ВК ВОСТОЧНЫЙ (ТПУ ЧЕРКИЗОВО)","date0":"01.04.2022","time0":"00:11","date1":"01.04.2022","time1":"10:08","timeInWay":"09:57","flMsk":3,"train_id":0,"cars":[{"carDataType":1,"itype":1,"type":"Плац","typeLoc":"Плацкартный","freeSeats":121,"pt":436,"tariff":1459,"servCls":"3Б"},{"carDataType":1,"itype":3,"type":"Сид","typeLoc":"Сидячий","freeSeats":106,"pt":237,"tariff":795,"servCls":"2С"},{"carDataType":1,"itype":4,"type":"Купе","typeLoc":"Купе","freeSeats":66,"pt":745,"tariff":2489,"servCls":"2К"},{"carDataType":1,"itype":4,"type":"Купе","typeLoc":"Купе","freeSeats":2,"pt":745,"tariff":1362,"servCls":"2К","disabledPerson":true}],"disabledType":true,"addCompLuggageNum":16,"addCompLuggage":true,"addHandLuggage":true},{"number":"713В","number2":"713В","type":0,"typeEx":0,"depth":89,"new":false,"elReg":true,"deferredPayment":false,"varPrice":true,"code0":2004006,"code1":2001025,"bEntire":true,"trainName":"","brandLogo":true,"brand":"СТРИЖ","brandId":19,"carrier":"ФПК","route0":"С-ПЕТ-ЛАД","route1":"САМАРА","routeCode0":2004006,"routeCode1":2024000,"trDate0":"01.04.2022","trTime0":"00:20","station0":"САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)","station1":"МОСКВА ВК ВОСТОЧНЫЙ (ТПУ ЧЕРКИЗОВО)","date0":"01.04.2022","time0":"00:20","date1":"01.04.2022","time1":"05:34","timeInWay":"05:14","flMsk":3,"train_id":0,"cars":[{"carDataType":1,"itype":6,"type":"Люкс","typeLoc":"СВ","freeSeats":48,"pt":802,"tariff":2679,"servCls":"1Е"},{"carDataType":1,"itype":3,"type":"Сид","typeLoc":"Сидячий","freeSeats":29,"pt":527,"tariff":1762,"servCls":"1Р"},{"carDataType":1,"itype":4,"type":"Купе","typeLoc":"Купе","freeSeats":51,"pt":679,"tariff":2269,"servCls":"2А"}],"addCompLuggage":false,"addHandLuggage":true},{"number":"725Ч","number2":"725Ч","type":0,"typeEx":0,"depth":89,"new":false,"elReg":true,"deferredPayment":false,"varPrice":false,"code0":2004001,"code1":2006004,"bEntire":true,"trainName":"","brandLogo":true,"brand":"ЛАСТОЧКА","brandId":13,"carrier":"ДОСС","route0":"С-ПЕТЕР-ГЛ","route1":"МОСКВА ОКТ","routeCode0":2004001,"routeCode1":2006004,"trDate0":"01.04.2022","trTime0":"15:16","station0":"САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)","station1":"МОСКВА ОКТЯБРЬСКАЯ (ЛЕНИНГРАДСКИЙ ВОКЗАЛ)","date0":"01.04.2022","time0":"15:16","date1":"01.04.2022","time1":"21:58","timeInWay":"06:42","flMsk":3,"train_id":0,"cars":[{"carDataType":1,"itype":3,"type":"Сид","typeLoc":"Сидячий","freeSeats":319,"pt":328,"tariff":1099,"servCls":"1П"},{"carDataType":1,"itype":3,"type":"Сид","typeLoc":"Сидячий","freeSeats":2,"pt":328,"tariff":660,"servCls":"2Ж","disabledPerson":true}],"seatCars":[{"carDataType":2,"servCls":"2Ж","tariff":"1099","tariff2":null,"itype":3,"type":"Сид","typeLoc":"Базовый","freeSeats":129},{"carDataType":2,"servCls":"2Ж","tariff":"660","tariff2":null,"itype":3,"type":"Сид","typeLoc":"Базовый","freeSeats":2,"disabledPerson":true},{"carDataType":2,"servCls":"2П","tariff":"1199","tariff2":null,"itype":3,"type":"Сид","typeLoc":"Эконом","freeSeats":180},{"carDataType":2,"servCls":"1П","tariff":"2060","tariff2":"2299","itype":3,"type":"Сид","typeLoc":"Бизнес класс","freeSeats":10}],"disabledType":true,"nonRefundable":true}],"msgList":[]}],"TransferSearchMode":"SEMI_AUTO","flFPKRoundBonus":false,"AutoTransferMode":false,"discounts":{},"timestamp":"20.03.2022 18:28:31.458"}"#;
-        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let answer = load_fixture("train_schedule").unwrap();
+        let answer: ScheduleReply = serde_json::from_str(&answer).unwrap();
         let answer = answer.0;
 
         let data = vec![Route {
@@ -761,15 +2101,28 @@ mod tests {
             leaving_code: RzdStationCode(2004000),
             arriving_name: "МОСКВА".to_string(),
             arriving_code: RzdStationCode(2000000),
+            requested_date: Some(TrainDate::new(2022, 4, 1)),
+            no_seats: false,
+            options: ScheduleOptions {
+                round_trip_bonus: false,
+                transfer_search_mode: TransferSearchMode::SemiAuto,
+                auto_transfer: false,
+            },
+            warnings: Warnings::default(),
+            errors: RzdErrors::default(),
             trains: ResultList::<TrainInfo>(vec![
                 TrainInfo {
                     train_number: "119А".to_string(),
                     train_brand: "".to_string(),
-                    train_type: "ФПК".to_string(),
+                    carrier: Carrier::Fpk,
+                    train_type: Some(TrainType::Train),
+                    category: TrainCategory::Unknown,
                     leaving_route: "С-ПЕТЕР-ГЛ".to_string(),
                     leaving_route_code: RzdStationCode::new(2004001),
                     arriving_route: "БЕЛГОРОД".to_string(),
                     arriving_route_code: RzdStationCode::new(2014370),
+                    leaving_code: RzdStationCode::new(2004001),
+                    arriving_code: RzdStationCode::new(2001025),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("00:11"),
@@ -777,34 +2130,52 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("10:08"),
                     trip_duration: parse_train_time!("09:57"),
+                    duration: "09:57".parse().ok(),
                     stops: String::new(),
+                    stops_full: String::new(),
+                    sale_depth: Some(89),
+                    sold_out: false,
+                    sale_state: SaleState::OnSale,
+                    train_id: None,
                     seats: ResultList::<SeatsInfo>(vec![
                         SeatsInfo {
                             free_seats: 121,
                             seats_type: "Плацкартный".to_string(),
+                            kind: SeatKind::OpenBerth,
+                            price: Some(1459),
                         },
                         SeatsInfo {
                             free_seats: 106,
                             seats_type: "Сидячий".to_string(),
+                            kind: SeatKind::Seat,
+                            price: Some(795),
                         },
                         SeatsInfo {
                             free_seats: 66,
                             seats_type: "Купе".to_string(),
+                            kind: SeatKind::Compartment,
+                            price: Some(2489),
                         },
                         SeatsInfo {
                             free_seats: 2,
                             seats_type: "Купе".to_string(),
+                            kind: SeatKind::Compartment,
+                            price: Some(1362),
                         },
                     ]),
                 },
                 TrainInfo {
                     train_number: "713В".to_string(),
                     train_brand: "СТРИЖ".to_string(),
-                    train_type: "ФПК".to_string(),
+                    carrier: Carrier::Fpk,
+                    train_type: Some(TrainType::Train),
+                    category: TrainCategory::Unknown,
                     leaving_route: "С-ПЕТ-ЛАД".to_string(),
                     leaving_route_code: RzdStationCode::new(2004006),
                     arriving_route: "САМАРА".to_string(),
                     arriving_route_code: RzdStationCode::new(2024000),
+                    leaving_code: RzdStationCode::new(2004006),
+                    arriving_code: RzdStationCode::new(2001025),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("00:20"),
@@ -812,30 +2183,46 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("05:34"),
                     trip_duration: parse_train_time!("05:14"),
+                    duration: "05:14".parse().ok(),
                     stops: String::new(),
+                    stops_full: String::new(),
+                    sale_depth: Some(89),
+                    sold_out: false,
+                    sale_state: SaleState::OnSale,
+                    train_id: None,
                     seats: ResultList::<SeatsInfo>(vec![
                         SeatsInfo {
                             free_seats: 48,
                             seats_type: "СВ".to_string(),
+                            kind: SeatKind::Luxury,
+                            price: Some(2679),
                         },
                         SeatsInfo {
                             free_seats: 29,
                             seats_type: "Сидячий".to_string(),
+                            kind: SeatKind::Seat,
+                            price: Some(1762),
                         },
                         SeatsInfo {
                             free_seats: 51,
                             seats_type: "Купе".to_string(),
+                            kind: SeatKind::Compartment,
+                            price: Some(2269),
                         },
                     ]),
                 },
                 TrainInfo {
                     train_number: "725Ч".to_string(),
                     train_brand: "ЛАСТОЧКА".to_string(),
-                    train_type: "ДОСС".to_string(),
+                    carrier: Carrier::Doss,
+                    train_type: Some(TrainType::Train),
+                    category: TrainCategory::Unknown,
                     leaving_route: "С-ПЕТЕР-ГЛ".to_string(),
                     leaving_route_code: RzdStationCode::new(2004001),
                     arriving_route: "МОСКВА ОКТ".to_string(),
                     arriving_route_code: RzdStationCode::new(2006004),
+                    leaving_code: RzdStationCode::new(2004001),
+                    arriving_code: RzdStationCode::new(2006004),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("15:16"),
@@ -843,15 +2230,25 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("21:58"),
                     trip_duration: parse_train_time!("06:42"),
+                    duration: "06:42".parse().ok(),
                     stops: String::new(),
+                    stops_full: String::new(),
+                    sale_depth: Some(89),
+                    sold_out: false,
+                    sale_state: SaleState::OnSale,
+                    train_id: None,
                     seats: ResultList::<SeatsInfo>(vec![
                         SeatsInfo {
                             free_seats: 319,
                             seats_type: "Сидячий".to_string(),
+                            kind: SeatKind::Seat,
+                            price: Some(1099),
                         },
                         SeatsInfo {
                             free_seats: 2,
                             seats_type: "Сидячий".to_string(),
+                            kind: SeatKind::Seat,
+                            price: Some(660),
                         },
                     ]),
                 },
@@ -864,8 +2261,8 @@ mod tests {
 
     #[test]
     fn train_electric_list_deserialize_test() {
-        let answer = r#"{"result":"OK","tp":[{"from":"САНКТ-ПЕТЕРБУРГ","fromCode":2004000,"where":"ПУПЫШЕВО","whereCode":2005283,"date":"01.04.2022","noSeats":false,"defShowTime":"local","state":"Trains","list":[{"number":"6201","number2":"6201","type":1,"typeEx":1,"subt":1,"subtrainCatName":"Пассажирский","elReg":false,"deferredPayment":false,"varPrice":false,"code0":2004001,"code1":2005283,"bEntire":true,"trainName":"","brand":"","carrier":"СЗППК","route0":"САНКТ-ПЕТЕРБУРГ-ГЛАВН.","route1":"ВОЛХОВСТРОЙ 1","routeCode0":2004001,"routeCode1":2004672,"station0":"САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)","station1":"ПУПЫШЕВО","date0":"01.04.2022","time0":"05:50","date1":"01.04.2022","time1":"07:53","timeInWay":"02:03","flMsk":3,"stList":"Везде, кроме: ОСТ.ПУНКТ 5 КМ, УСТЬ-ТОСНЕНСКАЯ, ОСТ.ПУНКТ 77 КМ","mvMode":"Ежедневно","chWarn":false,"relev":true,"onWay":false,"suburbanTrainName":null,"subTabloVisible":0,"train_id":2212797,"cars":[]},{"number":"6208","number2":"6208","type":1,"typeEx":1,"subt":1,"subtrainCatName":"Пассажирский","elReg":false,"deferredPayment":false,"varPrice":false,"code0":2004006,"code1":2005283,"bEntire":true,"trainName":"","brand":"","carrier":"СЗППК","route0":"САНКТ-ПЕТЕРБУРГ ЛАДОЖ.","route1":"ВОЛХОВСТРОЙ 1","routeCode0":2004006,"routeCode1":2004672,"station0":"САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)","station1":"ПУПЫШЕВО","date0":"01.04.2022","time0":"10:29","date1":"01.04.2022","time1":"12:36","timeInWay":"02:07","flMsk":3,"stList":"Везде","mvMode":"Ежедневно","chWarn":false,"relev":true,"onWay":false,"suburbanTrainName":null,"subTabloVisible":0,"train_id":2213151,"cars":[]},{"number":"7406","number2":"7406","type":1,"typeEx":1,"subt":2,"subtrainCatName":"Экспресс","elReg":false,"deferredPayment":false,"varPrice":false,"code0":2004006,"code1":2005283,"bEntire":true,"trainName":"","brand":"","carrier":"СЗППК","route0":"САНКТ-ПЕТЕРБУРГ ЛАДОЖ.","route1":"ТИХВИН","routeCode0":2004006,"routeCode1":2004669,"station0":"САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)","station1":"ПУПЫШЕВО","date0":"01.04.2022","time0":"18:51","date1":"01.04.2022","time1":"20:28","timeInWay":"01:37","flMsk":3,"stList":"МГА, ЖИХАРЕВО, ПУПЫШЕВО","mvMode":"Кроме субботы","chWarn":false,"relev":true,"onWay":false,"suburbanTrainName":"Ласточка","subTabloVisible":0,"train_id":2205039,"cars":[]},{"number":"6218","number2":"6218","type":1,"typeEx":1,"subt":1,"subtrainCatName":"Пассажирский","elReg":false,"deferredPayment":false,"varPrice":false,"code0":2004006,"code1":2005283,"bEntire":true,"trainName":"","brand":"","carrier":"СЗППК","route0":"САНКТ-ПЕТЕРБУРГ ЛАДОЖ.","route1":"ВОЛХОВСТРОЙ 1","routeCode0":2004006,"routeCode1":2004672,"station0":"САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)","station1":"ПУПЫШЕВО","date0":"01.04.2022","time0":"21:33","date1":"01.04.2022","time1":"23:31","timeInWay":"01:58","flMsk":3,"stList":"ОСТ.ПУНКТ 5 КМ, ОСТ.ПУНКТ 7 КМ, МЯГЛОВО, ОСТ.ПУНКТ 11 КМ","stListX":"ОСТ.ПУНКТ 5 КМ, ОСТ.ПУНКТ 7 КМ, МЯГЛОВО, ОСТ.ПУНКТ 11 КМ, КОЛТУШИ, ОСТ.ПУНКТ 16 КМ, МАНУШКИНО, ОСТ.ПУНКТ 20 КМ, ОСТРОВКИ, ОСТ.ПУНКТ 26 КМ, ГЕРОЙСКАЯ, ПАВЛОВО НА НЕВЕ, ГОРЫ, ОСТ.ПУНКТ 45 КМ, МГА, МИХАЙЛОВСКАЯ, АПРАКСИН, ОСТ.ПУНКТ 63 КМ, НАЗИЯ, ЖИХАРЕВО, ВОЙБОКАЛО, ОСТ.ПУНКТ 95КМ, НОВЫЙ БЫТ, ОСТ.ПУНКТ 106 КМ, ПУПЫШЕВО","mvMode":"Ежедневно","chWarn":false,"relev":true,"onWay":false,"suburbanTrainName":null,"subTabloVisible":0,"train_id":2213204,"cars":[]}],"msgList":[]}],"TransferSearchMode":"SEMI_AUTO","flFPKRoundBonus":false,"AutoTransferMode":false,"discounts":{},"timestamp":"20.03.2022 21:21:54.543"}"#;
-        let answer: ScheduleReply = serde_json::from_str(answer).unwrap();
+        let answer = load_fixture("train_schedule_electric").unwrap();
+        let answer: ScheduleReply = serde_json::from_str(&answer).unwrap();
         let answer = answer.0;
 
         let data = vec![Route {
@@ -873,15 +2270,28 @@ mod tests {
             leaving_code: RzdStationCode(2004000),
             arriving_name: "ПУПЫШЕВО".to_string(),
             arriving_code: RzdStationCode(2005283),
+            requested_date: Some(TrainDate::new(2022, 4, 1)),
+            no_seats: false,
+            options: ScheduleOptions {
+                round_trip_bonus: false,
+                transfer_search_mode: TransferSearchMode::SemiAuto,
+                auto_transfer: false,
+            },
+            warnings: Warnings::default(),
+            errors: RzdErrors::default(),
             trains: ResultList::<TrainInfo>(vec![
                 TrainInfo {
                     train_number: "6201".to_string(),
                     train_brand: "".to_string(),
-                    train_type: "СЗППК".to_string(),
+                    carrier: Carrier::Szppk,
+                    train_type: Some(TrainType::ElectricTrain),
+                    category: TrainCategory::Passenger,
                     leaving_route: "САНКТ-ПЕТЕРБУРГ-ГЛАВН.".to_string(),
                     leaving_route_code: RzdStationCode::new(2004001),
                     arriving_route: "ВОЛХОВСТРОЙ 1".to_string(),
                     arriving_route_code: RzdStationCode::new(2004672),
+                    leaving_code: RzdStationCode::new(2004001),
+                    arriving_code: RzdStationCode::new(2005283),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("05:50"),
@@ -889,18 +2299,28 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("07:53"),
                     trip_duration: parse_train_time!("02:03"),
+                    duration: "02:03".parse().ok(),
                     stops: "Везде, кроме: ОСТ.ПУНКТ 5 КМ, УСТЬ-ТОСНЕНСКАЯ, ОСТ.ПУНКТ 77 КМ"
                         .to_string(),
+                    stops_full: String::new(),
+                    sale_depth: None,
+                    sold_out: false,
+                    sale_state: SaleState::Unknown,
+                    train_id: Some(2212797),
                     seats: ResultList::<SeatsInfo>(vec![]),
                 },
                 TrainInfo {
                     train_number: "6208".to_string(),
                     train_brand: "".to_string(),
-                    train_type: "СЗППК".to_string(),
+                    carrier: Carrier::Szppk,
+                    train_type: Some(TrainType::ElectricTrain),
+                    category: TrainCategory::Passenger,
                     leaving_route: "САНКТ-ПЕТЕРБУРГ ЛАДОЖ.".to_string(),
                     leaving_route_code: RzdStationCode::new(2004006),
                     arriving_route: "ВОЛХОВСТРОЙ 1".to_string(),
                     arriving_route_code: RzdStationCode::new(2004672),
+                    leaving_code: RzdStationCode::new(2004006),
+                    arriving_code: RzdStationCode::new(2005283),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("10:29"),
@@ -908,17 +2328,27 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("12:36"),
                     trip_duration: parse_train_time!("02:07"),
+                    duration: "02:07".parse().ok(),
                     stops: "Везде".to_string(),
+                    stops_full: String::new(),
+                    sale_depth: None,
+                    sold_out: false,
+                    sale_state: SaleState::Unknown,
+                    train_id: Some(2213151),
                     seats: ResultList::<SeatsInfo>(vec![]),
                 },
                 TrainInfo {
                     train_number: "7406".to_string(),
                     train_brand: "".to_string(),
-                    train_type: "СЗППК".to_string(),
+                    carrier: Carrier::Szppk,
+                    train_type: Some(TrainType::ElectricTrain),
+                    category: TrainCategory::Express,
                     leaving_route: "САНКТ-ПЕТЕРБУРГ ЛАДОЖ.".to_string(),
                     leaving_route_code: RzdStationCode::new(2004006),
                     arriving_route: "ТИХВИН".to_string(),
                     arriving_route_code: RzdStationCode::new(2004669),
+                    leaving_code: RzdStationCode::new(2004006),
+                    arriving_code: RzdStationCode::new(2005283),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("18:51"),
@@ -926,17 +2356,27 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("20:28"),
                     trip_duration: parse_train_time!("01:37"),
+                    duration: "01:37".parse().ok(),
                     stops: "МГА, ЖИХАРЕВО, ПУПЫШЕВО".to_string(),
+                    stops_full: String::new(),
+                    sale_depth: None,
+                    sold_out: false,
+                    sale_state: SaleState::Unknown,
+                    train_id: Some(2205039),
                     seats: ResultList::<SeatsInfo>(vec![]),
                 },
                 TrainInfo {
                     train_number: "6218".to_string(),
                     train_brand: "".to_string(),
-                    train_type: "СЗППК".to_string(),
+                    carrier: Carrier::Szppk,
+                    train_type: Some(TrainType::ElectricTrain),
+                    category: TrainCategory::Passenger,
                     leaving_route: "САНКТ-ПЕТЕРБУРГ ЛАДОЖ.".to_string(),
                     leaving_route_code: RzdStationCode::new(2004006),
                     arriving_route: "ВОЛХОВСТРОЙ 1".to_string(),
                     arriving_route_code: RzdStationCode::new(2004672),
+                    leaving_code: RzdStationCode::new(2004006),
+                    arriving_code: RzdStationCode::new(2005283),
                     leaving_station: "САНКТ-ПЕТЕРБУРГ (ЛАДОЖСКИЙ ВОКЗАЛ)".to_string(),
                     leaving_date: parse_train_date!("01.04.2022"),
                     leaving_time: parse_train_time!("21:33"),
@@ -944,7 +2384,13 @@ mod tests {
                     arriving_date: parse_train_date!("01.04.2022"),
                     arriving_time: parse_train_time!("23:31"),
                     trip_duration: parse_train_time!("01:58"),
+                    duration: "01:58".parse().ok(),
                     stops: "ОСТ.ПУНКТ 5 КМ, ОСТ.ПУНКТ 7 КМ, МЯГЛОВО, ОСТ.ПУНКТ 11 КМ".to_string(),
+                    stops_full: "ОСТ.ПУНКТ 5 КМ, ОСТ.ПУНКТ 7 КМ, МЯГЛОВО, ОСТ.ПУНКТ 11 КМ, КОЛТУШИ, ОСТ.ПУНКТ 16 КМ, МАНУШКИНО, ОСТ.ПУНКТ 20 КМ, ОСТРОВКИ, ОСТ.ПУНКТ 26 КМ, ГЕРОЙСКАЯ, ПАВЛОВО НА НЕВЕ, ГОРЫ, ОСТ.ПУНКТ 45 КМ, МГА, МИХАЙЛОВСКАЯ, АПРАКСИН, ОСТ.ПУНКТ 63 КМ, НАЗИЯ, ЖИХАРЕВО, ВОЙБОКАЛО, ОСТ.ПУНКТ 95КМ, НОВЫЙ БЫТ, ОСТ.ПУНКТ 106 КМ, ПУПЫШЕВО".to_string(),
+                    sale_depth: None,
+                    sold_out: false,
+                    sale_state: SaleState::Unknown,
+                    train_id: Some(2213204),
                     seats: ResultList::<SeatsInfo>(vec![]),
                 },
             ]),
@@ -953,4 +2399,218 @@ mod tests {
         assert!(answer.success);
         assert_eq!(answer.value, data);
     }
+
+    #[test]
+    fn from_rzd_json_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        assert!(matches!(RouteList::from_rzd_json(&answer), Ok(Some(_))));
+
+        assert!(matches!(
+            RouteList::from_rzd_json("not a json"),
+            Err(Error::DeserializeError(_))
+        ));
+    }
+
+    #[test]
+    fn suburban_details_test() {
+        let answer = load_fixture("train_schedule_electric").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+
+        let q = train.suburban_details().unwrap();
+        assert_eq!(q.train_number(), "6201");
+        assert_eq!(q.train_id(), Some(2212797));
+        assert!(q.request_id().contains("train_id=2212797"));
+
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+
+        assert_eq!(train.train_id(), None);
+        assert!(train.suburban_details().is_none());
+    }
+
+    #[test]
+    fn schedule_options_test() {
+        let answer = r#"{"result":"OK","tp":[{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"list":[{"number":"1"}],"msgList":[]}],"TransferSearchMode":"AUTO","flFPKRoundBonus":true,"AutoTransferMode":null}"#;
+        let routes = RouteList::from_rzd_json(answer).unwrap().unwrap();
+        let options = routes.schedule_options().unwrap();
+
+        assert!(options.round_trip_bonus());
+        assert_eq!(options.transfer_search_mode(), TransferSearchMode::Auto);
+        assert!(!options.auto_transfer());
+    }
+
+    #[test]
+    fn route_date_test() {
+        let answer = r#"{"result":"OK","tp":[{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"date":"01.04.2022","noSeats":false,"list":[{"number":"1"}]},{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"date":"02.04.2022","noSeats":true,"list":[{"number":"2"}]}]}"#;
+        let routes = RouteList::from_rzd_json(answer).unwrap().unwrap();
+        let routes = routes.as_ref();
+
+        assert_eq!(routes[0].requested_date(), Some(TrainDate::new(2022, 4, 1)));
+        assert!(!routes[0].no_seats());
+        assert_eq!(routes[1].requested_date(), Some(TrainDate::new(2022, 4, 2)));
+        assert!(routes[1].no_seats());
+    }
+
+    #[test]
+    fn partial_errors_test() {
+        let answer = r#"{"result":"OK","tp":[{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"list":[],"msgList":[{"message":"Поезда не найдены."}]},{"from":"B","fromCode":2000000,"where":"C","whereCode":2010000,"list":[{"number":"1"}],"msgList":[]}]}"#;
+        let routes = RouteList::from_rzd_json(answer).unwrap().unwrap();
+
+        assert_eq!(routes.as_ref().len(), 2);
+        assert!(routes.as_ref()[0].trains().is_empty());
+        assert_eq!(
+            routes.as_ref()[0].errors().clone().to_vec(),
+            ["поезда не найдены"]
+        );
+        assert!(!routes.as_ref()[1].trains().is_empty());
+        assert_eq!(
+            routes.partial_errors(),
+            Some(RzdErrors::new(vec!["поезда не найдены".to_string()]))
+        );
+
+        let answer = r#"{"result":"OK","tp":[{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"list":[],"msgList":[{"message":"Поезда не найдены."}]}]}"#;
+        assert!(RouteList::from_rzd_json(answer).is_err());
+    }
+
+    #[test]
+    fn warnings_test() {
+        let answer = r#"{"result":"OK","tp":[{"from":"A","fromCode":2004000,"where":"B","whereCode":2000000,"list":[{"number":"1"}],"msgList":[{"message":"Продажа откроется через 2 дня."}]}]}"#;
+        let routes = RouteList::from_rzd_json(answer).unwrap().unwrap();
+
+        assert_eq!(
+            routes.as_ref()[0].warnings(),
+            &Warnings::new(vec!["продажа откроется через 2 дня".to_string()])
+        );
+        assert!(routes.as_ref()[0].errors().is_empty());
+        assert_eq!(
+            routes.warnings().to_string(),
+            "продажа откроется через 2 дня"
+        );
+        assert_eq!(routes.partial_errors(), None);
+    }
+
+    #[test]
+    fn first_with_seats_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+
+        let train = routes.first_with_seats(None).unwrap();
+        assert_eq!(train.train_number(), "119А");
+
+        let train = routes.first_with_seats(Some(&SeatKind::Luxury)).unwrap();
+        assert_eq!(train.train_number(), "713В");
+
+        assert!(routes
+            .first_with_seats(Some(&SeatKind::Other("-".to_string())))
+            .is_none());
+    }
+
+    #[test]
+    fn availability_matrix_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let matrix = routes.availability_matrix();
+
+        assert_eq!(matrix.len(), 3);
+        let train = &routes.as_ref()[0].trains().as_ref()[0];
+        let seats = &train.seats().as_ref()[0];
+        let cell = matrix.get(train.train_number(), seats.kind()).unwrap();
+        assert_eq!(cell.free_seats(), seats.free_seats_number());
+        assert_eq!(cell.min_price(), seats.price());
+
+        let csv = matrix.to_csv();
+        assert!(csv.starts_with("train,kind,free_seats,min_price\n119А,"));
+        assert_eq!(
+            csv.lines().count(),
+            1 + matrix.rows.values().map(|r| r.len()).sum::<usize>()
+        );
+
+        let json = serde_json::to_value(&matrix).unwrap();
+        assert!(json["119А"].is_object());
+        assert_eq!(csv_field("a,\"b\""), r#""a,""b""""#);
+    }
+
+    #[test]
+    fn summary_test() {
+        let answer = load_fixture("train_schedule").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let summary = routes.as_ref()[0].trains().as_ref()[0].summary();
+
+        assert_eq!(summary.train_number(), "119А");
+        assert_eq!(summary.min_price(), Some(795));
+        assert_eq!(summary.free_seats(), 295);
+        assert_eq!(
+            routes.as_ref()[0].trains().as_ref()[0].total_free_seats(),
+            295
+        );
+        assert_eq!(
+            summary.to_string(),
+            "№ 119А \"САНКТ-ПЕТЕРБУРГ-ГЛАВН. (МОСКОВСКИЙ ВОКЗАЛ)\" 01.04.2022 00:11 - \"МОСКВА ВК ВОСТОЧНЫЙ (ТПУ ЧЕРКИЗОВО)\" 01.04.2022 10:08, в пути 09:57, от 795 руб., мест: 295"
+        );
+    }
+
+    #[test]
+    fn stop_policy_test() {
+        let except = "Везде, кроме: ОСТ.ПУНКТ 5 КМ, УСТЬ-ТОСНЕНСКАЯ, ОСТ.ПУНКТ 77 КМ";
+        let policy = StopPolicy::parse(except);
+        assert_eq!(
+            policy.stations(),
+            ["ОСТ.ПУНКТ 5 КМ", "УСТЬ-ТОСНЕНСКАЯ", "ОСТ.ПУНКТ 77 КМ"]
+        );
+        assert!(matches!(policy, StopPolicy::Except(_)));
+        assert_eq!(policy.to_string(), except);
+
+        assert_eq!(StopPolicy::parse(" Везде "), StopPolicy::Everywhere);
+        assert_eq!(StopPolicy::parse("Без остановок."), StopPolicy::NonStop);
+        assert_eq!(StopPolicy::parse(""), StopPolicy::Unknown);
+        assert_eq!(
+            StopPolicy::parse("МГА, ЖИХАРЕВО, ПУПЫШЕВО")
+                .stations()
+                .len(),
+            3
+        );
+
+        let answer = load_fixture("train_schedule_electric").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        for train in routes.as_ref()[0].trains().iter() {
+            assert_ne!(train.stop_policy(), StopPolicy::Unknown);
+        }
+    }
+
+    #[test]
+    fn stops_at_test() {
+        let answer = load_fixture("train_schedule_electric").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let trains = routes.as_ref()[0].trains().as_ref();
+        let stops_at =
+            |name: &str| -> Vec<Option<bool>> { trains.iter().map(|t| t.stops_at(name)).collect() };
+
+        // Везде, кроме; Везде; МГА, ЖИХАРЕВО, ПУПЫШЕВО; the full list.
+        assert_eq!(stops_at("Мга"), [Some(true); 4]);
+        assert_eq!(
+            stops_at("ост. пункт 5 км"),
+            [Some(false), Some(true), Some(false), Some(true)]
+        );
+        assert_eq!(
+            stops_at("Ост.пункт 95 км"),
+            [Some(true), Some(true), Some(false), Some(true)]
+        );
+        assert_eq!(stops_at("санкт-петербург"), [Some(true); 4]);
+        assert_eq!(stops_at("санкт петербург ладожский")[2], Some(true));
+        // Only the whole words of the long enough name match the beginning of the station.
+        assert_eq!(stops_at("С")[2], Some(false));
+        assert_eq!(stops_at("санкт-петер")[2], Some(false));
+        assert_eq!(stops_at("Санкт")[2], Some(true));
+        assert_eq!(stops_at("2005283"), [Some(true); 4]);
+        assert_eq!(stops_at("2000000"), [None; 4]);
+        assert_eq!(stops_at(" "), [None; 4]);
+
+        assert!(trains[3].stops_full().ends_with("ПУПЫШЕВО"));
+        assert_eq!(trains[0].stops_full_opt(), None);
+        assert_eq!(trains[1].stops_opt(), Some("Везде"));
+        assert_eq!(trains[0].brand_opt(), None);
+        assert_eq!(trains[3].stop_policy().stations().len(), 25);
+    }
 }
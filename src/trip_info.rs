@@ -1,22 +1,53 @@
-use reqwest::blocking::Response;
-use serde::Serialize;
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use url::form_urlencoded::byte_serialize;
 
-use crate::client::{RzdClientInterface, RzdQueryType, RzdRequestId};
-use crate::{error::Error, Result};
-use crate::{ReplyResult, ResultList, RzdStationCode, TrainDate, TrainTime};
+use crate::client::{
+    request_url, RzdClientInterface, RzdLayer, RzdQuery, RzdQueryType, RzdRequestId,
+};
+use crate::Url;
+use crate::{error::Error, error::GatewayInfo, Result};
+use crate::{Lang, ReplyResult, ResultList, RzdStationCode, TrainDate, TrainTime, Warnings};
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TripStopsSearchDef")]
 /// Train stops search.
 pub struct TripStopsSearch {
     train_number: String,
     train_date: TrainDate,
+    train_id: Option<u64>,
+    #[serde(skip_serializing_if = "crate::is_default_lang")]
+    lang: Lang,
+}
+
+// Raw fields of the search checked by `TripStopsSearch::new` on deserialization.
+#[derive(Deserialize)]
+struct TripStopsSearchDef {
+    train_number: String,
+    train_date: TrainDate,
+    #[serde(default)]
+    train_id: Option<u64>,
+    #[serde(default)]
+    lang: Lang,
+}
+
+impl TryFrom<TripStopsSearchDef> for TripStopsSearch {
+    type Error = Error;
+
+    fn try_from(d: TripStopsSearchDef) -> Result<Self> {
+        let search = TripStopsSearch::new(&d.train_number, d.train_date)?.with_lang(d.lang);
+        Ok(match d.train_id {
+            Some(id) => search.with_train_id(id),
+            None => search,
+        })
+    }
 }
 
 impl TripStopsSearch {
     /// Takes a number and a date of the train and creates a new search query.
-    pub fn new(train_number: &str, train_date: TrainDate) -> Result<Self> {
+    pub fn new(train_number: &str, train_date: impl Into<TrainDate>) -> Result<Self> {
         let train_number = train_number.trim().to_uppercase();
+        let train_date = train_date.into();
 
         if train_number.is_empty() {
             return Err(Error::EmptyTrainNumber);
@@ -26,8 +57,52 @@ impl TripStopsSearch {
         Ok(TripStopsSearch {
             train_number,
             train_date,
+            train_id: None,
+            lang: Lang::default(),
         })
     }
+
+    /// Sets the language of the names returned by the server, e.g. `Lang::En` for Latin names.
+    pub fn with_lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Sets the id of the suburban train to get its full stops and times.
+    pub fn with_train_id(mut self, train_id: u64) -> Self {
+        self.train_id = Some(train_id);
+        self
+    }
+
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the departure date of the train.
+    #[inline]
+    pub fn train_date(&self) -> TrainDate {
+        self.train_date
+    }
+
+    /// Returns the id of the suburban train.
+    #[inline]
+    pub fn train_id(&self) -> Option<u64> {
+        self.train_id
+    }
+
+    /// Returns the language of the names returned by the server.
+    #[inline]
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    /// Returns the URL of the first request made for the search to inspect the encoded parameters.
+    /// Fails with `Error::ParseUrlError` if the search makes an invalid URL.
+    pub fn request_url(&self) -> Result<Url> {
+        request_url::<TripStations, _>(self)
+    }
 }
 
 impl RzdClientInterface<TripStations> for TripStopsSearch {
@@ -35,33 +110,33 @@ impl RzdClientInterface<TripStations> for TripStopsSearch {
         RzdQueryType::WithId
     }
 
+    fn layer(&self) -> RzdLayer {
+        RzdLayer::TripStops
+    }
+
     fn request_id(&self) -> String {
-        let train_encoded: String = byte_serialize(self.train_number.as_bytes()).collect();
-
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5804\
-            &date={}\
-            &train_num={}\
-            &json=y\
-            &format=array",
-            self.train_date, train_encoded
-        )
+        let query = RzdQuery::new(RzdLayer::TripStops)
+            .lang(self.lang)
+            .param("date", self.train_date)
+            .param("train_num", &self.train_number);
+        let query = match self.train_id {
+            Some(id) => query.param("train_id", id),
+            None => query,
+        };
+        query.param("json", "y").param("format", "array").to_url()
     }
 
     fn request_data(&self, id: RzdRequestId) -> String {
-        format!(
-            "https://pass.rzd.ru/timetable/public/ru\
-            ?layer_id=5804\
-            &rid={}\
-            &json=y\
-            &format=array",
-            id
-        )
+        RzdQuery::new(RzdLayer::TripStops)
+            .lang(self.lang)
+            .param("rid", id)
+            .param("json", "y")
+            .param("format", "array")
+            .to_url()
     }
 
-    fn deserialize_reply_id(&self, response: Response) -> Result<Option<RzdRequestId>> {
-        let reply: RidReply = match response.json() {
+    fn deserialize_reply_id(&self, body: &str) -> Result<Option<RzdRequestId>> {
+        let reply: RidReply = match serde_json::from_str(body) {
             Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
             Ok(r) => r,
         };
@@ -75,35 +150,43 @@ impl RzdClientInterface<TripStations> for TripStopsSearch {
         Ok(Some(reply.value))
     }
 
-    fn deserialize_reply_data(&self, response: Response) -> Result<Option<TripStations>> {
-        let reply: TripInfoReply = match response.json() {
-            Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
-            Ok(r) => r,
-        };
-        let reply = reply.0;
-        trace!("reply: {:?}", reply);
-
-        if !reply.success {
-            return Err(reply.error);
-        }
+    fn deserialize_reply_data(&self, body: &str) -> Result<Option<TripStations>> {
+        parse_trip_reply(body)
+    }
+}
 
-        if reply.value.is_empty() {
-            return Ok(None);
-        }
+/// Parses a raw reply of the trip stops layer, e.g. a captured or logged payload.
+/// Returns a typed error for any malformed or failed reply, `None` if nothing is found.
+pub fn parse_trip_reply(body: &str) -> Result<Option<TripStations>> {
+    let reply: TripInfoReply = match serde_json::from_str(body) {
+        Err(e) => return Err(Error::DeserializeError(format!("{}", e))),
+        Ok(r) => r,
+    };
+    let reply = reply.0;
+    trace!("reply: {:?}", reply);
+
+    if !reply.success {
+        return Err(reply.error);
+    }
 
-        Ok(Some(reply.value))
+    if reply.value.is_empty() {
+        return Ok(None);
     }
+
+    Ok(Some(reply.value))
 }
 
 #[derive(Debug)]
 struct RidReply(ReplyResult<RzdRequestId>);
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Info about railway stops.
 pub struct TripStop {
     station: String,
     code: RzdStationCode,
     trip_days: u32,
+    #[serde(default)]
+    distance: Option<u32>,
     leaving_time: Option<TrainTime>,
     arriving_time: Option<TrainTime>,
 }
@@ -127,6 +210,12 @@ impl TripStop {
         self.trip_days
     }
 
+    /// Returns the distance from the first stop of the train in kilometers.
+    #[inline]
+    pub fn distance_km(&self) -> Option<u32> {
+        self.distance
+    }
+
     /// Returns the departure time of the train.
     #[inline]
     pub fn leaving_time(&self) -> Option<TrainTime> {
@@ -138,6 +227,81 @@ impl TripStop {
     pub fn arriving_time(&self) -> Option<TrainTime> {
         self.arriving_time
     }
+
+    /// Returns how many minutes the train stands at the stop,
+    /// `None` if the arrival or the departure time is unknown.
+    /// The stop over midnight is counted into the next day.
+    pub fn dwell_minutes(&self) -> Option<u32> {
+        let (arriving, leaving) = (self.arriving_time?, self.leaving_time?);
+        let minutes = (leaving.0 - arriving.0).num_minutes();
+        match minutes < 0 {
+            true => Some((minutes + 24 * 60) as u32),
+            false => Some(minutes as u32),
+        }
+    }
+
+    // Minutes from the midnight of the first day of the trip till the arrival.
+    fn arriving_minutes(&self) -> Option<u32> {
+        let time = self.arriving_time?.0;
+        Some(self.trip_days * 24 * 60 + time.num_seconds_from_midnight() / 60)
+    }
+
+    // Minutes from the midnight of the first day of the trip till the departure,
+    // the days of the trip are counted by the arrival.
+    fn leaving_minutes(&self) -> Option<u32> {
+        let time = self.leaving_time?.0;
+        let mut minutes = self.trip_days * 24 * 60 + time.num_seconds_from_midnight() / 60;
+        if self.arriving_time.is_some_and(|t| t.0 > time) {
+            minutes += 24 * 60;
+        }
+        Some(minutes)
+    }
+}
+
+/// Part of the trip between two neighbouring stops.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TripLeg<'a> {
+    from: &'a TripStop,
+    to: &'a TripStop,
+}
+
+impl<'a> TripLeg<'a> {
+    /// Returns the stop the train leaves.
+    #[inline]
+    pub fn from(&self) -> &'a TripStop {
+        self.from
+    }
+
+    /// Returns the stop the train arrives at.
+    #[inline]
+    pub fn to(&self) -> &'a TripStop {
+        self.to
+    }
+
+    /// Returns the distance between the stops in kilometers.
+    pub fn distance_km(&self) -> Option<u32> {
+        self.to.distance?.checked_sub(self.from.distance?)
+    }
+
+    /// Returns how many minutes the train goes from one stop to another one.
+    pub fn duration_minutes(&self) -> Option<u32> {
+        self.to
+            .arriving_minutes()?
+            .checked_sub(self.from.leaving_minutes()?)
+    }
+
+    /// Returns the average speed between the stops in km/h,
+    /// `None` if the distance or the times are unknown.
+    pub fn average_speed_kmh(&self) -> Option<f64> {
+        speed_kmh(self.distance_km()?, self.duration_minutes()?)
+    }
+}
+
+fn speed_kmh(distance_km: u32, minutes: u32) -> Option<f64> {
+    match minutes {
+        0 => None,
+        m => Some(distance_km as f64 * 60.0 / m as f64),
+    }
 }
 
 impl fmt::Display for TripStop {
@@ -158,20 +322,35 @@ impl fmt::Display for TripStop {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 /// Info about the stops the train makes.
 pub struct TripStations {
     train_number: String,
+    #[serde(default)]
+    gateway: GatewayInfo,
     stations: ResultList<TripStop>,
+    #[serde(default)]
+    warnings: Warnings,
 }
 
 impl TripStations {
+    /// Parses a raw reply of the trip stops, e.g. a logged one.
+    pub fn from_rzd_json(body: &str) -> Result<Option<Self>> {
+        parse_trip_reply(body)
+    }
+
     /// Returns the train number.
     #[inline]
     pub fn train_number(&self) -> &str {
         &self.train_number
     }
 
+    /// Returns the diagnostics of the gateway the reply is made by.
+    #[inline]
+    pub fn gateway(&self) -> &GatewayInfo {
+        &self.gateway
+    }
+
     /// Returns the immutable list of stopping stations.
     #[inline]
     pub fn stations(&self) -> &ResultList<TripStop> {
@@ -183,6 +362,125 @@ impl TripStations {
         &mut self.stations
     }
 
+    /// Returns the messages about the invalid times and days of the stops in the reply.
+    #[inline]
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+
+    /// Returns the stops from one station to another one including them,
+    /// the days of the trip are counted from the first of them.
+    /// The arrival at the first stop and the departure from the last one are dropped.
+    /// `None` is returned if the train doesn't go from the first station to the second one.
+    pub fn segment(
+        &self,
+        from_code: impl Into<RzdStationCode>,
+        to_code: impl Into<RzdStationCode>,
+    ) -> Option<TripStations> {
+        let (from_code, to_code) = (from_code.into(), to_code.into());
+        let stops = self.stations.as_ref();
+        let from = stops.iter().position(|s| s.code == from_code)?;
+        let to = from + stops[from..].iter().position(|s| s.code == to_code)?;
+        if from == to {
+            return None;
+        }
+
+        let days = stops[from].trip_days;
+        let distance = stops[from].distance;
+        let mut segment: Vec<TripStop> = stops[from..=to]
+            .iter()
+            .map(|s| TripStop {
+                trip_days: s.trip_days.saturating_sub(days),
+                distance: match (s.distance, distance) {
+                    (Some(d), Some(first)) => d.checked_sub(first),
+                    _ => None,
+                },
+                ..s.clone()
+            })
+            .collect();
+        segment[0].arriving_time = None;
+        segment[to - from].leaving_time = None;
+
+        Some(TripStations {
+            train_number: self.train_number.clone(),
+            gateway: self.gateway.clone(),
+            stations: ResultList::new(segment),
+            warnings: self.warnings.clone(),
+        })
+    }
+
+    /// Returns the stops where the train stands at least the number of minutes,
+    /// e.g. long enough to leave the car.
+    pub fn long_stops(&self, min_minutes: u32) -> Vec<&TripStop> {
+        self.stations
+            .iter()
+            .filter(|s| s.dwell_minutes().is_some_and(|m| m >= min_minutes))
+            .collect()
+    }
+
+    /// Returns the stops in the opposite direction, e.g. to show the way back.
+    /// The times and the days of the trip belong to this train only, so they are dropped,
+    /// the distances are counted from the other end.
+    pub fn reverse(&self) -> TripStations {
+        let total = self.distance_km();
+        let stops = self
+            .stations
+            .as_ref()
+            .iter()
+            .rev()
+            .map(|s| TripStop {
+                station: s.station.clone(),
+                code: s.code,
+                trip_days: 0,
+                distance: match (total, s.distance) {
+                    (Some(total), Some(d)) => total.checked_sub(d),
+                    _ => None,
+                },
+                leaving_time: None,
+                arriving_time: None,
+            })
+            .collect();
+
+        TripStations {
+            train_number: self.train_number.clone(),
+            gateway: self.gateway.clone(),
+            stations: ResultList::new(stops),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Returns the parts of the trip between the neighbouring stops.
+    pub fn legs(&self) -> Vec<TripLeg<'_>> {
+        self.stations
+            .as_ref()
+            .windows(2)
+            .map(|w| TripLeg {
+                from: &w[0],
+                to: &w[1],
+            })
+            .collect()
+    }
+
+    /// Returns the distance from the first stop to the last one in kilometers.
+    pub fn distance_km(&self) -> Option<u32> {
+        let stops = self.stations.as_ref();
+        stops
+            .last()?
+            .distance?
+            .checked_sub(stops.first()?.distance?)
+    }
+
+    /// Returns the average speed of the whole trip in km/h including the stops,
+    /// `None` if the distance or the times are unknown.
+    pub fn average_speed_kmh(&self) -> Option<f64> {
+        let stops = self.stations.as_ref();
+        let minutes = stops
+            .last()?
+            .arriving_minutes()?
+            .checked_sub(stops.first()?.leaving_minutes()?)?;
+        speed_kmh(self.distance_km()?, minutes)
+    }
+
     /// Returns true if the list of stops is empty.
     #[inline]
     fn is_empty(&self) -> bool {
@@ -196,6 +494,27 @@ impl TripStations {
             Err(_) => "{}".to_string(),
         }
     }
+
+    /// Performs the conversion into a YAML document.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the stops couldn't be serialized.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self).map_err(|e| Error::SerializeError(format!("{}", e)))
+    }
+
+    /// Performs the conversion into a TOML document, the stops are written as
+    /// the array of tables `[[stations]]`.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the stops couldn't be serialized.
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| Error::SerializeError(format!("{}", e)))
+    }
 }
 
 impl fmt::Display for TripStations {
@@ -215,12 +534,12 @@ mod de {
     use super::{RidReply, TripInfoReply, TripStations, TripStop};
     use crate::client::RzdRequestId;
     use crate::error::Error as GError;
-    use crate::error::RzdErrors;
-    use crate::{ReplyResult, ResultList, RzdStationCode, TrainTime};
+    use crate::error::{GatewayInfo, RzdErrors};
+    use crate::{ReplyResult, ResultList, RzdStationCode, Warnings};
     use serde::Deserialize;
     type ReplyResultId = ReplyResult<RzdRequestId>;
     type ReplyResultStations = ReplyResult<TripStations>;
-    use crate::parse_train_time;
+    use crate::parse_reply_time;
 
     impl<'de> serde::Deserialize<'de> for RidReply {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -276,6 +595,20 @@ mod de {
                 #[serde(alias = "Code")]
                 #[serde(default)]
                 code: RzdStationCode,
+
+                #[serde(alias = "Distance")]
+                #[serde(default)]
+                distance: serde_json::Value,
+            }
+
+            impl RzdStop {
+                fn distance(&self) -> Option<u32> {
+                    match &self.distance {
+                        serde_json::Value::Number(d) => d.as_u64().map(|d| d as u32),
+                        serde_json::Value::String(d) => d.trim().parse().ok(),
+                        _ => None,
+                    }
+                }
             }
 
             #[derive(Deserialize, Debug, Default)]
@@ -305,12 +638,50 @@ mod de {
                 #[serde(alias = "Error")]
                 #[serde(default)]
                 error: RzdError,
+
+                #[serde(alias = "ReqExpressZK")]
+                #[serde(default)]
+                request_number: Option<u64>,
+
+                #[serde(alias = "ReqAddress")]
+                #[serde(default)]
+                address: String,
+
+                #[serde(alias = "ReqLocalRecv")]
+                #[serde(default)]
+                local_recv: String,
+
+                #[serde(alias = "ReqLocalSend")]
+                #[serde(default)]
+                local_send: String,
+
+                #[serde(alias = "Version")]
+                #[serde(default)]
+                version: String,
             }
 
             #[derive(Deserialize, Debug, Default)]
             struct RzdError {
                 #[serde(default)]
                 content: String,
+
+                #[serde(alias = "Code")]
+                #[serde(default)]
+                code: serde_json::Value,
+
+                #[serde(alias = "Version")]
+                #[serde(default)]
+                version: String,
+            }
+
+            impl RzdError {
+                fn code(&self) -> Option<String> {
+                    match &self.code {
+                        serde_json::Value::Null => None,
+                        serde_json::Value::String(c) => Some(c.clone()),
+                        c => Some(c.to_string()),
+                    }
+                }
             }
 
             #[derive(Deserialize, Debug, Default)]
@@ -336,10 +707,19 @@ mod de {
                 )));
             }
 
+            let info = input.result;
+
             let mut error = input.error.content.trim().to_lowercase();
-            if error.is_empty() {
-                error = input.result.error.content.trim().to_lowercase();
-            }
+            let gateway = if error.is_empty() {
+                error = info.error.content.trim().to_lowercase();
+                let code = info.error.code();
+                GatewayInfo::new(info.request_number, info.address, info.version, code)
+                    .with_local_times(info.local_recv.parse().ok(), info.local_send.parse().ok())
+            } else {
+                // The error is returned by the gateway without the response.
+                let code = input.error.code();
+                GatewayInfo::new(None, String::new(), input.error.version, code)
+            };
 
             if !error.is_empty() {
                 match error.strip_suffix(".") {
@@ -347,27 +727,51 @@ mod de {
                     None => {}
                 }
 
-                let error = GError::RzdError(RzdErrors::new(vec![error]));
+                let error = GError::from(RzdErrors::new(vec![error]).with_gateway(gateway));
                 return Ok(TripInfoReply(ReplyResultStations::fail(error)));
             }
 
-            let stops: Vec<TripStop> = input
-                .result
+            let mut warnings: Vec<String> = vec![];
+            let stops: Vec<TripStop> = info
                 .routes
                 .stops
                 .into_iter()
-                .map(|s| TripStop {
-                    station: s.station,
-                    code: s.code,
-                    trip_days: s.days.trim().parse().unwrap_or_else(|_| 0),
-                    leaving_time: parse_train_time!(s.dep_time),
-                    arriving_time: parse_train_time!(s.arv_time),
+                .map(|s| {
+                    let what = |value| format!("остановка {}, {}", s.station, value);
+                    TripStop {
+                        distance: s.distance(),
+                        leaving_time: parse_reply_time(
+                            &s.dep_time,
+                            &what("отправление"),
+                            &mut warnings,
+                        ),
+                        arriving_time: parse_reply_time(
+                            &s.arv_time,
+                            &what("прибытие"),
+                            &mut warnings,
+                        ),
+                        trip_days: match s.days.trim().parse() {
+                            Ok(days) => days,
+                            Err(_) if s.days.trim().is_empty() => 0,
+                            Err(_) => {
+                                warnings.push(format!(
+                                    "остановка {}: некорректные сутки в пути \"{}\"",
+                                    s.station, s.days
+                                ));
+                                0
+                            }
+                        },
+                        station: s.station,
+                        code: s.code,
+                    }
                 })
                 .collect();
 
             let list = TripStations {
-                train_number: input.result.train.train_number,
+                train_number: info.train.train_number,
+                gateway,
                 stations: ResultList::new(stops),
+                warnings: Warnings::new(warnings),
             };
 
             Ok(TripInfoReply(ReplyResultStations::success(list)))
@@ -379,9 +783,11 @@ mod de {
 mod tests {
     use super::{RidReply, TripInfoReply, TripStations, TripStop};
     use crate::client::RzdRequestId;
+    use crate::error::GatewayInfo;
+    use crate::fixtures::load_fixture;
     use crate::parse_train_time;
     use crate::{error::Error, RzdErrors};
-    use crate::{ResultList, RzdStationCode, TrainTime};
+    use crate::{ResultList, RzdStationCode, TrainTime, Warnings};
 
     #[test]
     fn rid_reply_deserialize_test() {
@@ -437,17 +843,29 @@ mod tests {
         assert!(!answer.success);
         assert_eq!(answer.error.to_string(), data.to_string());
 
-        let answer = r#"{"GtExpress_Response":{"ReqExpressZK":3263309,"ReqLocalRecv":"20.03.2022 19:04:56","ReqLocalSend":"20.03.2022 19:04:56","ReqAddress":"MZD:5431","Train":{"Route":{"Station":["С-ПЕТЕР-ГЛ","МОСКВА ОКТ"],"CodeFrom":2004001,"CodeTo":2006004},"Number":"001А"},"Version":"2.7.81","Routes":{"Stop":[{"Station":"С-ПЕТЕР-ГЛ","Distance":0,"Days":"00","DepTime":"23:55","Code":2004001},{"ArvTime":"07:55","Station":"МОСКВА ОКТ","Distance":650,"Days":"01","Code":2006004}],"Title":"ОСНОВНОЙ МАРШРУТ","Route":{"Station":["С-ПЕТЕР-ГЛ","МОСКВА ОКТ"],"CodeFrom":2004001,"CodeTo":2006004}},"Type":"TrainRoute","ReqExpressDateTime":"20.03.2022 00:00"}}"#;
-        let answer: TripInfoReply = serde_json::from_str(answer).unwrap();
+        let answer = load_fixture("trip_info").unwrap();
+        let answer: TripInfoReply = serde_json::from_str(&answer).unwrap();
         let answer = answer.0;
 
         let data = TripStations {
             train_number: String::from("001А"),
+            gateway: GatewayInfo::new(
+                Some(3263309),
+                "MZD:5431".to_string(),
+                "2.7.81".to_string(),
+                None,
+            )
+            .with_local_times(
+                "20.03.2022 19:04:56".parse().ok(),
+                "20.03.2022 19:04:56".parse().ok(),
+            ),
+            warnings: Warnings::default(),
             stations: ResultList::new(vec![
                 TripStop {
                     station: String::from("С-ПЕТЕР-ГЛ"),
                     code: RzdStationCode::new(2004001),
                     trip_days: 0,
+                    distance: Some(0),
                     leaving_time: parse_train_time!("23:55"),
                     arriving_time: parse_train_time!(""),
                 },
@@ -455,6 +873,7 @@ mod tests {
                     station: String::from("МОСКВА ОКТ"),
                     code: RzdStationCode::new(2006004),
                     trip_days: 1,
+                    distance: Some(650),
                     leaving_time: parse_train_time!(""),
                     arriving_time: parse_train_time!("07:55"),
                 },
@@ -464,4 +883,203 @@ mod tests {
         assert!(answer.success);
         assert_eq!(answer.value, data);
     }
+
+    #[test]
+    fn segment_test() {
+        let stop = |station: &str, code, trip_days, arriving: &str, leaving: &str| TripStop {
+            station: station.to_string(),
+            code: RzdStationCode::new(code),
+            trip_days,
+            distance: None,
+            leaving_time: parse_train_time!(leaving),
+            arriving_time: parse_train_time!(arriving),
+        };
+        let trip = TripStations {
+            train_number: String::from("002Щ"),
+            gateway: GatewayInfo::default(),
+            warnings: Warnings::default(),
+            stations: ResultList::new(vec![
+                stop("МОСКВА ЯР", 2000002, 0, "", "13:45"),
+                stop("ЕКАТЕРИНБУРГ", 2030000, 1, "21:08", "21:38"),
+                stop("НОВОСИБИРСК", 2044001, 3, "01:26", "01:47"),
+                stop("ВЛАДИВОСТОК", 2034130, 6, "19:40", ""),
+            ]),
+        };
+
+        let segment = trip.segment(2030000, 2044001).unwrap();
+        assert_eq!(
+            segment.stations().as_ref(),
+            [
+                stop("ЕКАТЕРИНБУРГ", 2030000, 0, "", "21:38"),
+                stop("НОВОСИБИРСК", 2044001, 2, "01:26", ""),
+            ]
+        );
+        assert_eq!(segment.train_number(), "002Щ");
+
+        assert!(trip.segment(2044001, 2030000).is_none());
+        assert!(trip.segment(2030000, 2030000).is_none());
+        assert!(trip.segment(2030000, 2004001).is_none());
+
+        let back = trip.reverse();
+        let codes: Vec<u32> = back.stations().iter().map(|s| s.code().to_uint()).collect();
+        assert_eq!(codes, [2034130, 2044001, 2030000, 2000002]);
+        assert!(back.stations().iter().all(|s| s.leaving_time().is_none()));
+    }
+
+    #[test]
+    fn dwell_test() {
+        let stop = |station: &str, arriving: &str, leaving: &str| TripStop {
+            station: station.to_string(),
+            code: RzdStationCode::default(),
+            trip_days: 0,
+            distance: None,
+            leaving_time: parse_train_time!(leaving),
+            arriving_time: parse_train_time!(arriving),
+        };
+        let trip = TripStations {
+            train_number: String::from("002Щ"),
+            gateway: GatewayInfo::default(),
+            warnings: Warnings::default(),
+            stations: ResultList::new(vec![
+                stop("МОСКВА ЯР", "", "13:45"),
+                stop("ЕКАТЕРИНБУРГ", "21:08", "21:38"),
+                stop("НОВОСИБИРСК", "01:26", "01:47"),
+                stop("ТАЙГА", "23:50", "00:15"),
+                stop("ВЛАДИВОСТОК", "19:40", ""),
+            ]),
+        };
+
+        let dwell: Vec<Option<u32>> = trip.stations().iter().map(|s| s.dwell_minutes()).collect();
+        // The stop over midnight is counted into the next day.
+        assert_eq!(dwell, [None, Some(30), Some(21), Some(25), None]);
+
+        let stops: Vec<&str> = trip.long_stops(25).iter().map(|s| s.station()).collect();
+        assert_eq!(stops, ["ЕКАТЕРИНБУРГ", "ТАЙГА"]);
+        assert_eq!(trip.long_stops(20).len(), 3);
+        assert!(trip.long_stops(31).is_empty());
+    }
+
+    #[test]
+    fn average_speed_test() {
+        let answer = load_fixture("trip_info").unwrap();
+        let trip = TripStations::from_rzd_json(&answer).unwrap().unwrap();
+        assert_eq!(trip.distance_km(), Some(650));
+        assert_eq!(trip.average_speed_kmh(), Some(81.25));
+
+        let stop = |code, trip_days, distance, arriving: &str, leaving: &str| TripStop {
+            station: String::new(),
+            code: RzdStationCode::new(code),
+            trip_days,
+            distance,
+            leaving_time: parse_train_time!(leaving),
+            arriving_time: parse_train_time!(arriving),
+        };
+        let trip = TripStations {
+            train_number: String::from("002Щ"),
+            gateway: GatewayInfo::default(),
+            warnings: Warnings::default(),
+            stations: ResultList::new(vec![
+                stop(2000002, 0, Some(0), "", "13:45"),
+                stop(2030000, 1, Some(1814), "21:08", "21:38"),
+                stop(2044001, 3, Some(3303), "23:50", "00:15"),
+                stop(2034130, 6, None, "19:40", ""),
+            ]),
+        };
+
+        let legs = trip.legs();
+        assert_eq!(legs.len(), 3);
+        assert_eq!(legs[1].from().code().to_uint(), 2030000);
+        assert_eq!(legs[1].distance_km(), Some(1489));
+        assert_eq!(legs[1].duration_minutes(), Some(3012));
+        assert_eq!(legs[2].duration_minutes(), Some(4045));
+        assert_eq!(legs[2].average_speed_kmh(), None);
+        assert_eq!(trip.average_speed_kmh(), None);
+
+        let segment = trip.segment(2030000, 2044001).unwrap();
+        assert_eq!(segment.distance_km(), Some(1489));
+        assert_eq!(segment.stations().as_ref()[0].distance_km(), Some(0));
+        let speed = segment.average_speed_kmh().unwrap();
+        assert!((speed - 29.66).abs() < 0.01);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn to_yaml_test() {
+        let answer = load_fixture("trip_info").unwrap();
+        let trip = TripStations::from_rzd_json(&answer).unwrap().unwrap();
+        let yaml = trip.to_yaml().unwrap();
+
+        assert!(yaml.starts_with("train_number: 001А\n"));
+        assert!(yaml.contains("- station: С-ПЕТЕР-ГЛ\n"));
+        assert!(yaml.contains("  leaving_time: 23:55\n"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn to_toml_test() {
+        let answer = load_fixture("trip_info").unwrap();
+        let trip = TripStations::from_rzd_json(&answer).unwrap().unwrap();
+        let toml = trip.to_toml().unwrap();
+
+        assert!(toml.starts_with("train_number = \"001А\"\n"));
+        assert_eq!(toml.matches("[[stations]]").count(), 2);
+        assert!(toml.contains("arriving_time = \"07:55\"\n"));
+
+        let list = trip.stations().to_toml().unwrap();
+        assert_eq!(list.matches("[[items]]").count(), 2);
+    }
+
+    #[test]
+    fn from_rzd_json_test() {
+        let answer = load_fixture("trip_info").unwrap();
+        assert!(matches!(TripStations::from_rzd_json(&answer), Ok(Some(_))));
+
+        let answer = answer
+            .replace(r#""DepTime":"23:55""#, r#""DepTime":"25:61""#)
+            .replace(r#""Days":"01""#, r#""Days":"x""#);
+        let trip = TripStations::from_rzd_json(&answer).unwrap().unwrap();
+        assert_eq!(trip.stations().as_ref()[0].leaving_time(), None);
+        assert_eq!(trip.stations().as_ref()[1].trip_days(), 0);
+        assert_eq!(
+            trip.warnings().as_ref(),
+            [
+                "остановка С-ПЕТЕР-ГЛ, отправление: некорректное время \"25:61\"",
+                "остановка МОСКВА ОКТ: некорректные сутки в пути \"x\"",
+            ]
+        );
+
+        assert!(matches!(
+            TripStations::from_rzd_json("not a json"),
+            Err(Error::DeserializeError(_))
+        ));
+    }
+
+    #[test]
+    fn gateway_error_test() {
+        let answer = r#"{"GtExpress_Response":{"ExprInfo":"II","ReqExpressZK":3189015,"ReqAddress":"MZD:5433","ReqLocalRecv":"01.04.2022 13:58:10","ReqLocalSend":"01.04.2022 13:58:11","Error":{"content":"Неверная дата отправления.","Code":2010},"Version":"2.7.86","Type":"Train"}}"#;
+        let error = match TripStations::from_rzd_json(answer) {
+            Err(Error::RzdError(e)) => e,
+            r => panic!("unexpected result: {:?}", r),
+        };
+        let gateway = error.gateway().unwrap();
+
+        assert_eq!(gateway.request_number(), Some(3189015));
+        assert_eq!(gateway.address(), "MZD:5433");
+        assert_eq!(gateway.version(), "2.7.86");
+        assert_eq!(gateway.error_code(), Some("2010"));
+        assert_eq!(
+            gateway.received_at().map(|t| t.to_string()),
+            Some("01.04.2022 13:58:10.000".to_string())
+        );
+        assert_eq!(gateway.sent_at().unwrap().time().seconds(), 11);
+
+        let answer = r#"{"Error":{"Version":"2.7.81","content":"Parameter [Train::Number]: not found or invalid format","Code":"040311"}}"#;
+        let error = match TripStations::from_rzd_json(answer) {
+            Err(Error::RzdError(e)) => e,
+            r => panic!("unexpected result: {:?}", r),
+        };
+
+        assert_eq!(error.gateway().unwrap().error_code(), Some("040311"));
+        assert_eq!(error.gateway().unwrap().request_number(), None);
+    }
 }
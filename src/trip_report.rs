@@ -0,0 +1,255 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::client::{RequestOptions, RzdClient, SessionToken};
+use crate::error::Error;
+use crate::train_schedule::TrainInfo;
+use crate::{Result, ResultList, Route, RzdStationCode, TrainDate, TrainNumber, TrainType};
+use crate::{TrainItem, TrainScheduleSearch, TrainSearch, TripStations, TripStopsSearch};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The schedule row, the cars and the stops of one train.
+pub struct TripReport {
+    schedule_row: TrainInfo,
+    cars: Option<TrainItem>,
+    stops: Option<TripStations>,
+}
+
+impl TripReport {
+    /// Returns the train info from the schedule.
+    #[inline]
+    pub fn schedule_row(&self) -> &TrainInfo {
+        &self.schedule_row
+    }
+
+    /// Returns the cars and the free seats of the train, if the server returned them.
+    #[inline]
+    pub fn cars(&self) -> Option<&TrainItem> {
+        self.cars.as_ref()
+    }
+
+    /// Returns the stops of the train, if the server returned them.
+    #[inline]
+    pub fn stops(&self) -> Option<&TripStations> {
+        self.stops.as_ref()
+    }
+}
+
+impl fmt::Display for TripReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.schedule_row.summary())?;
+        if let Some(ref cars) = self.cars {
+            write!(f, "{}", cars)?;
+        }
+        if let Some(ref stops) = self.stops {
+            write!(f, "{}", stops)?;
+        }
+        Ok(())
+    }
+}
+
+impl RzdClient<TripReport> {
+    /// Finds the train in the schedule between the stations on the date
+    /// and gets its cars and stops within one session.
+    ///
+    /// The cars and the stops are got by the stations, the date and the number of the train
+    /// from the schedule, as the cities may be searched, and the number may be given
+    /// in Latin letters.
+    ///
+    /// Returns `None` if the train is not found in the schedule.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the train number is empty, there was an error while processing request
+    /// or received data couldn't be deserialized.
+    /// The errors of the server while getting the cars or the stops are skipped.
+    pub fn full_trip(
+        from: impl Into<RzdStationCode>,
+        to: impl Into<RzdStationCode>,
+        date: impl Into<TrainDate>,
+        train_number: &str,
+    ) -> Result<Option<TripReport>> {
+        RzdClient::<TripReport>::full_trip_with(
+            from,
+            to,
+            date,
+            train_number,
+            &RequestOptions::default(),
+        )
+    }
+
+    /// Does the same as `full_trip` making the requests with the options.
+    ///
+    /// # Errors
+    ///
+    /// The method fails if the train number is empty, there was an error while processing request
+    /// or received data couldn't be deserialized.
+    /// The errors of the server while getting the cars or the stops are skipped.
+    pub fn full_trip_with(
+        from: impl Into<RzdStationCode>,
+        to: impl Into<RzdStationCode>,
+        date: impl Into<TrainDate>,
+        train_number: &str,
+        options: &RequestOptions,
+    ) -> Result<Option<TripReport>> {
+        let (from, to, date) = (from.into(), to.into(), date.into());
+        let number = TrainNumber::parse(train_number)?;
+        let mut session = SessionToken::default();
+
+        let q = TrainScheduleSearch::new(from, to, date, TrainType::AllTrains, false);
+        let routes = match RzdClient::<ResultList<Route>>::get_with_session_options(
+            &q,
+            &mut session,
+            options,
+        )? {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+
+        let schedule_row = match routes
+            .iter()
+            .flat_map(|r| r.trains().iter())
+            .find(|t| is_same_number(t.train_number(), &number))
+        {
+            Some(t) => t.clone(),
+            None => return Ok(None),
+        };
+        let leaving_date = schedule_row.leaving_date().unwrap_or(date);
+
+        let cars = match schedule_row.leaving_time() {
+            Some(time) => {
+                let q = TrainSearch::new(
+                    schedule_row.leaving_station_code(),
+                    schedule_row.arriving_station_code(),
+                    leaving_date,
+                    time,
+                    schedule_row.train_number(),
+                )?;
+                skip_rzd_error(
+                    RzdClient::<ResultList<TrainItem>>::get_with_session_options(
+                        &q,
+                        &mut session,
+                        options,
+                    ),
+                )?
+                .and_then(|list| list.iter().next().cloned())
+            }
+            None => None,
+        };
+
+        let q = match schedule_row.suburban_details() {
+            Some(q) => q,
+            None => TripStopsSearch::new(schedule_row.train_number(), leaving_date)?,
+        };
+        let stops = skip_rzd_error(RzdClient::<TripStations>::get_with_session_options(
+            &q,
+            &mut session,
+            options,
+        ))?;
+
+        Ok(Some(TripReport {
+            schedule_row,
+            cars,
+            stops,
+        }))
+    }
+}
+
+// The numbers of the train are compared as parsed, i.e. ignoring case, spaces
+// and the Latin letters looking like the Cyrillic ones.
+fn is_same_number(a: &str, b: &TrainNumber) -> bool {
+    matches!(TrainNumber::parse(a), Ok(ref a) if a == b)
+}
+
+fn skip_rzd_error<T>(result: Result<Option<T>>) -> Result<Option<T>> {
+    match result {
+        Err(e) if matches!(e.root(), Error::RzdError(_)) => {
+            warn!("{}", e);
+            Ok(None)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_same_number, skip_rzd_error, TripReport};
+    use crate::clock::MockClock;
+    use crate::error::Error;
+    use crate::fixtures::{load_fixture, serve};
+    use crate::{RzdClient, RzdErrors, TrainNumber};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn helpers_test() {
+        let number = TrainNumber::parse("119А").unwrap();
+        assert!(is_same_number(" 119а", &number));
+        assert!(is_same_number("119A", &number));
+        assert!(!is_same_number("119", &number));
+        assert!(!is_same_number("", &number));
+
+        let result: crate::Result<Option<u32>> = Err(Error::RzdError(RzdErrors::new(vec![])));
+        assert_eq!(skip_rzd_error(result).unwrap(), None);
+        assert!(skip_rzd_error::<u32>(Err(Error::FailRzdResponse)).is_err());
+
+        let e = Error::RzdError(RzdErrors::new(vec![]));
+        let result: crate::Result<Option<u32>> =
+            Err(Error::Correlated("1".to_string(), Box::new(e)));
+        assert_eq!(skip_rzd_error(result).unwrap(), None);
+    }
+
+    #[test]
+    fn full_trip_test() {
+        // The schedule, the cars and the stops are got by the request ids 1, 2 and 3.
+        let requests = Arc::new(Mutex::new(vec![]));
+        let log = requests.clone();
+        let clock = Arc::new(MockClock::default());
+        let options = serve(move |request| {
+            log.lock().unwrap().push(request.to_string());
+            let fixture = match request {
+                r if r.contains("rid=1") => "train_schedule",
+                r if r.contains("rid=2") => "train_info",
+                r if r.contains("rid=3") => "trip_info",
+                r if r.contains("layer_id=5827") => return r#"{"result":"RID","RID":1}"#.into(),
+                r if r.contains("layer_id=5764") => return r#"{"result":"RID","RID":2}"#.into(),
+                _ => return r#"{"type":"REQUEST_ID","rid":3}"#.into(),
+            };
+            load_fixture(fixture).unwrap()
+        })
+        .with_clock(clock.clone())
+        .with_sleeper(clock);
+
+        // The cities are searched and the number is given in Latin letters.
+        let report = RzdClient::<TripReport>::full_trip_with(
+            2004000,
+            2000000,
+            (2022, 4, 1),
+            "119a",
+            &options,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(report.schedule_row().train_number(), "119А");
+        assert!(report.cars().is_some());
+        assert!(report.stops().is_some());
+
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 6);
+            let cars = requests.iter().find(|r| r.contains("tnum0=")).unwrap();
+            assert!(cars.contains("code0=2004001&"));
+            assert!(cars.contains("code1=2001025&"));
+            assert!(cars.contains("dt0=01.04.2022&"));
+            assert!(cars.contains("tnum0=119%D0%90"));
+        }
+
+        let missing = RzdClient::<TripReport>::full_trip_with(
+            2004000,
+            2000000,
+            (2022, 4, 1),
+            "001А",
+            &options,
+        );
+        assert_eq!(missing.unwrap(), None);
+    }
+}
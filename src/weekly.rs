@@ -0,0 +1,429 @@
+//! Timetable of the trains for the days of a week made from the daily schedules,
+//! e.g. to print the timetable of the suburban trains.
+
+use std::fmt;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Weekday};
+
+use crate::client::{request_target, RequestOptions};
+use crate::error::RzdErrorSet;
+use crate::scheduler::Scheduler;
+use crate::train_schedule::TrainInfo;
+use crate::TrainType;
+use crate::{Result, RouteList, RzdStationCode, TrainDate, TrainScheduleSearch, TrainTime};
+
+// Number of the parallel searches of `weekly_timetable`.
+const WEEKLY_WORKERS: usize = 2;
+// Pause between the searches of `weekly_timetable` not to overload the server.
+const WEEKLY_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Short Russian names of the days of the week.
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "пн",
+        Weekday::Tue => "вт",
+        Weekday::Wed => "ср",
+        Weekday::Thu => "чт",
+        Weekday::Fri => "пт",
+        Weekday::Sat => "сб",
+        Weekday::Sun => "вс",
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A run of the train on a day of the week.
+pub struct WeeklyRun {
+    date: TrainDate,
+    leaving_time: Option<TrainTime>,
+    arriving_time: Option<TrainTime>,
+}
+
+impl WeeklyRun {
+    /// Returns the date the train leaves on.
+    #[inline]
+    pub fn date(&self) -> TrainDate {
+        self.date
+    }
+
+    /// Returns the day of the week the train leaves on.
+    #[inline]
+    pub fn weekday(&self) -> Weekday {
+        self.date.0.weekday()
+    }
+
+    /// Returns the departure time.
+    #[inline]
+    pub fn leaving_time(&self) -> Option<TrainTime> {
+        self.leaving_time
+    }
+
+    /// Returns the arrival time.
+    #[inline]
+    pub fn arriving_time(&self) -> Option<TrainTime> {
+        self.arriving_time
+    }
+
+    // Returns true if the train leaves and arrives at the same times.
+    fn same_times(&self, other: &WeeklyRun) -> bool {
+        self.leaving_time == other.leaving_time && self.arriving_time == other.arriving_time
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The days of the week the train runs on with the times of every run.
+pub struct WeeklyTrain {
+    train_number: String,
+    leaving_code: RzdStationCode,
+    leaving_station: String,
+    arriving_code: RzdStationCode,
+    arriving_station: String,
+    runs: Vec<WeeklyRun>,
+}
+
+impl WeeklyTrain {
+    /// Returns the train number.
+    #[inline]
+    pub fn train_number(&self) -> &str {
+        &self.train_number
+    }
+
+    /// Returns the name of the departure station.
+    #[inline]
+    pub fn leaving_station(&self) -> &str {
+        &self.leaving_station
+    }
+
+    /// Returns the name of the arrival station.
+    #[inline]
+    pub fn arriving_station(&self) -> &str {
+        &self.arriving_station
+    }
+
+    /// Returns the runs of the train, from Monday to Sunday.
+    #[inline]
+    pub fn runs(&self) -> &[WeeklyRun] {
+        &self.runs
+    }
+
+    /// Returns the days of the week the train runs on, from Monday to Sunday.
+    pub fn weekdays(&self) -> Vec<Weekday> {
+        self.runs.iter().map(|r| r.weekday()).collect()
+    }
+
+    /// Returns true if the train runs on the day of the week.
+    pub fn runs_on(&self, day: Weekday) -> bool {
+        self.runs.iter().any(|r| r.weekday() == day)
+    }
+
+    /// Returns true if the train runs every day of the week.
+    pub fn is_daily(&self) -> bool {
+        self.runs.len() == 7
+    }
+
+    /// Returns true if the times of the train differ by the days.
+    pub fn times_vary(&self) -> bool {
+        match self.runs.first() {
+            Some(first) => self.runs.iter().any(|r| !r.same_times(first)),
+            None => false,
+        }
+    }
+
+    // Adds the run keeping the order of the days, the repeated day is replaced.
+    fn insert(&mut self, run: WeeklyRun) {
+        let day = run.weekday().num_days_from_monday();
+        match self
+            .runs
+            .binary_search_by_key(&day, |r| r.weekday().num_days_from_monday())
+        {
+            Ok(i) => self.runs[i] = run,
+            Err(i) => self.runs.insert(i, run),
+        }
+    }
+}
+
+impl fmt::Display for WeeklyTrain {
+    /// Writes the train with the days grouped by the times,
+    /// e.g. "№ 6201 САНКТ-ПЕТЕРБУРГ - ПУПЫШЕВО: пн, вт 21:33 - 23:31; сб 21:40 - 23:38".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = |t: Option<TrainTime>| t.map(|t| t.to_string()).unwrap_or_default();
+
+        write!(
+            f,
+            "№ {} {} - {}:",
+            self.train_number, self.leaving_station, self.arriving_station
+        )?;
+
+        let mut groups: Vec<(WeeklyRun, Vec<&str>)> = vec![];
+        for run in &self.runs {
+            match groups.iter_mut().find(|(r, _)| r.same_times(run)) {
+                Some((_, days)) => days.push(weekday_name(run.weekday())),
+                None => groups.push((*run, vec![weekday_name(run.weekday())])),
+            }
+        }
+
+        for (i, (run, days)) in groups.iter().enumerate() {
+            let sep = if i == 0 { " " } else { "; " };
+            write!(
+                f,
+                "{}{} {} - {}",
+                sep,
+                days.join(", "),
+                time(run.leaving_time),
+                time(run.arriving_time)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The trains of the week ordered by the departure time.
+pub struct WeeklyTimetable(Vec<WeeklyTrain>);
+
+impl WeeklyTimetable {
+    /// Makes the timetable of the schedules found for the dates,
+    /// the days of the week are taken from the departure dates of the trains.
+    pub fn from_days(days: &[(TrainDate, RouteList)]) -> Self {
+        let mut timetable = WeeklyTimetable::default();
+        for (date, routes) in days {
+            for train in routes.iter().flat_map(|r| r.trains().iter()) {
+                timetable.insert(train, *date);
+            }
+        }
+        timetable.0.sort_by_key(|t| {
+            t.runs
+                .iter()
+                .filter_map(|r| r.leaving_time)
+                .map(|t| t.0)
+                .min()
+        });
+        timetable
+    }
+
+    /// Returns the trains.
+    #[inline]
+    pub fn trains(&self) -> &[WeeklyTrain] {
+        &self.0
+    }
+
+    /// Returns the train by the number.
+    pub fn get(&self, train_number: &str) -> Option<&WeeklyTrain> {
+        self.0.iter().find(|t| t.train_number == train_number)
+    }
+
+    /// Returns the trains running on the day of the week.
+    pub fn on(&self, day: Weekday) -> impl Iterator<Item = &WeeklyTrain> {
+        self.0.iter().filter(move |t| t.runs_on(day))
+    }
+
+    // Adds the run of the train, the trains are told apart by the number and the stations.
+    fn insert(&mut self, train: &TrainInfo, date: TrainDate) {
+        let run = WeeklyRun {
+            date: train.leaving_date().unwrap_or(date),
+            leaving_time: train.leaving_time(),
+            arriving_time: train.arriving_time(),
+        };
+
+        let found = self.0.iter_mut().find(|t| {
+            t.train_number == train.train_number()
+                && t.leaving_code == train.leaving_station_code()
+                && t.arriving_code == train.arriving_station_code()
+        });
+        match found {
+            Some(t) => t.insert(run),
+            None => self.0.push(WeeklyTrain {
+                train_number: train.train_number().to_string(),
+                leaving_code: train.leaving_station_code(),
+                leaving_station: train.leaving_station().to_string(),
+                arriving_code: train.arriving_station_code(),
+                arriving_station: train.arriving_station().to_string(),
+                runs: vec![run],
+            }),
+        }
+    }
+}
+
+impl fmt::Display for WeeklyTimetable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for t in &self.0 {
+            writeln!(f, "{}", t)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Search of the trains between the stations on seven days in a row
+/// to make the timetable of the week.
+///
+/// ```rust,no_run
+/// # use rzd_trains::{RequestOptions, Scheduler, TrainType, WeeklySearch};
+/// # use std::time::Duration;
+/// #
+/// let search = WeeklySearch::new(2004001, 2005283, (2022, 4, 4), TrainType::ElectricTrain);
+///
+/// let scheduler = Scheduler::new(2, 7, Duration::from_secs(1), RequestOptions::new());
+/// let (timetable, errors) = search.run(&scheduler);
+/// print!("{}", timetable);
+/// ```
+pub struct WeeklySearch {
+    leaving_code: RzdStationCode,
+    arriving_code: RzdStationCode,
+    first_date: TrainDate,
+    train_type: TrainType,
+}
+
+impl WeeklySearch {
+    /// Takes departure and arrival station codes, the first date of the week
+    /// and the type of the trains. The codes of the cities may be passed
+    /// as to `TrainScheduleSearch`, the week is the first date and six following days.
+    pub fn new(
+        leaving_code: impl Into<RzdStationCode>,
+        arriving_code: impl Into<RzdStationCode>,
+        first_date: impl Into<TrainDate>,
+        train_type: TrainType,
+    ) -> Self {
+        WeeklySearch {
+            leaving_code: leaving_code.into(),
+            arriving_code: arriving_code.into(),
+            first_date: first_date.into(),
+            train_type,
+        }
+    }
+
+    /// Returns the dates to be searched, the first one and six following days.
+    pub fn dates(&self) -> Vec<TrainDate> {
+        (0..7)
+            .filter_map(|i| self.first_date.0.checked_add_days(Days::new(i)))
+            .map(TrainDate)
+            .collect()
+    }
+
+    /// Returns the daily searches, the sold out trains are searched too.
+    pub fn searches(&self) -> Vec<TrainScheduleSearch> {
+        self.dates()
+            .into_iter()
+            .map(|date| {
+                TrainScheduleSearch::new(
+                    self.leaving_code,
+                    self.arriving_code,
+                    date,
+                    self.train_type,
+                    false,
+                )
+            })
+            .collect()
+    }
+
+    /// Makes the searches by the scheduler, in parallel and with the pauses of the scheduler.
+    /// Returns the timetable of the days found and the errors
+    /// described by the URLs of their searches.
+    pub fn run(&self, scheduler: &Scheduler) -> (WeeklyTimetable, RzdErrorSet) {
+        let replies: Vec<(TrainScheduleSearch, Receiver<Result<Option<RouteList>>>)> = self
+            .searches()
+            .into_iter()
+            .map(|q| {
+                let rx = scheduler.enqueue::<RouteList, _>(q.clone(), 0);
+                (q, rx)
+            })
+            .collect();
+
+        let mut errors = RzdErrorSet::new();
+        let mut days: Vec<(TrainDate, RouteList)> = vec![];
+        for (q, rx) in replies {
+            match rx.recv() {
+                Ok(Ok(Some(routes))) => days.push((q.leaving_date(), routes)),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => errors.push(request_target::<RouteList, _>(&q), e),
+                // The scheduler is dropped before the search is made.
+                Err(_) => {}
+            }
+        }
+
+        (WeeklyTimetable::from_days(&days), errors)
+    }
+}
+
+/// Makes the timetable of the week starting on the date, see `WeeklySearch`.
+/// Two searches go in parallel with a pause of a second between them.
+pub fn weekly_timetable(
+    leaving_code: impl Into<RzdStationCode>,
+    arriving_code: impl Into<RzdStationCode>,
+    first_date: impl Into<TrainDate>,
+    train_type: TrainType,
+) -> (WeeklyTimetable, RzdErrorSet) {
+    let search = WeeklySearch::new(leaving_code, arriving_code, first_date, train_type);
+    let scheduler = Scheduler::new(
+        WEEKLY_WORKERS,
+        search.dates().len(),
+        WEEKLY_INTERVAL,
+        RequestOptions::new(),
+    );
+    search.run(&scheduler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WeeklyRun, WeeklySearch, WeeklyTimetable};
+    use crate::fixtures::load_fixture;
+    use crate::{parse_schedule_reply, TrainDate, TrainType};
+    use chrono::Weekday;
+
+    #[test]
+    fn searches_test() {
+        let search = WeeklySearch::new(2004001, 2005283, (2022, 3, 30), TrainType::ElectricTrain);
+        let dates: Vec<String> = search.dates().iter().map(|d| d.to_string()).collect();
+        assert_eq!(dates.first().unwrap(), "30.03.2022");
+        assert_eq!(dates.last().unwrap(), "05.04.2022");
+        assert_eq!(search.searches().len(), 7);
+        assert_eq!(
+            search.searches()[2].leaving_date(),
+            TrainDate::new(2022, 4, 1)
+        );
+    }
+
+    #[test]
+    fn timetable_test() {
+        let answer = load_fixture("train_schedule_electric").unwrap();
+        let routes = parse_schedule_reply(&answer).unwrap().unwrap();
+        let trains = routes.as_ref()[0].trains().as_ref().len();
+
+        // The trains of the fixture leave on Friday, 01.04.2022.
+        let mut timetable =
+            WeeklyTimetable::from_days(&[(TrainDate::new(2022, 4, 1), routes.clone())]);
+        assert_eq!(timetable.trains().len(), trains);
+        assert_eq!(timetable.on(Weekday::Fri).count(), trains);
+        assert_eq!(timetable.on(Weekday::Sat).count(), 0);
+
+        let first = timetable.trains()[0].clone();
+        let mut train = first.clone();
+        let run = first.runs()[0];
+        train.insert(WeeklyRun {
+            date: TrainDate::new(2022, 3, 28),
+            ..run
+        });
+        train.insert(WeeklyRun {
+            date: TrainDate::new(2022, 4, 2),
+            leaving_time: "21:40".parse().ok(),
+            ..run
+        });
+        assert_eq!(train.weekdays(), [Weekday::Mon, Weekday::Fri, Weekday::Sat]);
+        assert!(train.times_vary());
+        assert!(!first.times_vary());
+        assert!(!train.is_daily());
+
+        let text = train.to_string();
+        assert!(text.starts_with(&format!("№ {} ", train.train_number())));
+        assert!(text.contains(": пн, пт "));
+        assert!(text.contains("; сб 21:40 - "));
+
+        timetable.0[0] = train;
+        assert!(timetable
+            .get(first.train_number())
+            .unwrap()
+            .runs_on(Weekday::Mon));
+        assert_eq!(timetable.to_string().lines().count(), trains);
+    }
+}